@@ -1,7 +1,11 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use deployment::crd_manifest::CrdApiVersion;
+use deployment::quantity::{self, MemoryQuantity};
+use k8s_openapi::api::core::v1::Toleration;
 use names::Generator;
 use num::Num;
 use regex::Regex;
@@ -19,7 +23,9 @@ pub fn get_command() -> Result<Command, UserInputError> {
     } else if let Some(undeploy_args) = args.subcommand_matches("undeploy") {
         Ok(Command::DeleteCluster(existing_deployment(undeploy_args)))
     } else if let Some(ingress_args) = args.subcommand_matches("ingress") {
-        Ok(Command::Ingress(existing_deployment(ingress_args)))
+        Ok(Command::Ingress(existing_deployment(ingress_args), ingress(ingress_args)))
+    } else if let Some(crd_args) = args.subcommand_matches("crd") {
+        Ok(Command::PrintCrd(crd_api_version(crd_args)))
     } else {
         Result::Err(UserInputError::new(CommandErrorKind::UnknownCommand))
     };
@@ -46,6 +52,32 @@ fn new_deployment(deploy_args: &ArgMatches) -> UserNewClusterSpecification {
     let version: Option<String> = extract_string(deploy_args, "version");
     let custom_image: Option<String> = extract_string(deploy_args, "image");
     let custom_command: Option<String> = extract_string(deploy_args, "command");
+    let scheduling: UserSchedulingSpecification = scheduling(deploy_args);
+    let env: BTreeMap<String, String> = extract_key_values(deploy_args, "env");
+    let jvm_args: Vec<String> = extract_arguments(deploy_args, "jvm_arg");
+    let h2o_args: Vec<String> = extract_arguments(deploy_args, "h2o_arg");
+    let registry: UserRegistrySpecification = UserRegistrySpecification::new(
+        extract_string(deploy_args, "image_repository"),
+        deploy_args.values_of("image_pull_secret")
+            .map(|secrets| secrets.map(|secret| secret.to_string()).collect())
+            .unwrap_or_default(),
+        extract_string(deploy_args, "image_pull_policy"),
+    );
+    let resources: UserResourcesSpecification = UserResourcesSpecification::new(
+        extract_string(deploy_args, "cpu_request"),
+        extract_string(deploy_args, "memory_request"),
+        extract_string(deploy_args, "ephemeral_storage"),
+    );
+    let security: UserSecuritySpecification = UserSecuritySpecification::new(
+        extract_num(deploy_args, "run_as_user"),
+        deploy_args.is_present("writable_root_filesystem"),
+        extract_string(deploy_args, "service_account"),
+        deploy_args.is_present("create_service_account"),
+        extract_string(deploy_args, "auth_secret"),
+        // Arg has a default, it is therefore safe to unwrap.
+        extract_string(deploy_args, "auth_method").unwrap(),
+        extract_string(deploy_args, "keystore_secret"),
+    );
 
     UserNewClusterSpecification::new(
         deployment_name,
@@ -54,10 +86,35 @@ fn new_deployment(deploy_args: &ArgMatches) -> UserNewClusterSpecification {
         jvm_memory_percentage,
         memory,
         num_cpus,
+        resources,
         cluster_size,
         kubeconfig_path,
         custom_image,
         custom_command,
+        scheduling,
+        env,
+        jvm_args,
+        h2o_args,
+        registry,
+        security,
+    )
+}
+
+/// Extracts scheduling constraints of H2O pods from user's input into `UserSchedulingSpecification` struct.
+fn scheduling(deploy_args: &ArgMatches) -> UserSchedulingSpecification {
+    let tolerations: Vec<Toleration> = deploy_args.values_of("toleration")
+        .map(|tolerations| tolerations
+            // Tolerations are validated, it is therefore safe to unwrap.
+            .map(|toleration| parse_toleration(toleration).unwrap())
+            .collect())
+        .unwrap_or_default();
+
+    UserSchedulingSpecification::new(
+        extract_key_values(deploy_args, "node_selector"),
+        tolerations,
+        extract_string(deploy_args, "priority_class"),
+        extract_string(deploy_args, "spreading"),
+        extract_string(deploy_args, "spreading_topology"),
     )
 }
 
@@ -81,11 +138,31 @@ fn existing_deployment(args: &ArgMatches) -> UserExistingClusterSpecification {
     UserExistingClusterSpecification::new(name, namespace, kubeconfig_path)
 }
 
+/// Extracts ingress configuration from user's input into `UserIngressSpecification` struct.
+fn ingress(ingress_args: &ArgMatches) -> UserIngressSpecification {
+    UserIngressSpecification::new(
+        extract_string(ingress_args, "host"),
+        extract_string(ingress_args, "tls_secret"),
+        extract_string(ingress_args, "ingress_class"),
+        extract_key_values(ingress_args, "annotation"),
+    )
+}
+
+/// Extracts the API version of the H2O `CustomResourceDefinition` to print from user's input.
+fn crd_api_version(crd_args: &ArgMatches) -> CrdApiVersion {
+    // Possible values are enforced and the arg has a default, it is therefore safe to unwrap.
+    match extract_string(crd_args, "api_version").unwrap().as_str() {
+        "v1beta1" => CrdApiVersion::V1beta1,
+        _ => CrdApiVersion::V1,
+    }
+}
+
 /// Commands issuable by the user.
 pub enum Command {
     CreateCluster(UserNewClusterSpecification),
     DeleteCluster(UserExistingClusterSpecification),
-    Ingress(UserExistingClusterSpecification),
+    Ingress(UserExistingClusterSpecification, UserIngressSpecification),
+    PrintCrd(CrdApiVersion),
 }
 
 
@@ -97,10 +174,12 @@ pub struct UserNewClusterSpecification {
     pub namespace: Option<String>,
     /// Memory percentage to allocate by the JVM running H2O inside the docker container.
     pub memory_percentage: u8,
-    /// Total memory for each H2O node. Effectively a pod memory request and limit.
+    /// Total memory for each H2O node. Effectively a pod memory limit, and a request unless overridden by `resources`.
     pub memory: String,
-    /// Number of CPUs allocated for each H2O node. Effectively a pod CPU request and limit.
+    /// Number of CPUs allocated for each H2O node. Effectively a pod CPU limit, and a request unless overridden by `resources`.
     pub num_cpu: u32,
+    /// Optional requests lower than the limits and ephemeral storage of each H2O node.
+    pub resources: UserResourcesSpecification,
     /// Total count of H2O nodes inside the cluster created.
     pub num_h2o_nodes: u32,
     /// Kubeconfig - provided optionally. There are well-known standardized locations to look for Kubeconfig, therefore optional.
@@ -111,6 +190,18 @@ pub struct UserNewClusterSpecification {
     pub custom_image: Option<String>,
     /// Custom command for a custom Docker image, if defined. Otherwise noop.
     pub custom_command: Option<String>,
+    /// Constraints on Kubernetes nodes the H2O pods are scheduled on.
+    pub scheduling: UserSchedulingSpecification,
+    /// Additional environment variables of H2O containers.
+    pub env: BTreeMap<String, String>,
+    /// Additional arguments of the JVM running H2O.
+    pub jvm_args: Vec<String>,
+    /// Additional arguments of H2O.
+    pub h2o_args: Vec<String>,
+    /// Registry settings to pull the H2O image with.
+    pub registry: UserRegistrySpecification,
+    /// Overrides of the default security context of H2O pods.
+    pub security: UserSecuritySpecification,
 }
 
 impl UserNewClusterSpecification {
@@ -121,10 +212,17 @@ impl UserNewClusterSpecification {
         memory_percentage: u8,
         memory: String,
         num_cpu: u32,
+        resources: UserResourcesSpecification,
         num_h2o_nodes: u32,
         kubeconfig_path: Option<PathBuf>,
         custom_image: Option<String>,
         custom_command: Option<String>,
+        scheduling: UserSchedulingSpecification,
+        env: BTreeMap<String, String>,
+        jvm_args: Vec<String>,
+        h2o_args: Vec<String>,
+        registry: UserRegistrySpecification,
+        security: UserSecuritySpecification,
     ) -> Self {
         UserNewClusterSpecification {
             name,
@@ -133,10 +231,118 @@ impl UserNewClusterSpecification {
             memory_percentage,
             memory,
             num_cpu,
+            resources,
             num_h2o_nodes,
             kubeconfig_path,
             custom_image,
             custom_command,
+            scheduling,
+            env,
+            jvm_args,
+            h2o_args,
+            registry,
+            security,
+        }
+    }
+}
+
+/// Requests differing from the limits and ephemeral storage of H2O pods, based on user's input from the CLI
+pub struct UserResourcesSpecification {
+    /// Optional CPU request lower than the number of CPUs, e.g. `500m`.
+    pub cpu_request: Option<String>,
+    /// Optional memory request lower than the memory limit.
+    pub memory_request: Option<String>,
+    /// Optional ephemeral storage requested and limited for each H2O node.
+    pub ephemeral_storage: Option<String>,
+}
+
+impl UserResourcesSpecification {
+    pub fn new(cpu_request: Option<String>, memory_request: Option<String>, ephemeral_storage: Option<String>) -> Self {
+        UserResourcesSpecification {
+            cpu_request,
+            memory_request,
+            ephemeral_storage,
+        }
+    }
+}
+
+/// Settings of the registry to pull the H2O image from, based on user's input from the CLI
+pub struct UserRegistrySpecification {
+    /// Optional repository overriding the official H2O image repository. H2O version is used as a tag.
+    pub image_repository: Option<String>,
+    /// Names of secrets with registry credentials.
+    pub image_pull_secrets: Vec<String>,
+    /// Optional image pull policy - one of `Always`, `IfNotPresent` or `Never`.
+    pub image_pull_policy: Option<String>,
+}
+
+impl UserRegistrySpecification {
+    pub fn new(image_repository: Option<String>, image_pull_secrets: Vec<String>, image_pull_policy: Option<String>) -> Self {
+        UserRegistrySpecification {
+            image_repository,
+            image_pull_secrets,
+            image_pull_policy,
+        }
+    }
+}
+
+/// Overrides of the default security context of H2O pods, based on user's input from the CLI
+pub struct UserSecuritySpecification {
+    /// Optional user ID the H2O container runs as.
+    pub run_as_user: Option<i64>,
+    /// Whether the root filesystem of H2O containers is writable.
+    pub writable_root_filesystem: bool,
+    /// Optional name of an existing service account H2O pods run under.
+    pub service_account: Option<String>,
+    /// Whether a dedicated service account with RBAC permissions is created for H2O pods.
+    pub create_service_account: bool,
+    /// Optional name of a secret with H2O's login configuration. H2O requires no authentication if not specified.
+    pub auth_secret: Option<String>,
+    /// H2O login module - one of `hash`, `ldap` or `kerberos`. Noop unless `auth_secret` is specified.
+    pub auth_method: String,
+    /// Optional name of a secret with the keystore material to serve H2O over TLS with.
+    pub keystore_secret: Option<String>,
+}
+
+impl UserSecuritySpecification {
+    pub fn new(run_as_user: Option<i64>, writable_root_filesystem: bool, service_account: Option<String>,
+               create_service_account: bool, auth_secret: Option<String>, auth_method: String,
+               keystore_secret: Option<String>) -> Self {
+        UserSecuritySpecification {
+            run_as_user,
+            writable_root_filesystem,
+            service_account,
+            create_service_account,
+            auth_secret,
+            auth_method,
+            keystore_secret,
+        }
+    }
+}
+
+/// Scheduling constraints of H2O pods based on user's input from the CLI
+pub struct UserSchedulingSpecification {
+    /// Labels of Kubernetes nodes the H2O pods must be scheduled on.
+    pub node_selector: BTreeMap<String, String>,
+    /// Tolerations of H2O pods, allowing them to be scheduled on tainted Kubernetes nodes.
+    pub tolerations: Vec<Toleration>,
+    /// Optional name of the priority class of H2O pods.
+    pub priority_class: Option<String>,
+    /// How strictly the H2O pods are spread - one of `none`, `preferred` or `required`. No spreading is requested if not specified.
+    pub spreading: Option<String>,
+    /// Topology domain to spread the H2O pods across - one of `hostname` or `zone`. Only specified together with `spreading`.
+    pub spreading_topology: Option<String>,
+}
+
+impl UserSchedulingSpecification {
+    pub fn new(node_selector: BTreeMap<String, String>, tolerations: Vec<Toleration>, priority_class: Option<String>,
+               spreading: Option<String>, spreading_topology: Option<String>) -> Self {
+        UserSchedulingSpecification {
+            node_selector,
+            tolerations,
+            priority_class,
+            spreading,
+            spreading_topology,
         }
     }
 }
@@ -160,6 +366,30 @@ impl UserExistingClusterSpecification {
     }
 }
 
+/// Configuration of an ingress pointing to an existing deployment, based on user's input from the CLI
+pub struct UserIngressSpecification {
+    /// Optional hostname the ingress rule applies to.
+    pub host: Option<String>,
+    /// Optional name of a secret with TLS certificate and key to terminate TLS with.
+    pub tls_secret: Option<String>,
+    /// Optional ingress class of the ingress controller to handle the ingress.
+    pub ingress_class: Option<String>,
+    /// Additional ingress annotations.
+    pub annotations: BTreeMap<String, String>,
+}
+
+impl UserIngressSpecification {
+    pub fn new(host: Option<String>, tls_secret: Option<String>, ingress_class: Option<String>,
+               annotations: BTreeMap<String, String>) -> Self {
+        UserIngressSpecification {
+            host,
+            tls_secret,
+            ingress_class,
+            annotations,
+        }
+    }
+}
+
 /// Error while processing user input.
 #[derive(Debug)]
 pub struct UserInputError {
@@ -204,6 +434,63 @@ fn extract_string(args: &ArgMatches, arg_name: &str) -> Option<String> {
     };
 }
 
+/// Extracts all the 'key=value' pairs given by the user for a repeatable argument. The pairs are expected
+/// to be already validated by `validate_key_value`.
+fn extract_key_values(args: &ArgMatches, arg_name: &str) -> BTreeMap<String, String> {
+    return args.values_of(arg_name)
+        .map(|pairs| pairs
+            .filter_map(|pair| pair.find('=').map(|separator_index| pair.split_at(separator_index)))
+            .map(|(key, value)| (key.to_string(), value[1..].to_string()))
+            .collect())
+        .unwrap_or_default();
+}
+
+/// Extracts all the command line arguments given by the user for a repeatable argument. Each value is split
+/// on whitespace, e.g. `--h2o_arg "-log_level INFO"` yields two arguments.
+fn extract_arguments(args: &ArgMatches, arg_name: &str) -> Vec<String> {
+    return args.values_of(arg_name)
+        .map(|values| values
+            .flat_map(|value| value.split_whitespace())
+            .map(|argument| argument.to_string())
+            .collect())
+        .unwrap_or_default();
+}
+
+/// Parses a toleration in the `key[=value]:effect` format, the same format `kubectl taint` uses for taints.
+/// Without a value, the toleration tolerates any value of the taint with given key. The effect may be omitted
+/// together with the colon to tolerate all the effects.
+fn parse_toleration(input: &str) -> Result<Toleration, String> {
+    let (key_value, effect): (&str, Option<&str>) = match input.rfind(':') {
+        Some(separator_index) => (&input[..separator_index], Some(&input[separator_index + 1..])),
+        None => (input, None),
+    };
+    if let Some(effect) = effect {
+        if !["NoSchedule", "PreferNoSchedule", "NoExecute"].contains(&effect) {
+            return Result::Err(format!("Error: Unknown toleration effect '{}'. Expected one of NoSchedule, PreferNoSchedule or NoExecute.", effect));
+        }
+    }
+    let (key, value): (&str, Option<&str>) = match key_value.find('=') {
+        Some(separator_index) => (&key_value[..separator_index], Some(&key_value[separator_index + 1..])),
+        None => (key_value, None),
+    };
+    if key.is_empty() {
+        return Result::Err(format!("Error: '{}' is not in the 'key[=value]:effect' format.", input));
+    }
+
+    Result::Ok(Toleration {
+        key: Some(key.to_string()),
+        operator: Some(if value.is_some() { "Equal" } else { "Exists" }.to_string()),
+        value: value.map(|value| value.to_string()),
+        effect: effect.map(|effect| effect.to_string()),
+        toleration_seconds: None,
+    })
+}
+
+/// Validates user input to be a toleration in the `key[=value]:effect` format.
+fn validate_toleration(input: String) -> Result<(), String> {
+    return parse_toleration(&input).map(|_| ());
+}
+
 /// Contains definition of all commands, arguments, flags and the respective default values and descriptions
 /// This is the only source of truth for user-facing CLI.
 fn build_app<'a>() -> App<'a, 'a> {
@@ -241,14 +528,14 @@ fn build_app<'a>() -> App<'a, 'a> {
                 .long("memory_percentage")
                 .short("p")
                 .default_value("50")
-                .help("Memory percentage allocated by H2O inside the container. <0,100>. Defaults to 50% to make space for XGBoost.")
+                .help("Memory percentage allocated by H2O inside the container. <1,100>. Defaults to 50% to make space for XGBoost.")
                 .validator(self::validate_percentage))
             .arg(Arg::with_name("memory")
                 .long("memory")
                 .short("m")
                 .number_of_values(1)
                 .default_value("1Gi")
-                .help("Amount of memory allocated by each H2O node - in a format accepted by K8S, e.g. 4Gi.")
+                .help("Amount of memory allocated by each H2O node - in a format accepted by K8S, e.g. 4Gi. The JVM heap is derived from it.")
                 .validator(self::validate_memory))
             .arg(Arg::with_name("cpus")
                 .long("cpus")
@@ -256,6 +543,21 @@ fn build_app<'a>() -> App<'a, 'a> {
                 .default_value("1")
                 .help("Number of CPUs allocated for each H2O node.")
             )
+            .arg(Arg::with_name("memory_request")
+                .long("memory_request")
+                .number_of_values(1)
+                .help("Memory request of each H2O node, if lower than '--memory' - in a format accepted by K8S, e.g. 2Gi.")
+                .validator(self::validate_size))
+            .arg(Arg::with_name("cpu_request")
+                .long("cpu_request")
+                .number_of_values(1)
+                .help("CPU request of each H2O node, if lower than '--cpus' - in a format accepted by K8S, e.g. 500m.")
+                .validator(self::validate_cpu))
+            .arg(Arg::with_name("ephemeral_storage")
+                .long("ephemeral_storage")
+                .number_of_values(1)
+                .help("Ephemeral storage requested and limited for each H2O node, e.g. 10Gi. Unlimited if not specified.")
+                .validator(self::validate_size))
             .arg(Arg::with_name("version")
                 .short("v")
                 .long("version")
@@ -276,6 +578,122 @@ fn build_app<'a>() -> App<'a, 'a> {
                 .number_of_values(1)
                 .help("Custom command for to use for the custom docker image on startup.")
             )
+            .arg(Arg::with_name("image_repository")
+                .long("image_repository")
+                .number_of_values(1)
+                .conflicts_with("image")
+                .help("Repository of the official H2O images, e.g. a private mirror. H2O version is used as a tag. Defaults to 'h2oai/h2o-open-source-k8s'.")
+            )
+            .arg(Arg::with_name("image_pull_secret")
+                .long("image_pull_secret")
+                .multiple(true)
+                .number_of_values(1)
+                .help("Name of a secret with credentials to the registry with the H2O image. May be specified multiple times.")
+            )
+            .arg(Arg::with_name("image_pull_policy")
+                .long("image_pull_policy")
+                .number_of_values(1)
+                .possible_values(&["Always", "IfNotPresent", "Never"])
+                .help("Pull policy of the H2O image. Kubernetes defaults apply if not specified.")
+            )
+            .arg(Arg::with_name("run_as_user")
+                .long("run_as_user")
+                .number_of_values(1)
+                .validator(self::validate_user_id)
+                .help("User ID the H2O container runs as. Defaults to 1000. Custom images must support it.")
+            )
+            .arg(Arg::with_name("writable_root_filesystem")
+                .long("writable_root_filesystem")
+                .takes_value(false)
+                .help("Makes the root filesystem of H2O containers writable, e.g. for custom images writing outside of '/tmp'.")
+            )
+            .arg(Arg::with_name("service_account")
+                .long("service_account")
+                .number_of_values(1)
+                .conflicts_with("create_service_account")
+                .help("Name of an existing service account H2O pods run under. The namespace's default service account is used if not specified.")
+            )
+            .arg(Arg::with_name("create_service_account")
+                .long("create_service_account")
+                .takes_value(false)
+                .help("Creates a dedicated service account, role and role binding with the permissions H2O needs. Removed on undeploy.")
+            )
+            .arg(Arg::with_name("auth_secret")
+                .long("auth_secret")
+                .alias("auth-secret")
+                .number_of_values(1)
+                .help("Name of a secret with H2O's login configuration under the 'login.conf' key, e.g. a realm file with password hashes. Mounted to '/etc/h2o/auth'.")
+            )
+            .arg(Arg::with_name("auth_method")
+                .long("auth_method")
+                .alias("auth-method")
+                .number_of_values(1)
+                .possible_values(&["hash", "ldap", "kerberos"])
+                .default_value("hash")
+                .help("H2O login module authenticating users with the configuration from '--auth_secret'. Noop without '--auth_secret'. Kerberos configuration is expected under the 'krb5.conf' key.")
+            )
+            .arg(Arg::with_name("keystore_secret")
+                .long("keystore_secret")
+                .alias("keystore-secret")
+                .number_of_values(1)
+                .help("Name of a secret with 'keystore.jks', its 'password' and H2O's internal 'security.properties'. H2O REST API and communication between H2O nodes are encrypted with TLS.")
+            )
+            .arg(Arg::with_name("node_selector")
+                .long("node_selector")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(self::validate_key_value)
+                .help("Label of Kubernetes nodes to schedule H2O pods on in the 'key=value' format. May be specified multiple times.")
+            )
+            .arg(Arg::with_name("toleration")
+                .long("toleration")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(self::validate_toleration)
+                .help("Toleration of H2O pods in the 'key[=value]:effect' format, e.g. 'dedicated=h2o:NoSchedule'. May be specified multiple times.")
+            )
+            .arg(Arg::with_name("priority_class")
+                .long("priority_class")
+                .number_of_values(1)
+                .help("Name of the priority class of H2O pods.")
+            )
+            .arg(Arg::with_name("spreading")
+                .long("spreading")
+                .number_of_values(1)
+                .possible_values(&["none", "preferred", "required"])
+                .help("How strictly H2O pods are spread across Kubernetes nodes or zones. With 'required', pods remain pending unless they can be spread. No spreading is requested if not specified.")
+            )
+            .arg(Arg::with_name("env")
+                .long("env")
+                .short("e")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(self::validate_key_value)
+                .help("Environment variable of H2O containers in the 'NAME=value' format. May be specified multiple times.")
+            )
+            .arg(Arg::with_name("jvm_arg")
+                .long("jvm_arg")
+                .alias("jvm-arg")
+                .multiple(true)
+                .number_of_values(1)
+                .allow_hyphen_values(true)
+                .help("Argument of the JVM running H2O, e.g. '-XX:+UseG1GC'. May be specified multiple times. Passed via JAVA_TOOL_OPTIONS to custom images.")
+            )
+            .arg(Arg::with_name("h2o_arg")
+                .long("h2o_arg")
+                .alias("h2o-arg")
+                .multiple(true)
+                .number_of_values(1)
+                .allow_hyphen_values(true)
+                .help("Argument of H2O, e.g. '-log_level INFO'. May be specified multiple times. Split on whitespace.")
+            )
+            .arg(Arg::with_name("spreading_topology")
+                .long("spreading_topology")
+                .number_of_values(1)
+                .possible_values(&["hostname", "zone"])
+                .requires("spreading")
+                .help("Topology domain to spread H2O pods across. Defaults to 'hostname'.")
+            )
         )
         .subcommand(SubCommand::with_name("undeploy")
             .about("Undeploys an existing H2O cluster from Kubernetes")
@@ -314,7 +732,39 @@ fn build_app<'a>() -> App<'a, 'a> {
             .arg(Arg::with_name("name")
                 .index(1)
                 .help("Name of the H2O cluster deployment. Used as prefix for K8S entities. Generated if not specified.")
-                .number_of_values(1)));
+                .number_of_values(1))
+            .arg(Arg::with_name("host")
+                .long("host")
+                .number_of_values(1)
+                .help("Hostname the ingress applies to, e.g. h2o.example.com. If not specified, all inbound HTTP traffic is matched.")
+            )
+            .arg(Arg::with_name("tls_secret")
+                .long("tls_secret")
+                .number_of_values(1)
+                .help("Name of a secret with TLS certificate and key to terminate TLS with. Must reside in the same namespace as the H2O cluster.")
+            )
+            .arg(Arg::with_name("ingress_class")
+                .long("ingress_class")
+                .number_of_values(1)
+                .help("Ingress class of the ingress controller to handle the ingress, e.g. nginx.")
+            )
+            .arg(Arg::with_name("annotation")
+                .long("annotation")
+                .short("a")
+                .multiple(true)
+                .number_of_values(1)
+                .validator(self::validate_key_value)
+                .help("Additional ingress annotation in the 'key=value' format. May be specified multiple times.")
+            ))
+        .subcommand(SubCommand::with_name("crd")
+            .about("Prints the H2O CustomResourceDefinition generated from H2O resource types. Apply it with 'kubectl apply -f'.")
+            .arg(Arg::with_name("api_version")
+                .long("api_version")
+                .number_of_values(1)
+                .possible_values(&["v1", "v1beta1"])
+                .default_value("v1")
+                .help("API version of the CustomResourceDefinition - v1beta1 for Kubernetes older than 1.16.")
+            ));
 }
 
 /// Validates whether a file under a user-provided path exists.
@@ -340,31 +790,65 @@ fn validate_int_greater_than_zero(input: String) -> Result<(), String> {
     };
 }
 
-/// Validates if user's input is a number in an expected range.
+/// Validates user input to be a non-negative integer, e.g. a user ID.
+fn validate_user_id(input: String) -> Result<(), String> {
+    return match input.parse::<i64>() {
+        Ok(number) if number >= 0 => Result::Ok(()),
+        _ => Result::Err(format!("Error: '{}' is not a valid user ID.", input)),
+    };
+}
+
+/// Validates if user's input is a percentage within the <1,100> range, as accepted by the `H2O` resource validation.
 ///
 /// # Arguments
 ///  * `input` User's input in String
 ///
 fn validate_percentage(input: String) -> Result<(), String> {
-    let number: i64 = input.parse::<i64>().unwrap();
-    return if number < 0 || number > 100 {
-        Result::Err("Error: The number must be withing range <0,100>.".to_string())
-    } else {
-        Result::Ok(())
+    return match input.parse::<i64>() {
+        Ok(number) if (1..=100).contains(&number) => Result::Ok(()),
+        _ => Result::Err("Error: The number must be within range <1,100>.".to_string()),
+    };
+}
+
+/// Validates user input to be a 'key=value' pair with a non-empty key.
+fn validate_key_value(input: String) -> Result<(), String> {
+    return match input.find('=') {
+        Some(separator_index) if separator_index > 0 => Result::Ok(()),
+        _ => Result::Err(format!("Error: '{}' is not in the 'key=value' format.", input)),
     };
 }
 
 const MEMORY_PATTERN: &str = "^([+-]?[0-9.]+)([eEinumkKMGTP]*[-+]?[0-9]*)$";
 
-/// Validates memory input from user. The pattern the input is matched against is the same pattern K8S uses.
+/// Validates memory input from user. The input must be a Kubernetes quantity of whole bytes, at least the minimum
+/// memory of an H2O node.
 fn validate_memory(input: String) -> Result<(), String> {
-    let memory_regexp = Regex::new(MEMORY_PATTERN).unwrap();
+    let memory: MemoryQuantity = input.parse().map_err(|error: deployment::Error| error.to_string())?;
+    return if memory.bytes() >= quantity::MIN_MEMORY_BYTES {
+        Result::Ok(())
+    } else {
+        Result::Err(format!("Memory of each H2O node must be at least {}.", quantity::MIN_MEMORY))
+    };
+}
+
+/// Validates a size in bytes from user, e.g. memory request or ephemeral storage. The input must be a Kubernetes
+/// quantity of whole bytes.
+fn validate_size(input: String) -> Result<(), String> {
+    return match input.parse::<MemoryQuantity>() {
+        Ok(_) => Result::Ok(()),
+        Err(error) => Result::Err(error.to_string()),
+    };
+}
 
-    return if memory_regexp.is_match(&input) {
+/// Validates CPU input from user. CPU quantities match the same pattern as memory, e.g. 1, 0.5 or 500m.
+fn validate_cpu(input: String) -> Result<(), String> {
+    let cpu_regexp = Regex::new(MEMORY_PATTERN).unwrap();
+
+    return if cpu_regexp.is_match(&input) {
         Result::Ok(())
     } else {
         Result::Err(format!(
-            "Memory requirement must match the following pattern: {}. For example 1Gi or 1024Mi.",
+            "CPU requirement must match the following pattern: {}. For example 1 or 500m.",
             MEMORY_PATTERN
         ))
     };
@@ -377,6 +861,7 @@ mod tests {
     use std::path::PathBuf;
 
     use clap::{App, ArgMatches};
+    use deployment::crd_manifest::CrdApiVersion;
 
     use tests_common::kubeconfig_location_panic;
 
@@ -478,9 +963,240 @@ mod tests {
         assert_eq!("non-default", deploy.value_of("namespace").unwrap())
     }
 
+    #[test]
+    fn test_ingress() {
+        let app: App = super::build_app();
+        let args: Vec<&str> = vec![
+            "h2ok",
+            "ingress",
+            "my-h2o",
+            "--host",
+            "h2o.example.com",
+            "--tls_secret",
+            "h2o-tls",
+            "--annotation",
+            "nginx.ingress.kubernetes.io/proxy-body-size=0",
+            "--annotation",
+            "nginx.ingress.kubernetes.io/whitelist-source-range=10.0.0.0/8",
+        ];
+        let matches: ArgMatches = app.get_matches_from(args);
+        let ingress: super::UserIngressSpecification = super::ingress(matches.subcommand_matches("ingress").unwrap());
+        assert_eq!(Some("h2o.example.com".to_string()), ingress.host);
+        assert_eq!(Some("h2o-tls".to_string()), ingress.tls_secret);
+        assert!(ingress.ingress_class.is_none());
+        assert_eq!(2, ingress.annotations.len());
+        assert_eq!("0", ingress.annotations["nginx.ingress.kubernetes.io/proxy-body-size"]);
+        assert_eq!("10.0.0.0/8", ingress.annotations["nginx.ingress.kubernetes.io/whitelist-source-range"]);
+
+        assert!(super::validate_key_value("no-separator".to_string()).is_err());
+        assert!(super::validate_key_value("=no-key".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_scheduling() {
+        let app: App = super::build_app();
+        let args: Vec<&str> = vec![
+            "h2ok",
+            "deploy",
+            "--cluster_size",
+            "1",
+            "--version",
+            "latest",
+            "--node_selector",
+            "pool=highmem",
+            "--toleration",
+            "dedicated=h2o:NoSchedule",
+            "--toleration",
+            "highmem",
+            "--priority_class",
+            "high-priority",
+            "--spreading",
+            "required",
+        ];
+        let matches: ArgMatches = app.get_matches_from(args);
+        let scheduling: super::UserSchedulingSpecification = super::scheduling(matches.subcommand_matches("deploy").unwrap());
+        assert_eq!("highmem", scheduling.node_selector["pool"]);
+        assert_eq!(2, scheduling.tolerations.len());
+        assert_eq!(Some("Equal".to_string()), scheduling.tolerations[0].operator);
+        assert_eq!(Some("h2o".to_string()), scheduling.tolerations[0].value);
+        assert_eq!(Some("NoSchedule".to_string()), scheduling.tolerations[0].effect);
+        assert_eq!(Some("Exists".to_string()), scheduling.tolerations[1].operator);
+        assert!(scheduling.tolerations[1].effect.is_none());
+        assert_eq!(Some("high-priority".to_string()), scheduling.priority_class);
+        assert_eq!(Some("required".to_string()), scheduling.spreading);
+        assert!(scheduling.spreading_topology.is_none());
+
+        assert!(super::parse_toleration("dedicated=h2o:Unknown").is_err());
+        assert!(super::parse_toleration(":NoSchedule").is_err());
+    }
+
+    #[test]
+    fn test_arguments() {
+        let app: App = super::build_app();
+        let args: Vec<&str> = vec![
+            "h2ok",
+            "deploy",
+            "--cluster_size",
+            "1",
+            "--version",
+            "latest",
+            "--env",
+            "TZ=UTC",
+            "--jvm_arg",
+            "-XX:+UseG1GC",
+            "--h2o_arg",
+            "-log_level INFO",
+            "--h2o_arg",
+            "-nthreads",
+            "--h2o_arg",
+            "4",
+        ];
+        let matches: ArgMatches = app.get_matches_from(args);
+        let deploy: &ArgMatches = matches.subcommand_matches("deploy").unwrap();
+        assert_eq!("UTC", super::extract_key_values(deploy, "env")["TZ"]);
+        assert_eq!(vec!["-XX:+UseG1GC"], super::extract_arguments(deploy, "jvm_arg"));
+        assert_eq!(vec!["-log_level", "INFO", "-nthreads", "4"], super::extract_arguments(deploy, "h2o_arg"));
+
+        // Spellings with hyphens are kept as aliases
+        let app: App = super::build_app();
+        let args: Vec<&str> = vec![
+            "h2ok",
+            "deploy",
+            "--cluster_size",
+            "1",
+            "--version",
+            "latest",
+            "--jvm-arg",
+            "-XX:+UseG1GC",
+            "--h2o-arg",
+            "-nthreads 4",
+        ];
+        let matches: ArgMatches = app.get_matches_from(args);
+        let deploy: &ArgMatches = matches.subcommand_matches("deploy").unwrap();
+        assert_eq!(vec!["-XX:+UseG1GC"], super::extract_arguments(deploy, "jvm_arg"));
+        assert_eq!(vec!["-nthreads", "4"], super::extract_arguments(deploy, "h2o_arg"));
+    }
+
+    #[test]
+    fn test_security() {
+        let app: App = super::build_app();
+        let args: Vec<&str> = vec![
+            "h2ok",
+            "deploy",
+            "--cluster_size",
+            "1",
+            "--image",
+            "registry/h2o:latest",
+            "--run_as_user",
+            "2000",
+            "--writable_root_filesystem",
+        ];
+        let matches: ArgMatches = app.get_matches_from(args);
+        let deploy: &ArgMatches = matches.subcommand_matches("deploy").unwrap();
+        assert_eq!(Some(2000), super::extract_num::<i64>(deploy, "run_as_user"));
+        assert!(deploy.is_present("writable_root_filesystem"));
+        assert!(super::extract_string(deploy, "service_account").is_none());
+        assert!(super::extract_string(deploy, "auth_secret").is_none());
+        assert_eq!(Some("hash".to_string()), super::extract_string(deploy, "auth_method"));
+        assert!(super::extract_string(deploy, "keystore_secret").is_none());
+        assert!(super::validate_user_id("-1".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_authentication() {
+        for (secret_flag, method_flag) in &[("--auth_secret", "--auth_method"), ("--auth-secret", "--auth-method")] {
+            let app: App = super::build_app();
+            let args: Vec<&str> = vec![
+                "h2ok",
+                "deploy",
+                "--cluster_size",
+                "1",
+                "--version",
+                "latest",
+                secret_flag,
+                "h2o-realm",
+                method_flag,
+                "ldap",
+            ];
+            let matches: ArgMatches = app.get_matches_from(args);
+            let deploy: &ArgMatches = matches.subcommand_matches("deploy").unwrap();
+            assert_eq!(Some("h2o-realm".to_string()), super::extract_string(deploy, "auth_secret"));
+            assert_eq!(Some("ldap".to_string()), super::extract_string(deploy, "auth_method"));
+        }
+    }
+
+    #[test]
+    fn test_tls() {
+        for keystore_flag in &["--keystore_secret", "--keystore-secret"] {
+            let app: App = super::build_app();
+            let args: Vec<&str> = vec![
+                "h2ok",
+                "deploy",
+                "--cluster_size",
+                "1",
+                "--version",
+                "latest",
+                keystore_flag,
+                "h2o-keystore",
+            ];
+            let matches: ArgMatches = app.get_matches_from(args);
+            let deploy: &ArgMatches = matches.subcommand_matches("deploy").unwrap();
+            assert_eq!(Some("h2o-keystore".to_string()), super::extract_string(deploy, "keystore_secret"));
+        }
+    }
+
+    #[test]
+    fn test_resources() {
+        let app: App = super::build_app();
+        let args: Vec<&str> = vec![
+            "h2ok",
+            "deploy",
+            "--cluster_size",
+            "1",
+            "--version",
+            "latest",
+            "--memory",
+            "4Gi",
+            "--memory_request",
+            "2Gi",
+            "--cpu_request",
+            "500m",
+            "--ephemeral_storage",
+            "10Gi",
+        ];
+        let matches: ArgMatches = app.get_matches_from(args);
+        let deploy: &ArgMatches = matches.subcommand_matches("deploy").unwrap();
+        let specification: super::UserNewClusterSpecification = super::new_deployment(deploy);
+        assert_eq!("4Gi", specification.memory);
+        assert_eq!(1, specification.num_cpu);
+        assert_eq!(Some("2Gi".to_string()), specification.resources.memory_request);
+        assert_eq!(Some("500m".to_string()), specification.resources.cpu_request);
+        assert_eq!(Some("10Gi".to_string()), specification.resources.ephemeral_storage);
+        assert!(super::validate_cpu("half".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_validate_memory() {
+        assert!(super::validate_memory("1Gi".to_string()).is_ok());
+        assert!(super::validate_memory("1e3".to_string()).is_err());
+        assert!(super::validate_memory("0.1Ki".to_string()).is_err());
+        assert!(super::validate_size("1e3".to_string()).is_ok());
+    }
+
+    #[test]
+    fn test_crd() {
+        let matches: ArgMatches = super::build_app().get_matches_from(vec!["h2ok", "crd"]);
+        assert_eq!(CrdApiVersion::V1, super::crd_api_version(matches.subcommand_matches("crd").unwrap()));
+        let matches: ArgMatches = super::build_app().get_matches_from(vec!["h2ok", "crd", "--api_version", "v1beta1"]);
+        assert_eq!(CrdApiVersion::V1beta1, super::crd_api_version(matches.subcommand_matches("crd").unwrap()));
+    }
+
     #[test]
     fn validate_number_range() {
         assert!(super::validate_percentage("10".to_string()).is_ok());
+        assert!(super::validate_percentage("100".to_string()).is_ok());
+        assert!(super::validate_percentage("0".to_string()).is_err());
         assert!(super::validate_percentage("101".to_string()).is_err());
+        assert!(super::validate_percentage("ten".to_string()).is_err());
     }
 }
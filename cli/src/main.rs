@@ -2,14 +2,16 @@ extern crate clap;
 extern crate deployment;
 extern crate tokio;
 
-use k8s_openapi::api::networking::v1beta1::Ingress;
 use kube::Client;
 
 use cli::{Command, UserNewClusterSpecification};
-use deployment::crd::{CustomImage, H2OSpec, Resources};
+use deployment::crd::{Auth, AuthMethod, CustomImage, EnvVariable, H2OSpec, ImagePullPolicy, IngressSpec, ResourceRequests, Resources, SecurityContextSpec, ServiceAccountSpec, Spreading, SpreadingPolicy, SpreadingTopology, Tls};
+use deployment::crd_manifest::CrdApiVersion;
+use deployment::defaults::Defaults;
 use deployment::Error;
+use deployment::ingress::{Backend, H2OIngress, IngressApiVersion};
 
-use crate::cli::UserExistingClusterSpecification;
+use crate::cli::{UserExistingClusterSpecification, UserIngressSpecification};
 
 mod cli;
 
@@ -50,8 +52,11 @@ async fn main() {
         Command::DeleteCluster(existing_deployment_spec) => {
             delete_existing_deployment(existing_deployment_spec).await;
         }
-        Command::Ingress(existing_deployment_spec) => {
-            add_ingress(existing_deployment_spec).await;
+        Command::Ingress(existing_deployment_spec, ingress_spec) => {
+            add_ingress(existing_deployment_spec, ingress_spec).await;
+        }
+        Command::PrintCrd(api_version) => {
+            print_crd(api_version);
         }
     };
 }
@@ -69,26 +74,96 @@ async fn create_new_deployment(user_spec: UserNewClusterSpecification) {
             .unwrap(),
     };
 
-    let resources: Resources = Resources::new(
+    let mut resources: Resources = Resources::new(
         user_spec.num_cpu,
         user_spec.memory,
         Some(user_spec.memory_percentage),
     );
+    if user_spec.resources.cpu_request.is_some() || user_spec.resources.memory_request.is_some() {
+        resources.requests = Some(ResourceRequests::new(user_spec.resources.cpu_request, user_spec.resources.memory_request));
+    }
+    resources.ephemeral_storage = user_spec.resources.ephemeral_storage;
     let custom_image: Option<CustomImage> = match user_spec.custom_image {
         None => Option::None,
         Some(img) => Option::Some(CustomImage::new(img, user_spec.custom_command)),
     };
-    let specification: H2OSpec = H2OSpec::new(
+    let mut specification: H2OSpec = H2OSpec::new(
         user_spec.num_h2o_nodes,
         user_spec.version,
         resources,
         custom_image,
     );
+    specification.node_selector = user_spec.scheduling.node_selector;
+    specification.tolerations = user_spec.scheduling.tolerations;
+    specification.priority_class_name = user_spec.scheduling.priority_class;
+    // Spreading is only defined if requested by the user. Possible values are enforced by the CLI.
+    let spreading_topology: SpreadingTopology = match user_spec.scheduling.spreading_topology.as_deref() {
+        Some("zone") => SpreadingTopology::Zone,
+        _ => SpreadingTopology::Hostname,
+    };
+    specification.spreading = user_spec.scheduling.spreading.map(|spreading| {
+        let spreading_policy: SpreadingPolicy = match spreading.as_str() {
+            "preferred" => SpreadingPolicy::Preferred,
+            "required" => SpreadingPolicy::Required,
+            _ => SpreadingPolicy::None,
+        };
+        Spreading::new(spreading_policy, spreading_topology)
+    });
+    specification.env = user_spec.env.into_iter()
+        .map(|(name, value)| EnvVariable::new(name, value))
+        .collect();
+    specification.jvm_args = user_spec.jvm_args;
+    specification.h2o_args = user_spec.h2o_args;
+    specification.image_repository = user_spec.registry.image_repository;
+    specification.image_pull_secrets = user_spec.registry.image_pull_secrets;
+    // Possible values are enforced by the CLI
+    specification.image_pull_policy = match user_spec.registry.image_pull_policy.as_deref() {
+        Some("Always") => Some(ImagePullPolicy::Always),
+        Some("IfNotPresent") => Some(ImagePullPolicy::IfNotPresent),
+        Some("Never") => Some(ImagePullPolicy::Never),
+        _ => None,
+    };
+    if user_spec.security.run_as_user.is_some() || user_spec.security.writable_root_filesystem {
+        specification.security_context = Some(SecurityContextSpec {
+            run_as_user: user_spec.security.run_as_user,
+            read_only_root_filesystem: Some(!user_spec.security.writable_root_filesystem),
+            ..SecurityContextSpec::default()
+        });
+    }
+    if user_spec.security.create_service_account || user_spec.security.service_account.is_some() {
+        specification.service_account = Some(ServiceAccountSpec::new(user_spec.security.create_service_account,
+                                                                     user_spec.security.service_account));
+    }
+    // Possible values are enforced by the CLI
+    let auth_method: AuthMethod = match user_spec.security.auth_method.as_str() {
+        "ldap" => AuthMethod::Ldap,
+        "kerberos" => AuthMethod::Kerberos,
+        _ => AuthMethod::Hash,
+    };
+    specification.auth = user_spec.security.auth_secret
+        .map(|secret_name| Auth::new(auth_method, secret_name));
+    specification.tls = user_spec.security.keystore_secret.map(Tls::new);
+    // The CLI has no operator-level defaults, the built-in ones complete the specification the same way
+    Defaults::default().apply(&mut specification);
+    let violations: Vec<String> = deployment::validation::violations(&specification);
+    if !violations.is_empty() {
+        eprintln!("Invalid H2O specification:");
+        for violation in violations {
+            eprintln!("- {}", violation);
+        }
+        std::process::exit(1);
+    }
+    for warning in deployment::security::warnings(&specification) {
+        eprintln!("Warning: {}", warning);
+    }
     match deployment::create_h2o_cluster(
         client.clone(),
         &specification,
         &namespace,
         &user_spec.name,
+        // No ingress is deployed along with the cluster by the CLI, see the 'ingress' command
+        deployment::ingress::FALLBACK_API_VERSION,
+        Option::None,
     )
         .await
     {
@@ -102,12 +177,59 @@ async fn create_new_deployment(user_spec: UserNewClusterSpecification) {
         "Deployment of '{}' completed successfully.",
         &user_spec.name
     );
+    if let Ok(heap) = deployment::quantity::jvm_heap(&specification.resources) {
+        println!("Each H2O node has a maximum JVM heap of {}.", heap);
+    }
+    print_connection_hints(&namespace, &user_spec.name, specification.tls.is_some());
+    if specification.auth.is_some() {
+        println!("H2O requires authentication. Pass 'auth=(\"<user>\", \"<password>\")' to 'h2o.connect()' in Python, or 'username' and 'password' in R.");
+    }
     println!(
         "To undeploy, use the 'h2ok undeploy {}' command.",
         &user_spec.name
     );
 }
 
+/// Prints hints on how to connect to the H2O cluster via its leader service, both from inside the Kubernetes cluster
+/// and from user's machine by means of port forwarding.
+///
+/// # Arguments
+/// `namespace` - Namespace the H2O cluster is deployed to
+/// `name` - Name of the H2O deployment
+/// `tls` - Whether H2O serves its REST API over TLS
+fn print_connection_hints(namespace: &str, name: &str, tls: bool) {
+    let leader_service_name: String = deployment::leader_service::leader_service_name(name);
+    let port: i32 = deployment::leader_service::leader_service_port(tls);
+    let scheme: &str = if tls { "https" } else { "http" };
+    println!("Once clustered, use 'h2o.connect()' to connect to the H2O cluster from inside the Kubernetes cluster:");
+    println!(
+        "Python: 'h2o.connect(url=\"{}\")'",
+        deployment::leader_service::leader_service_url(namespace, name, tls)
+    );
+    println!(
+        "R: 'h2o.connect(ip = \"{}.{}.svc.cluster.local\", port={}, https={})'",
+        &leader_service_name, namespace, port, if tls { "TRUE" } else { "FALSE" }
+    );
+    println!(
+        "To connect from this machine, run 'kubectl port-forward -n {} service/{} 54321:{}' and use 'h2o.connect(url=\"{}://localhost:54321\")'.",
+        namespace, &leader_service_name, port, scheme
+    );
+}
+
+/// Prints the H2O `CustomResourceDefinition` generated from H2O resource types, to be applied by `kubectl apply -f`.
+///
+/// # Arguments
+/// `api_version` - API version of the `CustomResourceDefinition` resource to print
+fn print_crd(api_version: CrdApiVersion) {
+    match deployment::crd_manifest::h2o_crd_yaml(api_version, Option::None) {
+        Ok(manifest) => print!("{}", manifest),
+        Err(error) => {
+            eprintln!("Unable to generate H2O CustomResourceDefinition. Error:\n{:?}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
 /// Deletes an existing deployment and all its sub-resources. The deletion is asynchronous -
 /// and the resources might be deleted in parallel. This method does not wait for the deletion process to be completed, as
 /// this is the responsibility of the respective controllers.
@@ -141,43 +263,73 @@ async fn delete_existing_deployment(specification: UserExistingClusterSpecificat
 }
 
 /// Adds an ingress to an existing deployment specification. The ingress is pointed to the
-/// headless service used for H2O node discovery, as when the H2O cluster is ready, only one the
-/// pod with the H2O Leader node passes the readiness probe.
+/// leader service, as when the H2O cluster is ready, only the pod with the H2O Leader node
+/// passes the readiness probe and becomes the service's endpoint. Deployments created by older versions
+/// of h2ok have no leader service, the ingress is pointed to their headless service instead. An existing
+/// ingress is updated, so the command may be repeated.
 ///
 /// Asynchronous method. The ingress might not yet be fully initialized when this method returns.
 ///
 /// # Arguments
 /// `specification` - A descriptor of an existing deployment to point ingress to.
-async fn add_ingress(specification: UserExistingClusterSpecification) {
+/// `user_ingress_spec` - Host, TLS, ingress class and annotations of the ingress.
+async fn add_ingress(specification: UserExistingClusterSpecification, user_ingress_spec: UserIngressSpecification) {
     let (client, namespace): (Client, String) = match specification.kubeconfig_path {
         None => deployment::client::try_default().await.unwrap(),
         Some(kubeconfig_path) => deployment::client::from_kubeconfig(kubeconfig_path.as_path()).await
             .unwrap(),
     };
 
-    let ingress: Ingress = deployment::ingress::create(
+    let ingress_spec: IngressSpec = IngressSpec::new(
+        user_ingress_spec.host,
+        user_ingress_spec.tls_secret,
+        user_ingress_spec.ingress_class,
+        user_ingress_spec.annotations,
+    );
+    let namespace: String = specification.namespace.unwrap_or(namespace);
+    let api_version: IngressApiVersion = deployment::ingress::api_version(&client).await
+        .unwrap_or_else(|error| {
+            eprintln!("Warning: Unable to detect Ingress API version, assuming {:?}. Error:\n{}",
+                      deployment::ingress::FALLBACK_API_VERSION, error);
+            deployment::ingress::FALLBACK_API_VERSION
+        });
+    let backend: Backend = deployment::ingress::backend(client.clone(), &namespace, &specification.name)
+        .await.expect(&format!(
+        "Unable to find services of {} deployment.", specification.name));
+    let ingress: H2OIngress = deployment::ingress::apply(
         client.clone(),
-        &specification.namespace.unwrap_or(namespace),
+        api_version,
+        &namespace,
         &specification.name,
+        &ingress_spec,
+        backend,
+        Option::None,
     )
         .await.expect(&format!(
         "Unable to create ingress for {} deployment.", specification.name));
 
     println!("Ingress '{}' deployed successfully.", &specification.name);
-    let ingress_ip: Option<String> = deployment::ingress::any_lb_external_ip(&ingress);
+    // A configured host takes precedence over the load balancer address
+    let ingress_address: Option<String> = ingress_spec.host.clone()
+        .or_else(|| deployment::ingress::any_lb_external_ip(&ingress));
     let ingress_path: Option<String> = deployment::ingress::any_path(&ingress);
+    let (scheme, port): (&str, u16) = if ingress_spec.tls_secret.is_some() { ("https", 443) } else { ("http", 80) };
 
-    if ingress_ip.is_some() && ingress_path.is_some() {
+    if ingress_address.is_some() && ingress_path.is_some() {
         println!("You may now use 'h2o.connect()' to connect to the H2O cluster:");
         println!(
-            "Python: 'h2o.connect(url=\"http://{}:80{}\")'",
-            ingress_ip.as_ref().unwrap(),
+            "Python: 'h2o.connect(url=\"{}://{}:{}{}\")'",
+            scheme,
+            ingress_address.as_ref().unwrap(),
+            port,
             ingress_path.as_ref().unwrap()
         );
         println!(
-            "R: 'h2o.connect(ip = \"{}\", context_path = \"{}\", port=80)'",
-            ingress_ip.as_ref().unwrap(),
-            ingress_path.unwrap().strip_prefix("/").unwrap()
+            "R: 'h2o.connect(ip = \"{}\", context_path = \"{}\", port={}, https={})'",
+            ingress_address.as_ref().unwrap(),
+            ingress_path.unwrap().strip_prefix("/").unwrap(),
+            port,
+            if scheme == "https" { "TRUE" } else { "FALSE" }
         )
     }
 }
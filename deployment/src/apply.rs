@@ -0,0 +1,48 @@
+use kube::Api;
+use kube::api::{Meta, PatchParams};
+use log::debug;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::Error;
+
+/// Name of the field manager all H2O sub-resources are applied with. Kubernetes tracks ownership of each field
+/// of the applied resources under this name.
+pub const FIELD_MANAGER: &str = "h2o-kubernetes";
+
+/// Idempotently creates or updates a resource by means of
+/// [server-side apply](https://kubernetes.io/docs/reference/using-api/server-side-apply/).
+///
+/// - If the resource does not exist, it is created.
+/// - If the resource exists and differs from the desired state, the fields managed by this crate are patched.
+/// - If the resource exists and matches the desired state, it is left untouched.
+///
+/// Conflicts with other field managers are forced, as the desired state of H2O sub-resources is
+/// always dictated by the `H2OSpec`.
+///
+/// # Arguments
+/// `api` - Api to apply the resource with, determines the namespace as well.
+/// `name` - Name of the resource to apply.
+/// `resource` - Desired state of the resource. Must contain `apiVersion` and `kind`, as all `k8s_openapi` types
+/// and custom resources do when serialized.
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use k8s_openapi::api::core::v1::Service;
+/// use kube::{Api, Client};
+/// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
+/// let api: Api<Service> = Api::namespaced(client, &namespace);
+/// let service: Service = deployment::headless_service::h2o_service("any-name", &namespace, Option::None);
+/// deployment::apply::apply(&api, "any-name", &service).await.unwrap();
+/// }
+/// ```
+pub async fn apply<K>(api: &Api<K>, name: &str, resource: &K) -> Result<K, Error>
+    where K: Clone + DeserializeOwned + Serialize + Meta {
+    let patch_params: PatchParams = PatchParams::apply(FIELD_MANAGER).force();
+    let applied: K = api.patch(name, &patch_params, serde_json::to_vec(resource)?).await?;
+    debug!("Applied {} '{}'.", K::KIND, name);
+    Ok(applied)
+}
@@ -0,0 +1,76 @@
+use k8s_openapi::api::core::v1::{SecretVolumeSource, Volume, VolumeMount};
+
+use crate::crd::{Auth, AuthMethod};
+
+/// Name of the volume with the login configuration of H2O.
+pub const VOLUME_NAME: &str = "h2o-auth";
+/// Path the secret with the login configuration is mounted to in H2O containers.
+pub const MOUNT_PATH: &str = "/etc/h2o/auth";
+/// Key of the login configuration in the secret, unless specified otherwise.
+pub const DEFAULT_LOGIN_CONF_KEY: &str = "login.conf";
+/// Key of the Kerberos configuration in the secret, unless specified otherwise.
+pub const DEFAULT_KRB5_CONF_KEY: &str = "krb5.conf";
+
+/// Volume with the secret holding the login configuration. The files are only readable by the owner and the group,
+/// which is the filesystem group of H2O pods.
+///
+/// # Arguments
+/// `auth` - Authentication settings of the H2O cluster
+pub fn volume(auth: &Auth) -> Volume {
+    Volume {
+        name: VOLUME_NAME.to_string(),
+        secret: Some(SecretVolumeSource {
+            secret_name: Some(auth.secret_name.clone()),
+            default_mode: Some(0o440),
+            ..SecretVolumeSource::default()
+        }),
+        ..Volume::default()
+    }
+}
+
+/// Read-only mount of the volume with the login configuration in H2O containers.
+pub fn volume_mount() -> VolumeMount {
+    VolumeMount {
+        name: VOLUME_NAME.to_string(),
+        mount_path: MOUNT_PATH.to_string(),
+        read_only: Some(true),
+        ..VolumeMount::default()
+    }
+}
+
+/// H2O arguments enabling the login module selected by `auth.method`, pointed to the mounted login configuration.
+///
+/// # Arguments
+/// `auth` - Authentication settings of the H2O cluster
+pub fn h2o_args(auth: &Auth) -> Vec<String> {
+    let login_argument: &str = match auth.method {
+        AuthMethod::Hash => "-hash_login",
+        AuthMethod::Ldap => "-ldap_login",
+        AuthMethod::Kerberos => "-kerberos_login",
+    };
+    let login_conf_key: &str = auth.login_conf_key.as_deref().unwrap_or(DEFAULT_LOGIN_CONF_KEY);
+    let mut args: Vec<String> = vec![
+        login_argument.to_string(),
+        "-login_conf".to_string(),
+        format!("{}/{}", MOUNT_PATH, login_conf_key),
+    ];
+    if auth.form_auth {
+        args.push("-form_auth".to_string());
+    }
+    args
+}
+
+/// JVM arguments required by the login module selected by `auth.method`. Only Kerberos requires the JVM
+/// to be pointed to the Kerberos configuration, other login modules require no JVM arguments.
+///
+/// # Arguments
+/// `auth` - Authentication settings of the H2O cluster
+pub fn jvm_args(auth: &Auth) -> Vec<String> {
+    match auth.method {
+        AuthMethod::Kerberos => {
+            let krb5_conf_key: &str = auth.krb5_conf_key.as_deref().unwrap_or(DEFAULT_KRB5_CONF_KEY);
+            vec![format!("-Djava.security.krb5.conf={}/{}", MOUNT_PATH, krb5_conf_key)]
+        }
+        AuthMethod::Hash | AuthMethod::Ldap => Vec::new(),
+    }
+}
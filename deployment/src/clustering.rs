@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use futures::StreamExt;
+use k8s_openapi::api::apps::v1::StatefulSet;
+use k8s_openapi::api::core::v1::Pod;
+use kube::{Api, Client};
+use kube::api::{ListParams, Meta};
+use kube_runtime::watcher;
+use kube_runtime::watcher::Event;
+use log::debug;
+
+use crate::Error;
+
+/// Progress of H2O clustering, reported while waiting for an H2O cluster to form.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClusteringEvent {
+    /// Number of H2O pods up and running has changed.
+    NodesRunning {
+        /// Number of H2O pods up and running.
+        running: u32,
+        /// Number of H2O pods expected to form the cluster.
+        expected: u32,
+    },
+    /// An H2O pod failed or is stuck in a state it will not recover from without user's intervention.
+    /// Clustering may still succeed, e.g. once a missing image is pushed to the repository.
+    NodeFailed {
+        /// Name of the failed pod.
+        pod: String,
+        /// Human-readable reason of the failure.
+        reason: String,
+    },
+    /// H2O nodes formed a cluster and the leader node passed the readiness probe.
+    Clustered {
+        /// Name of the pod with H2O leader node.
+        leader_pod: String,
+    },
+}
+
+/// Waits until pods of an existing H2O deployment of given `name` form an H2O cluster, which is when the pod with H2O
+/// leader node passes the `/kubernetes/isLeaderNode` readiness probe. Progress is reported via `on_event`
+/// as the pods are being watched.
+///
+/// Returns the pod with H2O leader node once clustered.
+///
+/// # Arguments
+/// `client` - Client with permissions to get statefulsets and list/watch pods
+/// `namespace` - Namespace the H2O deployment resides in
+/// `name` - Name of the H2O deployment
+/// `timeout` - Maximum time to wait for the H2O cluster to form
+/// `on_event` - Called with each `ClusteringEvent` observed
+///
+/// # Errors
+///
+/// Returns `Error::Timeout` if the H2O cluster is not formed within the given `timeout`.
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use std::time::Duration;
+/// use k8s_openapi::api::core::v1::Pod;
+/// use kube::Client;
+/// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
+/// let leader: Pod = deployment::clustering::wait_until_clustered(client, &namespace, "any-name",
+///     Duration::from_secs(180), |event| println!("{:?}", event)).await.unwrap();
+/// }
+/// ```
+pub async fn wait_until_clustered<F>(
+    client: Client,
+    namespace: &str,
+    name: &str,
+    timeout: Duration,
+    on_event: F,
+) -> Result<Pod, Error>
+    where F: FnMut(&ClusteringEvent) {
+    with_timeout(name, timeout, watch_clustering(client, namespace, name, on_event)).await
+}
+
+/// Limits waiting for the H2O cluster of given `name` to form by `clustering` to `timeout`.
+async fn with_timeout<C>(name: &str, timeout: Duration, clustering: C) -> Result<Pod, Error>
+    where C: Future<Output=Result<Pod, Error>> {
+    match tokio::time::timeout(timeout, clustering).await {
+        Ok(result) => result,
+        Err(_) => Err(Error::Timeout(format!("H2O cluster '{}' did not form within {} seconds.",
+                                             name, timeout.as_secs()))),
+    }
+}
+
+/// Clustering progress already reported, so that each change is reported once.
+#[derive(Debug, Default)]
+struct ClusteringProgress {
+    /// Number of H2O pods up and running last reported
+    last_running: Option<u32>,
+    /// Failure reasons last reported, by pod name
+    reported_failures: HashMap<String, String>,
+}
+
+/// Compares current `pods` of an H2O deployment of given `name` with the `progress` already reported and returns
+/// the `ClusteringEvent`s not reported yet. `ClusteringEvent::Clustered` is always the last event returned.
+///
+/// # Arguments
+/// `name` - Name of the H2O deployment
+/// `expected` - Number of H2O pods expected to form the cluster
+/// `pods` - Current pods of the H2O deployment, by pod name
+/// `progress` - Clustering progress already reported, updated with the events returned
+fn clustering_events(name: &str, expected: u32, pods: &HashMap<String, Pod>, progress: &mut ClusteringProgress)
+                     -> Vec<ClusteringEvent> {
+    let mut events: Vec<ClusteringEvent> = Vec::new();
+    for pod in pods.values() {
+        if let Some(reason) = crate::pod::failure_reason(pod) {
+            if progress.reported_failures.get(&pod.name()) != Some(&reason) {
+                progress.reported_failures.insert(pod.name(), reason.clone());
+                events.push(ClusteringEvent::NodeFailed { pod: pod.name(), reason });
+            }
+        }
+    }
+
+    let running: u32 = pods.values().filter(|pod| crate::pod::is_running(pod)).count() as u32;
+    if progress.last_running != Some(running) {
+        progress.last_running = Some(running);
+        events.push(ClusteringEvent::NodesRunning { running, expected });
+    }
+
+    if running >= expected {
+        if let Ok(leader) = crate::pod::leader_of(name, pods.values()) {
+            events.push(ClusteringEvent::Clustered { leader_pod: leader.name() });
+        }
+    }
+    events
+}
+
+/// Watches pods of an H2O deployment of given `name` until the H2O cluster is formed, without any time limit.
+async fn watch_clustering<F>(client: Client, namespace: &str, name: &str, mut on_event: F) -> Result<Pod, Error>
+    where F: FnMut(&ClusteringEvent) {
+    let statefulset_api: Api<StatefulSet> = Api::namespaced(client.clone(), namespace);
+    let expected: u32 = statefulset_api.get(name).await?
+        .spec.as_ref()
+        .and_then(|spec| spec.replicas)
+        .unwrap_or(1) as u32;
+
+    let pod_api: Api<Pod> = Api::namespaced(client, namespace);
+    let list_params: ListParams = ListParams::default().labels(&crate::pod::label_selector(name));
+    let mut pod_events = watcher(pod_api, list_params).boxed();
+    let mut pods: HashMap<String, Pod> = HashMap::with_capacity(expected as usize);
+    let mut progress: ClusteringProgress = ClusteringProgress::default();
+
+    while let Some(event) = pod_events.next().await {
+        match event {
+            Ok(Event::Applied(pod)) => {
+                pods.insert(pod.name(), pod);
+            }
+            Ok(Event::Deleted(pod)) => {
+                pods.remove(&pod.name());
+            }
+            Ok(Event::Restarted(current_pods)) => {
+                pods = current_pods.into_iter()
+                    .map(|pod| (pod.name(), pod))
+                    .collect();
+            }
+            Err(error) => {
+                debug!("Error while watching pods of H2O cluster '{}': {}", name, error);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        }
+
+        for clustering_event in clustering_events(name, expected, &pods, &mut progress) {
+            on_event(&clustering_event);
+            if let ClusteringEvent::Clustered { leader_pod } = clustering_event {
+                if let Some(leader) = pods.get(&leader_pod) {
+                    return Ok(leader.clone());
+                }
+            }
+        }
+    }
+
+    Err(Error::UserError(format!("Watching pods of H2O cluster '{}' ended unexpectedly.", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use k8s_openapi::api::core::v1::Pod;
+    use serde_json::{json, Value};
+
+    use crate::Error;
+
+    use super::{ClusteringEvent, ClusteringProgress};
+
+    fn pod(name: &str, container_state: Value, ready: bool) -> Pod {
+        serde_json::from_value(json!({
+            "metadata": { "name": name },
+            "status": {
+                "phase": "Running",
+                "containerStatuses": [{
+                    "name": "h2o",
+                    "image": "h2oai/h2o-open-source-k8s",
+                    "imageID": "",
+                    "ready": ready,
+                    "restartCount": 0,
+                    "state": container_state
+                }],
+                "conditions": [{ "type": "Ready", "status": if ready { "True" } else { "False" } }]
+            }
+        })).unwrap()
+    }
+
+    fn running_pod(name: &str, ready: bool) -> Pod {
+        pod(name, json!({ "running": {} }), ready)
+    }
+
+    fn waiting_pod(name: &str, reason: &str) -> Pod {
+        pod(name, json!({ "waiting": { "reason": reason } }), false)
+    }
+
+    fn pods(pods: Vec<Pod>) -> HashMap<String, Pod> {
+        pods.into_iter()
+            .map(|pod| (pod.metadata.name.clone().unwrap(), pod))
+            .collect()
+    }
+
+    #[test]
+    fn test_nodes_running_reported_once() {
+        let mut progress: ClusteringProgress = ClusteringProgress::default();
+        let current_pods = pods(vec![running_pod("h2o-0", false), waiting_pod("h2o-1", "ContainerCreating")]);
+        assert_eq!(vec![ClusteringEvent::NodesRunning { running: 1, expected: 2 }],
+                   super::clustering_events("h2o", 2, &current_pods, &mut progress));
+        assert!(super::clustering_events("h2o", 2, &current_pods, &mut progress).is_empty());
+
+        let current_pods = pods(vec![running_pod("h2o-0", false), running_pod("h2o-1", false)]);
+        assert_eq!(vec![ClusteringEvent::NodesRunning { running: 2, expected: 2 }],
+                   super::clustering_events("h2o", 2, &current_pods, &mut progress));
+    }
+
+    #[test]
+    fn test_node_failed_reported_once_per_reason() {
+        let mut progress: ClusteringProgress = ClusteringProgress::default();
+        let current_pods = pods(vec![waiting_pod("h2o-0", "ErrImagePull")]);
+        let events: Vec<ClusteringEvent> = super::clustering_events("h2o", 1, &current_pods, &mut progress);
+        assert_eq!(2, events.len());
+        assert!(matches!(&events[0], ClusteringEvent::NodeFailed { pod, reason }
+            if pod == "h2o-0" && reason.contains("ErrImagePull")));
+        assert!(super::clustering_events("h2o", 1, &current_pods, &mut progress).is_empty());
+
+        let current_pods = pods(vec![waiting_pod("h2o-0", "ImagePullBackOff")]);
+        let events: Vec<ClusteringEvent> = super::clustering_events("h2o", 1, &current_pods, &mut progress);
+        assert_eq!(1, events.len());
+        assert!(matches!(&events[0], ClusteringEvent::NodeFailed { reason, .. } if reason.contains("ImagePullBackOff")));
+    }
+
+    #[test]
+    fn test_clustered_once_all_nodes_running() {
+        let mut progress: ClusteringProgress = ClusteringProgress::default();
+        let current_pods = pods(vec![running_pod("h2o-0", true), waiting_pod("h2o-1", "ContainerCreating")]);
+        assert!(!super::clustering_events("h2o", 2, &current_pods, &mut progress).iter()
+            .any(|event| matches!(event, ClusteringEvent::Clustered { .. })));
+
+        let current_pods = pods(vec![running_pod("h2o-0", true), running_pod("h2o-1", false)]);
+        assert_eq!(Some(&ClusteringEvent::Clustered { leader_pod: "h2o-0".to_string() }),
+                   super::clustering_events("h2o", 2, &current_pods, &mut progress).last());
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let clustering = futures::future::pending::<Result<Pod, Error>>();
+        match super::with_timeout("h2o", Duration::from_secs(0), clustering).await {
+            Err(Error::Timeout(message)) => assert!(message.contains("'h2o'")),
+            _ => panic!("Timeout expected."),
+        }
+    }
+}
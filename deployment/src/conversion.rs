@@ -0,0 +1,216 @@
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Status;
+use k8s_openapi::Resource;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{crd, crd_v1};
+use crate::Error;
+
+/// Path the conversion webhook is served on.
+pub const CONVERSION_PATH: &str = "/convert";
+
+/// A `ConversionReview` sent by the Kubernetes API server to the conversion webhook, and sent back with the `response`.
+/// Both `apiextensions.k8s.io/v1` and `v1beta1` reviews share this form.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConversionReview {
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request: Option<ConversionRequest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<ConversionResponse>,
+}
+
+/// Objects to convert to `desired_api_version`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConversionRequest {
+    pub uid: String,
+    #[serde(rename = "desiredAPIVersion")]
+    pub desired_api_version: String,
+    pub objects: Vec<Value>,
+}
+
+/// Converted objects, in the order of the request, or a failure `result` with no objects.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConversionResponse {
+    pub uid: String,
+    #[serde(rename = "convertedObjects")]
+    pub converted_objects: Vec<Value>,
+    pub result: Status,
+}
+
+/// Answers a `ConversionReview` by converting all the objects in its request. If any of them can not be converted,
+/// the whole review fails with the error message as a reason.
+///
+/// # Arguments
+/// `review` - `ConversionReview` as received from the Kubernetes API server
+///
+/// # Examples
+///
+/// ```
+/// use deployment::conversion::ConversionReview;
+/// use serde_json::json;
+/// let review: ConversionReview = serde_json::from_value(json!({
+///     "apiVersion": "apiextensions.k8s.io/v1",
+///     "kind": "ConversionReview",
+///     "request": { "uid": "42", "desiredAPIVersion": "h2o.ai/v1", "objects": [] }
+/// })).unwrap();
+/// let reviewed: ConversionReview = deployment::conversion::review(review);
+/// assert_eq!("Success", reviewed.response.unwrap().result.status.unwrap());
+/// ```
+pub fn review(review: ConversionReview) -> ConversionReview {
+    let response: Option<ConversionResponse> = review.request.map(|request| {
+        let desired_api_version: &str = &request.desired_api_version;
+        let converted: Result<Vec<Value>, Error> = request.objects.iter().cloned()
+            .map(|object| convert(object, desired_api_version))
+            .collect();
+        match converted {
+            Ok(converted_objects) => ConversionResponse {
+                uid: request.uid,
+                converted_objects,
+                result: Status { status: Some("Success".to_string()), ..Status::default() },
+            },
+            Err(error) => {
+                debug!("Conversion to '{}' failed: {}", request.desired_api_version, error);
+                ConversionResponse {
+                    uid: request.uid,
+                    converted_objects: Vec::new(),
+                    result: Status { status: Some("Failure".to_string()), message: Some(error.to_string()), ..Status::default() },
+                }
+            }
+        }
+    });
+    ConversionReview {
+        api_version: review.api_version,
+        kind: review.kind,
+        request: None,
+        response,
+    }
+}
+
+/// Converts an `H2O` object to `desired_api_version`. Only the `spec` differs between the API versions -
+/// metadata, status and the rest of the object are kept as they are. Objects already of `desired_api_version`
+/// are returned unchanged.
+///
+/// # Arguments
+/// `object` - `H2O` object of any served API version
+/// `desired_api_version` - API version to convert the object to, e.g. `h2o.ai/v1`
+pub fn convert(mut object: Value, desired_api_version: &str) -> Result<Value, Error> {
+    let api_version: String = object.get("apiVersion").and_then(Value::as_str).unwrap_or_default().to_string();
+    if api_version == desired_api_version {
+        return Ok(object);
+    }
+    let spec: Value = object.get_mut("spec").map(Value::take).unwrap_or(Value::Null);
+    let converted_spec: Value = if api_version == crd::H2O::API_VERSION && desired_api_version == crd_v1::H2O::API_VERSION {
+        serde_json::to_value(crd_v1::H2OSpec::from(serde_json::from_value::<crd::H2OSpec>(spec)?))?
+    } else if api_version == crd_v1::H2O::API_VERSION && desired_api_version == crd::H2O::API_VERSION {
+        serde_json::to_value(crd::H2OSpec::from(serde_json::from_value::<crd_v1::H2OSpec>(spec)?))?
+    } else {
+        return Err(Error::UserError(format!("Unable to convert H2O from '{}' to '{}'. Supported API versions are '{}' and '{}'.",
+                                            api_version, desired_api_version, crd::H2O::API_VERSION, crd_v1::H2O::API_VERSION)));
+    };
+    object["spec"] = converted_spec;
+    object["apiVersion"] = Value::String(desired_api_version.to_string());
+    Ok(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use k8s_openapi::api::core::v1::Toleration;
+    use serde_json::{json, Value};
+
+    use crate::crd::{Auth, AuthMethod, CustomImage, EnvVariable, H2OSpec, ImagePullPolicy, IngressSpec, Resources,
+                     SecurityContextSpec, ServiceAccountSpec, Spreading, SpreadingPolicy, SpreadingTopology, Storage,
+                     StorageDeletionPolicy, Tls};
+    use crate::crd_v1;
+
+    use super::ConversionReview;
+
+    fn full_spec() -> H2OSpec {
+        let mut spec: H2OSpec = H2OSpec::new(3, Some("3.32.0.1".to_string()), Resources::new(2, "4Gi".to_string(), Some(80)),
+                                             Some(CustomImage::new("h2o:custom".to_string(), Some("java -jar h2o.jar".to_string()))));
+        spec.ingress = Some(IngressSpec::new(Some("h2o.example.com".to_string()), None, None, BTreeMap::new()));
+        spec.node_selector.insert("pool".to_string(), "highmem".to_string());
+        spec.tolerations.push(Toleration { key: Some("dedicated".to_string()), ..Toleration::default() });
+        spec.priority_class_name = Some("high-priority".to_string());
+        spec.spreading = Some(Spreading::new(SpreadingPolicy::Required, SpreadingTopology::Zone));
+        spec.env.push(EnvVariable::new("TZ".to_string(), "UTC".to_string()));
+        spec.jvm_args.push("-XX:+UseG1GC".to_string());
+        spec.h2o_args.push("-log_level".to_string());
+        spec.storage = Some(Storage::new("10Gi".to_string(), None, None, StorageDeletionPolicy::Delete));
+        spec.image_repository = Some("registry.example.com/h2o".to_string());
+        spec.image_pull_secrets.push("registry-credentials".to_string());
+        spec.image_pull_policy = Some(ImagePullPolicy::IfNotPresent);
+        spec.security_context = Some(SecurityContextSpec { run_as_user: Some(2000), ..SecurityContextSpec::default() });
+        spec.service_account = Some(ServiceAccountSpec::new(true, None));
+        spec.auth = Some(Auth::new(AuthMethod::Hash, "h2o-realm".to_string()));
+        spec.tls = Some(Tls::new("h2o-keystore".to_string()));
+        spec
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let minimal: H2OSpec = H2OSpec::new(1, Some("latest".to_string()), Resources::new(1, "1Gi".to_string(), None), None);
+        for spec in [minimal, full_spec()].iter() {
+            let v1: crd_v1::H2OSpec = spec.clone().into();
+            assert_eq!(*spec, H2OSpec::from(v1.clone()));
+            assert_eq!(v1, crd_v1::H2OSpec::from(H2OSpec::from(v1.clone())));
+        }
+    }
+
+    #[test]
+    fn test_convert() {
+        let v1beta: Value = json!({
+            "apiVersion": "h2o.ai/v1beta",
+            "kind": "H2O",
+            "metadata": { "name": "h2o-test", "namespace": "default", "resourceVersion": "42" },
+            "spec": serde_json::to_value(full_spec()).unwrap(),
+            "status": { "phase": "Ready", "runningNodes": 3 }
+        });
+        let v1: Value = super::convert(v1beta.clone(), "h2o.ai/v1").unwrap();
+        assert_eq!("h2o.ai/v1", v1["apiVersion"]);
+        assert_eq!(v1beta["metadata"], v1["metadata"]);
+        assert_eq!(v1beta["status"], v1["status"]);
+        assert_eq!("3.32.0.1", v1["spec"]["image"]["version"]);
+        assert_eq!("highmem", v1["spec"]["scheduling"]["nodeSelector"]["pool"]);
+        assert_eq!("h2o-keystore", v1["spec"]["security"]["tls"]["secretName"]);
+        assert_eq!(v1beta, super::convert(v1.clone(), "h2o.ai/v1beta").unwrap());
+        assert_eq!(v1, super::convert(v1.clone(), "h2o.ai/v1").unwrap());
+        assert!(super::convert(v1, "h2o.ai/v2").is_err());
+    }
+
+    #[test]
+    fn test_review() {
+        let review: ConversionReview = serde_json::from_value(json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "ConversionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "desiredAPIVersion": "h2o.ai/v1beta",
+                "objects": [{
+                    "apiVersion": "h2o.ai/v1",
+                    "kind": "H2O",
+                    "metadata": { "name": "h2o-test" },
+                    "spec": { "nodes": 1, "image": { "version": "latest" }, "resources": { "cpu": 1, "memory": "1Gi" } }
+                }]
+            }
+        })).unwrap();
+        let reviewed: Value = serde_json::to_value(super::review(review.clone())).unwrap();
+        assert_eq!("apiextensions.k8s.io/v1", reviewed["apiVersion"]);
+        assert!(reviewed.get("request").is_none());
+        assert_eq!("705ab4f5-6393-11e8-b7cc-42010a800002", reviewed["response"]["uid"]);
+        assert_eq!("Success", reviewed["response"]["result"]["status"]);
+        assert_eq!("latest", reviewed["response"]["convertedObjects"][0]["spec"]["version"]);
+
+        let mut invalid: ConversionReview = review;
+        invalid.request.as_mut().unwrap().objects[0]["spec"]["nodes"] = json!("many");
+        let failed: ConversionReview = super::review(invalid);
+        let response = failed.response.unwrap();
+        assert_eq!(Some("Failure".to_string()), response.result.status);
+        assert!(response.converted_objects.is_empty());
+    }
+}
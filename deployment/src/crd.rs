@@ -1,24 +1,94 @@
 extern crate log;
 
-use kube::{Api, Client, CustomResource};
-use kube::api::{PatchParams};
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::{Affinity, Toleration};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::CustomResource;
+use schemars::gen::SchemaGenerator;
 use schemars::JsonSchema;
+use schemars::schema::{ArrayValidation, InstanceType, NumberValidation, Schema, SchemaObject, StringValidation};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use crate::{Error, finalizer};
+use crate::finalizer;
 
 /// Specification of an H2O cluster in a Kubernetes cluster.
 /// Determines attributes like cluster size, resources (cpu, memory) and pod configuration.
 #[derive(CustomResource, Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
 #[kube(group = "h2o.ai", version = "v1beta", kind = "H2O", status = "H2OStatus", derive = "PartialEq", namespaced)]
 #[kube(shortname = "h2o", namespaced)]
+#[kube(printcolumn = r#"{"name":"Phase", "type":"string", "jsonPath":".status.phase", "description":"Lifecycle phase of the H2O cluster"}"#)]
+#[kube(printcolumn = r#"{"name":"Nodes", "type":"integer", "jsonPath":".spec.nodes", "description":"Number of H2O nodes expected"}"#)]
+#[kube(printcolumn = r#"{"name":"Running", "type":"integer", "jsonPath":".status.runningNodes", "description":"Number of H2O nodes running"}"#)]
+#[kube(printcolumn = r#"{"name":"Leader", "type":"string", "jsonPath":".status.leaderPod", "description":"Pod with the H2O leader node"}"#)]
+#[kube(printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#)]
 pub struct H2OSpec {
+    /// Number of H2O nodes, one H2O node per pod.
+    #[schemars(schema_with = "positive_integer")]
     pub nodes: u32,
+    /// H2O version, used as a tag of the official H2O image. Noop if a custom image is defined.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
     pub resources: Resources,
+    /// Custom image with H2O inside, used instead of the official H2O image.
     #[serde(rename = "customImage", skip_serializing_if = "Option::is_none")]
     pub custom_image: Option<CustomImage>,
+    /// Optional ingress exposing the H2O cluster outside of the Kubernetes cluster.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingress: Option<IngressSpec>,
+    /// Labels of Kubernetes nodes H2O pods must be scheduled on.
+    #[serde(rename = "nodeSelector", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub node_selector: BTreeMap<String, String>,
+    /// Tolerations of H2O pods, allowing them to be scheduled on tainted Kubernetes nodes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(schema_with = "preserve_unknown_fields_array")]
+    pub tolerations: Vec<Toleration>,
+    /// Standard Kubernetes affinity of H2O pods.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "preserve_unknown_fields_object")]
+    pub affinity: Option<Affinity>,
+    /// Name of the priority class of H2O pods.
+    #[serde(rename = "priorityClassName", skip_serializing_if = "Option::is_none")]
+    pub priority_class_name: Option<String>,
+    /// Policy of spreading H2O pods across Kubernetes nodes or zones. H2O pods are not spread if not defined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spreading: Option<Spreading>,
+    /// Additional environment variables of H2O containers. Take precedence over variables set by default.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<EnvVariable>,
+    /// Additional arguments of the JVM running H2O, e.g. `-XX:+UseG1GC`.
+    #[serde(rename = "jvmArgs", default, skip_serializing_if = "Vec::is_empty")]
+    pub jvm_args: Vec<String>,
+    /// Additional arguments of H2O itself, e.g. `-log_level`, `INFO`. Each argument is a separate item.
+    #[serde(rename = "h2oArgs", default, skip_serializing_if = "Vec::is_empty")]
+    pub h2o_args: Vec<String>,
+    /// Optional persistent storage of each H2O node for H2O's ice directory and auto-recovery data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<Storage>,
+    /// Repository of the official H2O images, overriding `h2oai/h2o-open-source-k8s`, e.g. for air-gapped mirrors.
+    /// H2O version is used as a tag. Noop if a custom image is defined.
+    #[serde(rename = "imageRepository", skip_serializing_if = "Option::is_none")]
+    pub image_repository: Option<String>,
+    /// Names of secrets with credentials to pull the H2O image from a private registry.
+    #[serde(rename = "imagePullSecrets", default, skip_serializing_if = "Vec::is_empty")]
+    pub image_pull_secrets: Vec<String>,
+    /// Pull policy of the H2O image. Kubernetes defaults apply if not defined.
+    #[serde(rename = "imagePullPolicy", skip_serializing_if = "Option::is_none")]
+    pub image_pull_policy: Option<ImagePullPolicy>,
+    /// Overrides of the security context H2O pods run with. By default, H2O pods comply with the restricted
+    /// pod security standard, see `security` module for details.
+    #[serde(rename = "securityContext", skip_serializing_if = "Option::is_none")]
+    pub security_context: Option<SecurityContextSpec>,
+    /// Service account H2O pods run under. The namespace's default service account is used if not defined.
+    #[serde(rename = "serviceAccount", skip_serializing_if = "Option::is_none")]
+    pub service_account: Option<ServiceAccountSpec>,
+    /// Optional authentication of H2O's REST API and Flow. Anyone able to reach H2O may use it if not defined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth: Option<Auth>,
+    /// Optional TLS of H2O's REST API and Flow, and of communication between H2O nodes. Plaintext if not defined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<Tls>,
 }
 
 impl H2OSpec {
@@ -31,6 +101,8 @@ impl H2OSpec {
     /// a custom image. The tag must be present in [H2O Docker Hub repository](https://hub.docker.com/r/h2oai/h2o-open-source-k8s)
     /// `resources` - Per-pod resources to be allocated for H2O pods.
     /// `custom_image` - Custom image with H2O inside to be used. User takes full responsibility for image correctness.
+    ///
+    /// Optional parts of the specification, e.g. `ingress`, are left empty and may be set directly.
     pub fn new(
         nodes: u32,
         version: Option<String>,
@@ -42,32 +114,475 @@ impl H2OSpec {
             version,
             resources,
             custom_image,
+            ingress: None,
+            node_selector: BTreeMap::new(),
+            tolerations: Vec::new(),
+            affinity: None,
+            priority_class_name: None,
+            spreading: None,
+            env: Vec::new(),
+            jvm_args: Vec::new(),
+            h2o_args: Vec::new(),
+            storage: None,
+            image_repository: None,
+            image_pull_secrets: Vec::new(),
+            image_pull_policy: None,
+            security_context: None,
+            service_account: None,
+            auth: None,
+            tls: None,
+        }
+    }
+}
+
+/// Persistent storage of an H2O node. Each H2O pod is given its own persistent volume claim, which is bound to
+/// the pod of the same ordinal even if the pod is re-created.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct Storage {
+    /// A Kubernetes-compliant size of the volume, e.g. `10Gi`.
+    #[schemars(schema_with = "quantity")]
+    pub size: String,
+    /// Name of the storage class of the volume. The cluster's default storage class is used if not defined.
+    #[serde(rename = "storageClass", skip_serializing_if = "Option::is_none")]
+    pub storage_class: Option<String>,
+    /// Path the volume is mounted to in H2O containers. Defaults to `/h2o-data`.
+    #[serde(rename = "mountPath", skip_serializing_if = "Option::is_none")]
+    pub mount_path: Option<String>,
+    /// Whether the persistent volume claims are kept or removed once the H2O cluster is deleted. Defaults to `Retain`.
+    #[serde(rename = "deletionPolicy", default = "default_storage_deletion_policy")]
+    pub deletion_policy: StorageDeletionPolicy,
+}
+
+impl Storage {
+    /// Constructor for `Storage`
+    ///
+    /// # Arguments
+    /// `size` - A Kubernetes-compliant size of the volume
+    /// `storage_class` - Optional name of the storage class of the volume
+    /// `mount_path` - Optional path the volume is mounted to in H2O containers
+    /// `deletion_policy` - Whether the persistent volume claims are kept or removed once the H2O cluster is deleted
+    pub fn new(size: String, storage_class: Option<String>, mount_path: Option<String>,
+               deletion_policy: StorageDeletionPolicy) -> Self {
+        Storage {
+            size,
+            storage_class,
+            mount_path,
+            deletion_policy,
+        }
+    }
+}
+
+fn default_storage_deletion_policy() -> StorageDeletionPolicy {
+    StorageDeletionPolicy::Retain
+}
+
+/// Fate of persistent volume claims of H2O pods once the H2O cluster is deleted.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub enum StorageDeletionPolicy {
+    /// Persistent volume claims are kept and re-used by an H2O cluster of the same name.
+    Retain,
+    /// Persistent volume claims are deleted together with the H2O cluster.
+    Delete,
+}
+
+/// Pull policy of the H2O image, as defined by Kubernetes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub enum ImagePullPolicy {
+    /// The image is pulled every time a pod is started.
+    Always,
+    /// The image is pulled only if not already present on the Kubernetes node.
+    IfNotPresent,
+    /// The image is never pulled, it must be present on the Kubernetes node.
+    Never,
+}
+
+/// Authentication of H2O's REST API and Flow. Login configuration, e.g. a realm file with password hashes,
+/// is provided by a secret mounted into H2O pods.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct Auth {
+    /// H2O login module used to authenticate users.
+    pub method: AuthMethod,
+    /// Name of a secret with the login configuration, residing in the same namespace as the H2O cluster.
+    /// The secret is mounted to `/etc/h2o/auth`, so other files referenced by the login configuration (e.g. a keytab)
+    /// may be provided by the same secret.
+    #[serde(rename = "secretName")]
+    pub secret_name: String,
+    /// Key of the login configuration in the secret, passed to H2O as `-login_conf`. Defaults to `login.conf`.
+    #[serde(rename = "loginConfKey", skip_serializing_if = "Option::is_none")]
+    pub login_conf_key: Option<String>,
+    /// Key of the Kerberos configuration in the secret, passed to the JVM as `java.security.krb5.conf`.
+    /// Defaults to `krb5.conf`. Noop unless `method` is `Kerberos`.
+    #[serde(rename = "krb5ConfKey", skip_serializing_if = "Option::is_none")]
+    pub krb5_conf_key: Option<String>,
+    /// Whether Flow authenticates users with a login form instead of HTTP basic authentication. Defaults to `false`.
+    #[serde(rename = "formAuth", default)]
+    pub form_auth: bool,
+}
+
+impl Auth {
+    /// Constructor for `Auth`. Keys of the secret default to `login.conf` and `krb5.conf`, Flow uses
+    /// HTTP basic authentication.
+    ///
+    /// # Arguments
+    /// `method` - H2O login module used to authenticate users
+    /// `secret_name` - Name of a secret with the login configuration
+    pub fn new(method: AuthMethod, secret_name: String) -> Self {
+        Auth {
+            method,
+            secret_name,
+            login_conf_key: None,
+            krb5_conf_key: None,
+            form_auth: false,
+        }
+    }
+}
+
+/// TLS of H2O. Keystore, its password and the internal security configuration are provided by a secret mounted
+/// into H2O pods.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct Tls {
+    /// Name of a secret with the keystore material, residing in the same namespace as the H2O cluster.
+    /// The secret is mounted to `/etc/h2o/tls`.
+    #[serde(rename = "secretName")]
+    pub secret_name: String,
+    /// Key of the Java keystore with H2O's certificate in the secret, passed to H2O as `-jks`. Defaults to `keystore.jks`.
+    #[serde(rename = "keystoreKey", skip_serializing_if = "Option::is_none")]
+    pub keystore_key: Option<String>,
+    /// Key of the keystore password in the secret, passed to H2O as `-jks_pass`. Defaults to `password`.
+    #[serde(rename = "passwordKey", skip_serializing_if = "Option::is_none")]
+    pub password_key: Option<String>,
+    /// Whether communication between H2O nodes is encrypted as well. Defaults to `true`.
+    #[serde(default = "default_internal_tls")]
+    pub internal: bool,
+    /// Key of H2O's internal security configuration in the secret, passed to H2O as `-internal_security_conf`.
+    /// The configuration references the keystore and truststore by their paths in `/etc/h2o/tls`.
+    /// Defaults to `security.properties`. Noop unless `internal` is `true`.
+    #[serde(rename = "internalSecurityConfKey", skip_serializing_if = "Option::is_none")]
+    pub internal_security_conf_key: Option<String>,
+}
+
+impl Tls {
+    /// Constructor for `Tls`. Both H2O's REST API and communication between H2O nodes are encrypted,
+    /// keys of the secret are defaulted.
+    ///
+    /// # Arguments
+    /// `secret_name` - Name of a secret with the keystore material
+    pub fn new(secret_name: String) -> Self {
+        Tls {
+            secret_name,
+            keystore_key: None,
+            password_key: None,
+            internal: true,
+            internal_security_conf_key: None,
         }
     }
 }
+
+fn default_internal_tls() -> bool {
+    true
+}
+
+/// H2O login module, as selected by H2O's `-hash_login`, `-ldap_login` or `-kerberos_login` argument.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub enum AuthMethod {
+    /// Users and password hashes are listed in a Jetty realm file.
+    Hash,
+    /// Users are authenticated against an LDAP server, configured by a JAAS login configuration.
+    Ldap,
+    /// Users are authenticated by Kerberos, configured by a JAAS login configuration.
+    Kerberos,
+}
+
+/// Service account of H2O pods. Either a dedicated service account is created for the H2O cluster, or an existing one
+/// is referenced by its name.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema, Default)]
+pub struct ServiceAccountSpec {
+    /// Whether a dedicated `ServiceAccount`, `Role` and `RoleBinding` named after the H2O cluster are created,
+    /// granting H2O pods exactly the permissions H2O needs. Defaults to `false`.
+    #[serde(default)]
+    pub create: bool,
+    /// Name of an existing service account H2O pods run under. Ignored if `create` is `true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl ServiceAccountSpec {
+    /// Constructor for `ServiceAccountSpec`
+    ///
+    /// # Arguments
+    /// `create` - Whether a dedicated service account with RBAC permissions is created
+    /// `name` - Optional name of an existing service account, ignored if `create` is `true`
+    pub fn new(create: bool, name: Option<String>) -> Self {
+        ServiceAccountSpec { create, name }
+    }
+}
+
+/// Overrides of the default security context of H2O pods. Undefined fields keep their secure defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema, Default)]
+pub struct SecurityContextSpec {
+    /// User ID the H2O container runs as. Defaults to `1000`.
+    #[serde(rename = "runAsUser", default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "id")]
+    pub run_as_user: Option<i64>,
+    /// Group ID the H2O container runs as. Defaults to `1000`.
+    #[serde(rename = "runAsGroup", default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "id")]
+    pub run_as_group: Option<i64>,
+    /// Group owning the H2O data volume, if any. Defaults to `1000`.
+    #[serde(rename = "fsGroup", default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "id")]
+    pub fs_group: Option<i64>,
+    /// Whether Kubernetes refuses to start H2O containers running as root. Defaults to `true`.
+    #[serde(rename = "runAsNonRoot", skip_serializing_if = "Option::is_none")]
+    pub run_as_non_root: Option<bool>,
+    /// Whether the root filesystem of H2O containers is read-only. Defaults to `true`. A writable `/tmp`
+    /// is always provided.
+    #[serde(rename = "readOnlyRootFilesystem", skip_serializing_if = "Option::is_none")]
+    pub read_only_root_filesystem: Option<bool>,
+}
+
+/// A plain environment variable of H2O containers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct EnvVariable {
+    /// Name of the environment variable.
+    pub name: String,
+    /// Value of the environment variable.
+    pub value: String,
+}
+
+impl EnvVariable {
+    /// Constructor for `EnvVariable`
+    ///
+    /// # Arguments
+    /// `name` - Name of the environment variable
+    /// `value` - Value of the environment variable
+    pub fn new(name: String, value: String) -> Self {
+        EnvVariable { name, value }
+    }
+}
+
+/// Spreading of H2O pods across a topology domain. An H2O cluster dies when any single H2O node dies, spreading
+/// H2O pods limits the number of H2O nodes lost when a single Kubernetes node or zone fails.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct Spreading {
+    /// How strictly the H2O pods are spread.
+    pub policy: SpreadingPolicy,
+    /// Topology domain to spread the H2O pods across. Defaults to `Hostname`.
+    #[serde(default = "default_spreading_topology")]
+    pub topology: SpreadingTopology,
+}
+
+impl Spreading {
+    /// Constructor for `Spreading`
+    ///
+    /// # Arguments
+    /// `policy` - How strictly the H2O pods are spread
+    /// `topology` - Topology domain to spread the H2O pods across
+    pub fn new(policy: SpreadingPolicy, topology: SpreadingTopology) -> Self {
+        Spreading { policy, topology }
+    }
+}
+
+fn default_spreading_topology() -> SpreadingTopology {
+    SpreadingTopology::Hostname
+}
+
+/// How strictly H2O pods are spread across a topology domain.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub enum SpreadingPolicy {
+    /// H2O pods are not spread.
+    None,
+    /// H2O pods are spread if possible, otherwise they are scheduled anyway.
+    Preferred,
+    /// H2O pods are only scheduled if they can be spread. Pods may remain pending otherwise.
+    Required,
+}
+
+/// Topology domain H2O pods are spread across.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub enum SpreadingTopology {
+    /// Kubernetes nodes, as distinguished by the `kubernetes.io/hostname` label.
+    Hostname,
+    /// Zones, as distinguished by the `topology.kubernetes.io/zone` label.
+    Zone,
+}
+
+impl SpreadingTopology {
+    /// Well-known label of Kubernetes nodes identifying the topology domain.
+    pub fn topology_key(&self) -> &'static str {
+        match self {
+            SpreadingTopology::Hostname => "kubernetes.io/hostname",
+            SpreadingTopology::Zone => "topology.kubernetes.io/zone",
+        }
+    }
+}
+
+/// Schema of an object validated by Kubernetes itself once embedded into other resources, e.g. an `Affinity`.
+/// Unknown fields are preserved, as `k8s_openapi` types provide no JSON schema.
+pub(crate) fn preserve_unknown_fields_object(_: &mut SchemaGenerator) -> Schema {
+    let mut schema: SchemaObject = SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        ..SchemaObject::default()
+    };
+    schema.extensions.insert("x-kubernetes-preserve-unknown-fields".to_string(), Value::Bool(true));
+    Schema::Object(schema)
+}
+
+/// Schema of an array of objects validated by Kubernetes itself, see `preserve_unknown_fields_object`.
+pub(crate) fn preserve_unknown_fields_array(generator: &mut SchemaGenerator) -> Schema {
+    Schema::Object(SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(ArrayValidation {
+            items: Some(preserve_unknown_fields_object(generator).into()),
+            ..ArrayValidation::default()
+        })),
+        ..SchemaObject::default()
+    })
+}
+
+/// Pattern of Kubernetes quantities, e.g. `4Gi` or `500m`.
+pub const QUANTITY_PATTERN: &str = "^([+-]?[0-9.]+)([eEinumkKMGTP]*[-+]?[0-9]*)$";
+
+/// Schema of a Kubernetes quantity string, see `QUANTITY_PATTERN`.
+fn quantity(_: &mut SchemaGenerator) -> Schema {
+    Schema::Object(SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        string: Some(Box::new(StringValidation {
+            pattern: Some(QUANTITY_PATTERN.to_string()),
+            ..StringValidation::default()
+        })),
+        ..SchemaObject::default()
+    })
+}
+
+/// Schema of an integer within the given inclusive bounds.
+fn bounded_integer(format: &str, minimum: f64, maximum: Option<f64>) -> Schema {
+    Schema::Object(SchemaObject {
+        instance_type: Some(InstanceType::Integer.into()),
+        format: Some(format.to_string()),
+        number: Some(Box::new(NumberValidation {
+            minimum: Some(minimum),
+            maximum,
+            ..NumberValidation::default()
+        })),
+        ..SchemaObject::default()
+    })
+}
+
+/// Schema of a count of at least one, e.g. number of H2O nodes.
+pub(crate) fn positive_integer(_: &mut SchemaGenerator) -> Schema {
+    bounded_integer("uint32", 1.0, None)
+}
+
+/// Schema of a percentage in the <1,100> range.
+fn percentage(_: &mut SchemaGenerator) -> Schema {
+    bounded_integer("uint8", 1.0, Some(100.0))
+}
+
+/// Schema of a non-negative user or group ID.
+fn id(_: &mut SchemaGenerator) -> Schema {
+    bounded_integer("int64", 0.0, None)
+}
+
+/// Observed state of an H2O cluster, maintained by the operator.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema, Default)]
-pub struct H2OStatus{
+pub struct H2OStatus {
+    /// High-level summary of the H2O cluster's lifecycle.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<H2OPhase>,
+    /// Number of H2O nodes (pods) expected to form the cluster.
+    #[serde(rename = "expectedNodes", skip_serializing_if = "Option::is_none")]
+    pub expected_nodes: Option<u32>,
+    /// Number of H2O nodes (pods) up and running. Only the pod with H2O leader node passes the readiness probe,
+    /// running pods are therefore counted instead of ready ones.
+    #[serde(rename = "runningNodes", skip_serializing_if = "Option::is_none")]
+    pub running_nodes: Option<u32>,
+    /// Name of the pod with H2O leader node. Only present once H2O is clustered.
+    #[serde(rename = "leaderPod", skip_serializing_if = "Option::is_none")]
+    pub leader_pod: Option<String>,
+    /// IP address of the pod with H2O leader node. Only present once H2O is clustered.
+    #[serde(rename = "leaderIp", skip_serializing_if = "Option::is_none")]
+    pub leader_ip: Option<String>,
+    /// URL the H2O cluster is reachable on from inside the Kubernetes cluster.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Maximum JVM heap of each H2O node, derived from the memory limit and `memoryPercentage`, e.g. `2Gi`.
+    #[serde(rename = "jvmHeap", skip_serializing_if = "Option::is_none")]
+    pub jvm_heap: Option<String>,
+    /// The `metadata.generation` of the `H2O` resource this status has been computed for.
+    #[serde(rename = "observedGeneration", skip_serializing_if = "Option::is_none")]
+    pub observed_generation: Option<i64>,
+    /// Standard Kubernetes conditions describing the H2O cluster.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<H2OCondition>,
 }
 
+/// Lifecycle phase of an H2O cluster.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub enum H2OPhase {
+    /// Not all the H2O pods are up and running yet.
+    Pending,
+    /// All H2O pods are running, but H2O nodes have not formed a cluster with a leader node yet.
+    Clustering,
+    /// H2O is clustered and the leader node is ready to serve requests.
+    Ready,
+    /// At least one of the H2O pods failed, rendering the whole H2O cluster unusable.
+    Failed,
+    /// The H2O cluster is being deleted.
+    Terminating,
+}
 
-/// Resources allocated by each H2O pod
-/// Limits and requests are always set to the same value in order for H2O operations
-/// tobe reproducible.
+/// A condition of an H2O cluster, following the Kubernetes conventions for conditions.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct H2OCondition {
+    /// Type of the condition, e.g. `Ready`.
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Status of the condition - one of `True`, `False` or `Unknown`.
+    pub status: String,
+    /// Machine-readable reason for the condition's last transition, in CamelCase.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Human-readable details about the condition's last transition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Last time the condition transitioned from one status to another, RFC 3339 formatted.
+    #[serde(rename = "lastTransitionTime", skip_serializing_if = "Option::is_none")]
+    pub last_transition_time: Option<String>,
+}
+
+
+/// Resources allocated by each H2O pod.
+/// Limits and requests are set to the same value by default in order for H2O operations
+/// tobe reproducible. Lower requests may be set by `requests`, e.g. for burstable pods in development environments.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct Resources {
-    /// Number of virtual CPUs allocated to each H2O pod
+    /// Number of virtual CPUs allocated to each H2O pod. Used as a request as well, unless `requests.cpu` is defined.
+    #[schemars(schema_with = "positive_integer")]
     pub cpu: u32,
     /// A Kubernetes-compliant memory string matching the following pattern: `^([+-]?[0-9.]+)([eEinumkKMGTP]*[-+]?[0-9]*)$`.
+    /// Memory limit of each H2O pod, the JVM heap is derived from it. Used as a request as well,
+    /// unless `requests.memory` is defined.
+    #[schemars(schema_with = "quantity")]
     pub memory: String,
     /// Percentage of memory allocated by the H2O JVM inside the docker container running
     /// inside the pod. If not defined, defaults will be used. Unless external XGBoost is always spawned,
     /// there will always be some space required for XGBoost.
-    #[serde(rename = "memoryPercentage", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "memoryPercentage", default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "percentage")]
     pub memory_percentage: Option<u8>,
+    /// Optional requests lower than the limits defined by `cpu` and `memory`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests: Option<ResourceRequests>,
+    /// A Kubernetes-compliant size of ephemeral storage requested and limited for each H2O pod, e.g. for spill files
+    /// in `/tmp`. Unlimited if not defined.
+    #[serde(rename = "ephemeralStorage", default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "quantity")]
+    pub ephemeral_storage: Option<String>,
 }
 
 impl Resources {
-    /// Constructor for `Resources`
+    /// Constructor for `Resources`. Requests equal the limits and ephemeral storage is not limited.
     ///
     /// # Arguments
     /// `cpu` - Number of virtual CPUs allocated to each H2O pod
@@ -78,15 +593,42 @@ impl Resources {
             cpu,
             memory,
             memory_percentage,
+            requests: None,
+            ephemeral_storage: None,
         }
     }
 }
 
+/// Requests of H2O pods differing from their limits. Undefined requests equal the respective limits.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema, Default)]
+pub struct ResourceRequests {
+    /// A Kubernetes-compliant CPU request, e.g. `500m`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "quantity")]
+    pub cpu: Option<String>,
+    /// A Kubernetes-compliant memory request, e.g. `2Gi`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "quantity")]
+    pub memory: Option<String>,
+}
+
+impl ResourceRequests {
+    /// Constructor for `ResourceRequests`
+    ///
+    /// # Arguments
+    /// `cpu` - Optional Kubernetes-compliant CPU request
+    /// `memory` - Optional Kubernetes-compliant memory request
+    pub fn new(cpu: Option<String>, memory: Option<String>) -> Self {
+        ResourceRequests { cpu, memory }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub struct CustomImage {
     /// Full image definition, including repository prefix, image name and tag.
     pub image: String,
-    /// Docker command to be ran when the custom image is started.
+    /// Docker command to be ran when the custom image is started, in the exec form - either a YAML sequence
+    /// or a command line split on whitespace. H2O arguments (`h2oArgs`), if any, are passed as container arguments.
     pub command: Option<String>,
 }
 
@@ -95,12 +637,50 @@ impl CustomImage {
     ///
     /// # Arguments
     /// `image` - Full image definition, including repository prefix, image name and tag.
-    /// `command` - Optional Docker command to be ran when the custom image is started.
+    /// `command` - Optional Docker command to be ran when the custom image is started, in the exec form.
     pub fn new(image: String, command: Option<String>) -> Self {
         CustomImage { image, command }
     }
 }
 
+/// Ingress exposing the H2O cluster under the `/<name>` path.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema, Default)]
+pub struct IngressSpec {
+    /// Hostname the ingress rule applies to. If not set, the rule applies to all inbound HTTP traffic.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Name of a secret with TLS certificate and key to terminate TLS with. The secret must reside
+    /// in the same namespace as the H2O cluster.
+    #[serde(rename = "tlsSecret", skip_serializing_if = "Option::is_none")]
+    pub tls_secret: Option<String>,
+    /// Name of the ingress class of the ingress controller to handle the ingress.
+    #[serde(rename = "ingressClass", skip_serializing_if = "Option::is_none")]
+    pub ingress_class: Option<String>,
+    /// Additional annotations of the ingress, e.g. for ingress controller configuration. Take precedence
+    /// over annotations set by default.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+}
+
+impl IngressSpec {
+    /// Constructor for `IngressSpec`
+    ///
+    /// # Arguments
+    /// `host` - Optional hostname the ingress rule applies to
+    /// `tls_secret` - Optional name of a secret with TLS certificate and key
+    /// `ingress_class` - Optional name of the ingress class
+    /// `annotations` - Additional annotations of the ingress
+    pub fn new(host: Option<String>, tls_secret: Option<String>, ingress_class: Option<String>,
+               annotations: BTreeMap<String, String>) -> Self {
+        IngressSpec {
+            host,
+            tls_secret,
+            ingress_class,
+            annotations,
+        }
+    }
+}
+
 /// Scans `H2O` resources and returns `true` if there is a deletion timestamp present in the resource's
 /// metadata. Returns `false` if there is no deletion timestamp.
 ///
@@ -131,15 +711,22 @@ pub fn has_h2o3_finalizer(h2o: &H2O) -> bool {
     };
 }
 
-pub async fn add_empty_status(client: Client, name: &str, namespace: &str) -> Result<H2O, Error> {
-    let api: Api<H2O> = Api::namespaced(client.clone(), namespace);
-    let mut h2o: H2O = api.get(name).await.unwrap();
-    let status: H2OStatus = H2OStatus::default();
-    h2o.status = Option::Some(status);
-
-    let result: Result<H2O, Error> = api.patch_status(name, &PatchParams::default(), serde_json::to_vec(&h2o)
-        .unwrap()).await
-        .map_err(Error::from);
-
-    return result;
-}
\ No newline at end of file
+/// Creates a controller `OwnerReference` pointing to the given `H2O` resource. Resources carrying such a reference
+/// are garbage collected by Kubernetes once the `H2O` resource is deleted.
+///
+/// Returns `Option::None` if the `H2O` resource has no name or UID assigned, e.g. when it has not been
+/// created in the Kubernetes cluster yet.
+///
+/// # Arguments
+///
+/// `h2o` - The `H2O` resource instance, representing the current state of the resource in Kubernetes cluster.
+pub fn owner_reference(h2o: &H2O) -> Option<OwnerReference> {
+    Some(OwnerReference {
+        api_version: <H2O as k8s_openapi::Resource>::API_VERSION.to_string(),
+        kind: <H2O as k8s_openapi::Resource>::KIND.to_string(),
+        name: h2o.metadata.name.clone()?,
+        uid: h2o.metadata.uid.clone()?,
+        controller: Some(true),
+        block_owner_deletion: Some(true),
+    })
+}
@@ -0,0 +1,431 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::{v1, v1beta1};
+use k8s_openapi::ByteString;
+use kube::{Api, Client};
+use kube::Error as KubeError;
+use log::debug;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::conversion::CONVERSION_PATH;
+use crate::crd::H2O;
+use crate::crd_v1;
+use crate::Error;
+use crate::webhook::{SERVICE_PORT, WebhookService};
+
+/// Header of the generated CRD manifests, marking them as not to be edited by hand.
+pub const MANIFEST_HEADER: &str = "# Generated from the H2O custom resource types by `h2o-operator crd print`. Do not edit manually.\n";
+
+/// Revision of the H2O CRD generated by this crate. Must be incremented with every change of the generated CRD,
+/// so operators upgrade CRDs installed by older operators, while never downgrading CRDs installed by newer ones.
+pub const CRD_REVISION: u32 = 4;
+/// Annotation of the H2O CRD holding its `CRD_REVISION`.
+pub const REVISION_ANNOTATION: &str = "h2o.ai/crd-revision";
+
+/// API versions of the `CustomResourceDefinition` resource the H2O CRD may be rendered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrdApiVersion {
+    /// `apiextensions.k8s.io/v1`, served since Kubernetes 1.16
+    V1,
+    /// `apiextensions.k8s.io/v1beta1`, removed in Kubernetes 1.22. Used by the OLM bundle for older OpenShift clusters.
+    V1beta1,
+}
+
+/// The H2O `CustomResourceDefinition` without a conversion webhook, serving the `v1beta` API version only,
+/// see `h2o_crd_with`.
+pub fn h2o_crd() -> v1::CustomResourceDefinition {
+    h2o_crd_with(None)
+}
+
+/// The H2O `CustomResourceDefinition`, generated from the `H2OSpec` and `H2OStatus` types, including
+/// validation bounds, defaults and printer columns. Annotated by `REVISION_ANNOTATION`.
+///
+/// The `v1beta` (`crd::H2OSpec`) API version is always served and stored. The `v1` (`crd_v1::H2OSpec`) API version
+/// is only served with a conversion `webhook`, which converts resources between the versions. Without the webhook,
+/// the conversion strategy is `None`, as a `v1` version served by an unreachable webhook would break every client
+/// preferring it, including `kubectl`.
+///
+/// # Arguments
+/// `webhook` - Service the conversion webhook is reachable through, if served
+pub fn h2o_crd_with(webhook: Option<&WebhookService>) -> v1::CustomResourceDefinition {
+    let mut crd: v1::CustomResourceDefinition = H2O::crd();
+    crd.metadata.annotations.get_or_insert_with(BTreeMap::new)
+        .insert(REVISION_ANNOTATION.to_string(), CRD_REVISION.to_string());
+    let webhook: &WebhookService = match webhook {
+        Some(webhook) => webhook,
+        None => {
+            crd.spec.conversion = Some(v1::CustomResourceConversion {
+                strategy: "None".to_string(),
+                webhook: None,
+            });
+            return crd;
+        }
+    };
+    for mut version in crd_v1::H2O::crd().spec.versions {
+        version.storage = false;
+        crd.spec.versions.push(version);
+    }
+    crd.spec.conversion = Some(v1::CustomResourceConversion {
+        strategy: "Webhook".to_string(),
+        webhook: Some(v1::WebhookConversion {
+            client_config: Some(v1::WebhookClientConfig {
+                ca_bundle: webhook.ca_bundle.clone().map(ByteString),
+                service: Some(v1::ServiceReference {
+                    name: webhook.service.clone(),
+                    namespace: webhook.namespace.clone(),
+                    path: Some(CONVERSION_PATH.to_string()),
+                    port: Some(SERVICE_PORT),
+                }),
+                url: None,
+            }),
+            conversion_review_versions: vec!["v1".to_string(), "v1beta1".to_string()],
+        }),
+    });
+    crd
+}
+
+/// The H2O `CustomResourceDefinition` generated by `h2o_crd_with`, converted to `apiextensions.k8s.io/v1beta1`.
+/// Schema, subresources and printer columns shared by all the versions are defined once for the whole resource,
+/// as `v1beta1` refuses identical per-version definitions.
+///
+/// # Arguments
+/// `webhook` - Service the conversion webhook is reachable through, if served
+pub fn h2o_crd_v1beta1(webhook: Option<&WebhookService>) -> Result<v1beta1::CustomResourceDefinition, Error> {
+    let crd: v1::CustomResourceDefinition = h2o_crd_with(webhook);
+    let mut versions: Vec<v1beta1::CustomResourceDefinitionVersion> = Vec::with_capacity(crd.spec.versions.len());
+    for version in crd.spec.versions.iter() {
+        versions.push(v1beta1::CustomResourceDefinitionVersion {
+            name: version.name.clone(),
+            served: version.served,
+            storage: version.storage,
+            deprecated: version.deprecated,
+            deprecation_warning: version.deprecation_warning.clone(),
+            schema: version.schema.as_ref().map(convert).transpose()?,
+            subresources: version.subresources.as_ref().map(convert).transpose()?,
+            additional_printer_columns: version.additional_printer_columns.as_ref().map(|columns| columns.iter()
+                .map(|column| v1beta1::CustomResourceColumnDefinition {
+                    json_path: column.json_path.clone(),
+                    description: column.description.clone(),
+                    format: column.format.clone(),
+                    name: column.name.clone(),
+                    priority: column.priority,
+                    type_: column.type_.clone(),
+                })
+                .collect()),
+        });
+    }
+
+    let conversion: Option<v1beta1::CustomResourceConversion> = match crd.spec.conversion {
+        Some(conversion) => Some(v1beta1::CustomResourceConversion {
+            strategy: conversion.strategy,
+            conversion_review_versions: conversion.webhook.as_ref()
+                .map(|webhook| webhook.conversion_review_versions.clone()),
+            webhook_client_config: conversion.webhook.and_then(|webhook| webhook.client_config)
+                .as_ref().map(convert).transpose()?,
+        }),
+        None => None,
+    };
+    let validation = shared(&mut versions, |version| &mut version.schema);
+    let subresources = shared(&mut versions, |version| &mut version.subresources);
+    let additional_printer_columns = shared(&mut versions, |version| &mut version.additional_printer_columns);
+
+    Ok(v1beta1::CustomResourceDefinition {
+        metadata: crd.metadata,
+        spec: v1beta1::CustomResourceDefinitionSpec {
+            group: crd.spec.group,
+            names: convert(&crd.spec.names)?,
+            scope: crd.spec.scope,
+            // Defaults in the schema are only applied to CRDs not preserving unknown fields
+            preserve_unknown_fields: Some(false),
+            conversion,
+            validation,
+            subresources,
+            additional_printer_columns,
+            versions: Some(versions),
+            ..v1beta1::CustomResourceDefinitionSpec::default()
+        },
+        status: None,
+    })
+}
+
+/// YAML manifest of the H2O `CustomResourceDefinition` of given `api_version`, prefixed by `MANIFEST_HEADER`.
+///
+/// # Arguments
+/// `api_version` - API version of the `CustomResourceDefinition` resource to render
+/// `webhook` - Service the conversion webhook is reachable through, if served, see `h2o_crd_with`
+///
+/// # Examples
+///
+/// ```
+/// use deployment::crd_manifest::CrdApiVersion;
+/// let manifest: String = deployment::crd_manifest::h2o_crd_yaml(CrdApiVersion::V1, Option::None).unwrap();
+/// assert!(manifest.contains("name: h2os.h2o.ai"));
+/// ```
+pub fn h2o_crd_yaml(api_version: CrdApiVersion, webhook: Option<&WebhookService>) -> Result<String, Error> {
+    let manifest: String = match api_version {
+        CrdApiVersion::V1 => serde_yaml::to_string(&h2o_crd_with(webhook))?,
+        CrdApiVersion::V1beta1 => serde_yaml::to_string(&h2o_crd_v1beta1(webhook)?)?,
+    };
+    Ok(format!("{}{}", MANIFEST_HEADER, manifest))
+}
+
+/// Outcome of `install`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrdInstallation {
+    /// The H2O CRD was not present and has been created.
+    Created,
+    /// An older revision of the H2O CRD has been upgraded to `CRD_REVISION`.
+    Upgraded(u32),
+    /// The H2O CRD of `CRD_REVISION` was present with a different conversion webhook, or with one not served
+    /// by the operator any more, and has been updated.
+    Reconfigured,
+    /// The H2O CRD of `CRD_REVISION` is already present.
+    UpToDate,
+    /// A newer revision of the H2O CRD is present, presumably installed by a newer operator. It is left untouched.
+    NewerPresent(u32),
+}
+
+/// Makes sure the H2O CRD is present in the Kubernetes cluster. The CRD is created if missing and upgraded
+/// if its revision is older than `CRD_REVISION`. CRDs without the `REVISION_ANNOTATION`, e.g. applied manually from
+/// manifests of older releases, are considered the oldest. A CRD of `CRD_REVISION` is updated if its conversion webhook
+/// differs from the given one. Once present, waits until the CRD is established, so `H2O` resources may be served.
+///
+/// # Arguments
+/// `client` - Client with permissions to get, create and patch `CustomResourceDefinition`s
+/// `webhook` - Service the conversion webhook served by the operator is reachable through, if served
+/// `timeout` - Maximum time to wait for the H2O CRD to be established
+///
+/// # Errors
+///
+/// Returns `Error::KubeError` with code 403 if the client lacks permissions to manage CRDs,
+/// `Error::Timeout` if the CRD is not established within the given `timeout`, and `Error::UserError` if no `webhook`
+/// is served, but the present CRD converts resources by one, see `verify_conversion`.
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use std::time::Duration;
+/// use kube::Client;
+/// use deployment::webhook::WebhookService;
+/// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
+/// let webhook: WebhookService = WebhookService::new(namespace, "h2o-operator".to_string(), Option::None);
+/// deployment::crd_manifest::install(client, Option::Some(&webhook), Duration::from_secs(30)).await.unwrap();
+/// }
+/// ```
+pub async fn install(client: Client, webhook: Option<&WebhookService>, timeout: Duration) -> Result<CrdInstallation, Error> {
+    let api: Api<v1::CustomResourceDefinition> = Api::all(client);
+    let crd: v1::CustomResourceDefinition = h2o_crd_with(webhook);
+    let name: String = crd.metadata.name.clone().unwrap_or_default();
+
+    let installation: CrdInstallation = match api.get(&name).await {
+        Ok(present) => {
+            if webhook.is_none() && requires_webhook(&present) {
+                return Err(unserved_webhook(&name));
+            }
+            let revision: u32 = revision_of(&present);
+            if revision < CRD_REVISION {
+                CrdInstallation::Upgraded(revision)
+            } else if revision > CRD_REVISION {
+                CrdInstallation::NewerPresent(revision)
+            } else if conversion_outdated(&present, webhook) {
+                CrdInstallation::Reconfigured
+            } else {
+                CrdInstallation::UpToDate
+            }
+        }
+        Err(KubeError::Api(error_response)) if error_response.code == 404 => CrdInstallation::Created,
+        Err(error) => return Err(error.into()),
+    };
+    if matches!(installation, CrdInstallation::Created | CrdInstallation::Upgraded(_) | CrdInstallation::Reconfigured) {
+        crate::apply::apply(&api, &name, &crd).await?;
+    }
+    debug!("H2O CRD '{}': {:?}", name, installation);
+
+    match tokio::time::timeout(timeout, wait_established(&api, &name)).await {
+        Ok(result) => result.map(|_| installation),
+        Err(_) => Err(Error::Timeout(format!("CRD '{}' not established within {} seconds.", name, timeout.as_secs()))),
+    }
+}
+
+/// Makes sure the present H2O CRD, e.g. one managed externally, does not require a conversion webhook the operator
+/// does not serve. Conversion of `H2O` resources would fail otherwise, and so would any client using the `v1` API version.
+/// A missing CRD is not an error.
+///
+/// # Arguments
+/// `client` - Client with permissions to get `CustomResourceDefinition`s
+/// `webhook` - Service the conversion webhook served by the operator is reachable through, if served
+///
+/// # Errors
+///
+/// Returns `Error::UserError` if no `webhook` is served, but the present CRD converts resources by one.
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use kube::Client;
+/// let (client, _): (Client, String) = deployment::client::try_default().await.unwrap();
+/// deployment::crd_manifest::verify_conversion(client, Option::None).await.unwrap();
+/// }
+/// ```
+pub async fn verify_conversion(client: Client, webhook: Option<&WebhookService>) -> Result<(), Error> {
+    let api: Api<v1::CustomResourceDefinition> = Api::all(client);
+    let name: String = h2o_crd().metadata.name.unwrap_or_default();
+    match api.get(&name).await {
+        Ok(present) if webhook.is_none() && requires_webhook(&present) => Err(unserved_webhook(&name)),
+        Ok(_) => Ok(()),
+        Err(KubeError::Api(error_response)) if error_response.code == 404 => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Whether the CRD converts resources between API versions by a webhook.
+fn requires_webhook(crd: &v1::CustomResourceDefinition) -> bool {
+    matches!(crd.spec.conversion.as_ref(), Some(conversion) if conversion.strategy == "Webhook")
+}
+
+/// Error of a CRD of given `name` requiring a conversion webhook not served by the operator.
+fn unserved_webhook(name: &str) -> Error {
+    Error::UserError(format!("The CRD '{}' converts H2O resources by a conversion webhook, which this operator does not serve. \
+    Either start the operator with '--webhook_cert' and '--webhook_key', or replace the CRD by one without the conversion webhook, \
+    e.g. 'h2o-operator crd print | kubectl apply --server-side --force-conflicts -f -'.", name))
+}
+
+/// Revision of a present H2O CRD, taken from its `REVISION_ANNOTATION`. Zero if not annotated.
+fn revision_of(crd: &v1::CustomResourceDefinition) -> u32 {
+    crd.metadata.annotations.as_ref()
+        .and_then(|annotations| annotations.get(REVISION_ANNOTATION))
+        .and_then(|revision| revision.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Whether the conversion webhook of a present H2O CRD points to a service other than the given `webhook`, or is verified
+/// with a different CA bundle. CA bundles are only compared if defined by the `webhook`, as they may be injected externally.
+/// Without a `webhook`, a CRD is outdated if it still requires one.
+fn conversion_outdated(crd: &v1::CustomResourceDefinition, webhook: Option<&WebhookService>) -> bool {
+    let webhook: &WebhookService = match webhook {
+        Some(webhook) => webhook,
+        None => return requires_webhook(crd),
+    };
+    let client_config: Option<&v1::WebhookClientConfig> = crd.spec.conversion.as_ref()
+        .and_then(|conversion| conversion.webhook.as_ref())
+        .and_then(|conversion_webhook| conversion_webhook.client_config.as_ref());
+    let service_matches: bool = matches!(client_config.and_then(|config| config.service.as_ref()),
+        Some(service) if service.namespace == webhook.namespace && service.name == webhook.service);
+    let ca_bundle_matches: bool = match &webhook.ca_bundle {
+        Some(ca_bundle) => matches!(client_config.and_then(|config| config.ca_bundle.as_ref()),
+            Some(present) if &present.0 == ca_bundle),
+        None => true,
+    };
+    !(service_matches && ca_bundle_matches)
+}
+
+/// Whether the CRD has the `Established` condition, meaning its resources are served by the API server.
+fn is_established(crd: &v1::CustomResourceDefinition) -> bool {
+    crd.status.as_ref()
+        .and_then(|status| status.conditions.as_ref())
+        .into_iter()
+        .flatten()
+        .any(|condition| condition.type_ == "Established" && condition.status == "True")
+}
+
+/// Polls the CRD of given `name` until it is established, without any time limit.
+async fn wait_established(api: &Api<v1::CustomResourceDefinition>, name: &str) -> Result<(), Error> {
+    while !is_established(&api.get(name).await?) {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    Ok(())
+}
+
+/// Converts between the `v1` and `v1beta1` variants of the same apiextensions type, which share their serialized form.
+fn convert<A: Serialize, B: DeserializeOwned>(value: &A) -> Result<B, Error> {
+    Ok(serde_json::from_value(serde_json::to_value(value)?)?)
+}
+
+/// Takes a per-version definition out of the `versions` if it is identical for all of them, so it may be
+/// defined for the whole resource instead. Otherwise the per-version definitions are kept and `Option::None` is returned.
+fn shared<T: Clone + PartialEq>(versions: &mut [v1beta1::CustomResourceDefinitionVersion],
+                                field: fn(&mut v1beta1::CustomResourceDefinitionVersion) -> &mut Option<T>) -> Option<T> {
+    let first: Option<T> = versions.first_mut().and_then(|version| field(version).clone());
+    if first.is_none() || !versions.iter_mut().all(|version| *field(version) == first) {
+        return None;
+    }
+    versions.iter_mut().for_each(|version| *field(version) = None);
+    first
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::{v1, v1beta1};
+    use serde_json::json;
+
+    use crate::webhook::WebhookService;
+
+    #[test]
+    fn test_v1beta1() {
+        let crd: v1beta1::CustomResourceDefinition = super::h2o_crd_v1beta1(Some(&WebhookService::default())).unwrap();
+        assert_eq!(Some(false), crd.spec.preserve_unknown_fields);
+        assert!(crd.spec.validation.is_none());
+        assert_eq!(".status.phase", crd.spec.additional_printer_columns.unwrap()[0].json_path);
+        let version: &v1beta1::CustomResourceDefinitionVersion = &crd.spec.versions.as_ref().unwrap()[0];
+        assert!(version.schema.is_some());
+        assert!(version.additional_printer_columns.is_none());
+        let conversion: &v1beta1::CustomResourceConversion = crd.spec.conversion.as_ref().unwrap();
+        assert_eq!("Webhook", conversion.strategy);
+        assert_eq!(Some("/convert".to_string()), conversion.webhook_client_config.as_ref().unwrap().service.as_ref().unwrap().path);
+
+        let without_webhook: v1beta1::CustomResourceDefinition = super::h2o_crd_v1beta1(None).unwrap();
+        assert_eq!("None", without_webhook.spec.conversion.unwrap().strategy);
+        assert_eq!(1, without_webhook.spec.versions.unwrap().len());
+        assert!(without_webhook.spec.validation.is_some());
+    }
+
+    #[test]
+    fn test_versions() {
+        let crd: v1::CustomResourceDefinition = super::h2o_crd_with(Some(&WebhookService::default()));
+        let versions: Vec<(&str, bool, bool)> = crd.spec.versions.iter()
+            .map(|version| (version.name.as_str(), version.served, version.storage))
+            .collect();
+        assert_eq!(vec![("v1beta", true, true), ("v1", true, false)], versions);
+        assert!(super::requires_webhook(&crd));
+
+        let without_webhook: v1::CustomResourceDefinition = super::h2o_crd();
+        let versions: Vec<&str> = without_webhook.spec.versions.iter().map(|version| version.name.as_str()).collect();
+        assert_eq!(vec!["v1beta"], versions);
+        assert_eq!("None", without_webhook.spec.conversion.as_ref().unwrap().strategy);
+        assert!(!super::requires_webhook(&without_webhook));
+    }
+
+    #[test]
+    fn test_conversion_outdated() {
+        let webhook: WebhookService = WebhookService::new("h2o".to_string(), "h2o-operator".to_string(), None);
+        let crd: v1::CustomResourceDefinition = super::h2o_crd_with(Some(&webhook));
+        assert!(!super::conversion_outdated(&crd, Some(&webhook)));
+        assert!(super::conversion_outdated(&super::h2o_crd(), Some(&webhook)));
+        assert!(super::conversion_outdated(&crd, None));
+        assert!(!super::conversion_outdated(&super::h2o_crd(), None));
+        let with_ca: WebhookService = WebhookService::new("h2o".to_string(), "h2o-operator".to_string(), Some(b"ca".to_vec()));
+        assert!(super::conversion_outdated(&crd, Some(&with_ca)));
+        assert!(!super::conversion_outdated(&super::h2o_crd_with(Some(&with_ca)), Some(&with_ca)));
+        assert!(!super::conversion_outdated(&super::h2o_crd_with(Some(&with_ca)), Some(&webhook)));
+    }
+
+    #[test]
+    fn test_revision() {
+        let crd: v1::CustomResourceDefinition = super::h2o_crd();
+        assert_eq!(super::CRD_REVISION, super::revision_of(&crd));
+        let unannotated: v1::CustomResourceDefinition = serde_json::from_value(json!({
+            "metadata": { "name": "h2os.h2o.ai" },
+            "spec": { "group": "h2o.ai", "names": { "kind": "H2O", "plural": "h2os" }, "scope": "Namespaced", "versions": [] },
+            "status": { "conditions": [{ "type": "Established", "status": "True" }] }
+        })).unwrap();
+        assert_eq!(0, super::revision_of(&unannotated));
+        assert!(super::is_established(&unannotated));
+        assert!(!super::is_established(&crd));
+    }
+}
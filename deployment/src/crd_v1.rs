@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::{Affinity, Toleration};
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::crd;
+use crate::crd::{Auth, CustomImage, EnvVariable, H2OStatus, ImagePullPolicy, IngressSpec, Resources,
+                 SecurityContextSpec, ServiceAccountSpec, Spreading, Storage, Tls};
+
+/// Specification of an H2O cluster in a Kubernetes cluster, `h2o.ai/v1` API version. Carries the same information
+/// as `v1beta`, with image, scheduling and security settings grouped into their own sections.
+/// Converted from and to the stored `v1beta` version by the operator's conversion webhook.
+#[derive(CustomResource, Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[kube(group = "h2o.ai", version = "v1", kind = "H2O", status = "H2OStatus", derive = "PartialEq", namespaced)]
+#[kube(shortname = "h2o")]
+#[kube(printcolumn = r#"{"name":"Phase", "type":"string", "jsonPath":".status.phase", "description":"Lifecycle phase of the H2O cluster"}"#)]
+#[kube(printcolumn = r#"{"name":"Nodes", "type":"integer", "jsonPath":".spec.nodes", "description":"Number of H2O nodes expected"}"#)]
+#[kube(printcolumn = r#"{"name":"Running", "type":"integer", "jsonPath":".status.runningNodes", "description":"Number of H2O nodes running"}"#)]
+#[kube(printcolumn = r#"{"name":"Leader", "type":"string", "jsonPath":".status.leaderPod", "description":"Pod with the H2O leader node"}"#)]
+#[kube(printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#)]
+pub struct H2OSpec {
+    /// Number of H2O nodes, one H2O node per pod.
+    #[schemars(schema_with = "crate::crd::positive_integer")]
+    pub nodes: u32,
+    /// Image with H2O inside - either the official H2O image of given version, or a custom one.
+    pub image: Image,
+    pub resources: Resources,
+    /// Placement of H2O pods onto Kubernetes nodes.
+    #[serde(default, skip_serializing_if = "Scheduling::is_empty")]
+    pub scheduling: Scheduling,
+    /// Additional environment variables of H2O containers. Take precedence over variables set by default.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<EnvVariable>,
+    /// Additional arguments of the JVM running H2O, e.g. `-XX:+UseG1GC`.
+    #[serde(rename = "jvmArgs", default, skip_serializing_if = "Vec::is_empty")]
+    pub jvm_args: Vec<String>,
+    /// Additional arguments of H2O itself, e.g. `-log_level`, `INFO`. Each argument is a separate item.
+    #[serde(rename = "h2oArgs", default, skip_serializing_if = "Vec::is_empty")]
+    pub h2o_args: Vec<String>,
+    /// Optional ingress exposing the H2O cluster outside of the Kubernetes cluster.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ingress: Option<IngressSpec>,
+    /// Optional persistent storage of each H2O node for H2O's ice directory and auto-recovery data.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<Storage>,
+    /// Security context, identity, authentication and TLS of H2O pods.
+    #[serde(default, skip_serializing_if = "Security::is_empty")]
+    pub security: Security,
+}
+
+/// Image H2O pods run. The official H2O image `h2oai/h2o-open-source-k8s:<version>` is used unless `custom` is defined.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema, Default)]
+pub struct Image {
+    /// H2O version, used as a tag of the official H2O image. Noop if a custom image is defined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Repository of the official H2O images, overriding `h2oai/h2o-open-source-k8s`, e.g. for air-gapped mirrors.
+    /// Noop if a custom image is defined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// Custom image with H2O inside, used instead of the official H2O image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom: Option<CustomImage>,
+    /// Names of secrets with credentials to pull the H2O image from a private registry.
+    #[serde(rename = "pullSecrets", default, skip_serializing_if = "Vec::is_empty")]
+    pub pull_secrets: Vec<String>,
+    /// Pull policy of the H2O image. Kubernetes defaults apply if not defined.
+    #[serde(rename = "pullPolicy", skip_serializing_if = "Option::is_none")]
+    pub pull_policy: Option<ImagePullPolicy>,
+}
+
+/// Placement of H2O pods. Kubernetes schedules H2O pods freely if empty.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema, Default)]
+pub struct Scheduling {
+    /// Labels of Kubernetes nodes H2O pods must be scheduled on.
+    #[serde(rename = "nodeSelector", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub node_selector: BTreeMap<String, String>,
+    /// Tolerations of H2O pods, allowing them to be scheduled on tainted Kubernetes nodes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(schema_with = "crate::crd::preserve_unknown_fields_array")]
+    pub tolerations: Vec<Toleration>,
+    /// Standard Kubernetes affinity of H2O pods.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(schema_with = "crate::crd::preserve_unknown_fields_object")]
+    pub affinity: Option<Affinity>,
+    /// Name of the priority class of H2O pods.
+    #[serde(rename = "priorityClassName", skip_serializing_if = "Option::is_none")]
+    pub priority_class_name: Option<String>,
+    /// Policy of spreading H2O pods across Kubernetes nodes or zones. H2O pods are not spread if not defined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spreading: Option<Spreading>,
+}
+
+impl Scheduling {
+    /// Whether no scheduling constraints are defined.
+    pub fn is_empty(&self) -> bool {
+        *self == Scheduling::default()
+    }
+}
+
+/// Security settings of H2O pods. H2O pods comply with the restricted pod security standard and H2O is reachable
+/// without authentication over plaintext if empty.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema, Default)]
+pub struct Security {
+    /// Overrides of the security context H2O pods run with, see `security` module for the defaults.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<SecurityContextSpec>,
+    /// Service account H2O pods run under. The namespace's default service account is used if not defined.
+    #[serde(rename = "serviceAccount", skip_serializing_if = "Option::is_none")]
+    pub service_account: Option<ServiceAccountSpec>,
+    /// Optional authentication of H2O's REST API and Flow. Anyone able to reach H2O may use it if not defined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth: Option<Auth>,
+    /// Optional TLS of H2O's REST API and Flow, and of communication between H2O nodes. Plaintext if not defined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<Tls>,
+}
+
+impl Security {
+    /// Whether no security settings are defined.
+    pub fn is_empty(&self) -> bool {
+        *self == Security::default()
+    }
+}
+
+impl From<crd::H2OSpec> for H2OSpec {
+    /// Regroups a `v1beta` specification. No information is lost.
+    fn from(spec: crd::H2OSpec) -> Self {
+        H2OSpec {
+            nodes: spec.nodes,
+            image: Image {
+                version: spec.version,
+                repository: spec.image_repository,
+                custom: spec.custom_image,
+                pull_secrets: spec.image_pull_secrets,
+                pull_policy: spec.image_pull_policy,
+            },
+            resources: spec.resources,
+            scheduling: Scheduling {
+                node_selector: spec.node_selector,
+                tolerations: spec.tolerations,
+                affinity: spec.affinity,
+                priority_class_name: spec.priority_class_name,
+                spreading: spec.spreading,
+            },
+            env: spec.env,
+            jvm_args: spec.jvm_args,
+            h2o_args: spec.h2o_args,
+            ingress: spec.ingress,
+            storage: spec.storage,
+            security: Security {
+                context: spec.security_context,
+                service_account: spec.service_account,
+                auth: spec.auth,
+                tls: spec.tls,
+            },
+        }
+    }
+}
+
+impl From<H2OSpec> for crd::H2OSpec {
+    /// Flattens a `v1` specification back to `v1beta`. No information is lost.
+    fn from(spec: H2OSpec) -> Self {
+        crd::H2OSpec {
+            nodes: spec.nodes,
+            version: spec.image.version,
+            resources: spec.resources,
+            custom_image: spec.image.custom,
+            ingress: spec.ingress,
+            node_selector: spec.scheduling.node_selector,
+            tolerations: spec.scheduling.tolerations,
+            affinity: spec.scheduling.affinity,
+            priority_class_name: spec.scheduling.priority_class_name,
+            spreading: spec.scheduling.spreading,
+            env: spec.env,
+            jvm_args: spec.jvm_args,
+            h2o_args: spec.h2o_args,
+            storage: spec.storage,
+            image_repository: spec.image.repository,
+            image_pull_secrets: spec.image.pull_secrets,
+            image_pull_policy: spec.image.pull_policy,
+            security_context: spec.security.context,
+            service_account: spec.security.service_account,
+            auth: spec.security.auth,
+            tls: spec.security.tls,
+        }
+    }
+}
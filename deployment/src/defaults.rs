@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crd::{H2OSpec, Spreading};
+use crate::Error;
+use crate::quantity::DEFAULT_MEMORY_PERCENTAGE;
+
+/// Operator-level defaults and policy filled into `H2O` resources missing them, e.g. by the mutating admission webhook,
+/// so the stored resources show the effective specification. Values defined by the resources themselves always win.
+///
+/// Read from a YAML document, typically a mounted `ConfigMap`:
+///
+/// ```yaml
+/// memoryPercentage: 80
+/// version: "3.32.0.1"
+/// spreading:
+///   policy: Preferred
+///   topology: Zone
+/// nodeSelector:
+///   pool: h2o
+/// labels:
+///   cost-center: data-science
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Defaults {
+    /// Percentage of the memory limit allocated by the JVM heap, unless `resources.memoryPercentage` is defined.
+    #[serde(rename = "memoryPercentage", default = "default_memory_percentage")]
+    pub memory_percentage: u8,
+    /// H2O version, unless either `version` or `customImage` is defined.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Spreading of H2O pods, unless `spreading` is defined.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spreading: Option<Spreading>,
+    /// Labels of Kubernetes nodes H2O pods are scheduled on, e.g. a dedicated node pool, unless `nodeSelector` is defined.
+    #[serde(rename = "nodeSelector", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub node_selector: BTreeMap<String, String>,
+    /// Labels of `H2O` resources, e.g. a cost center. Only labels not already present are added.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+fn default_memory_percentage() -> u8 {
+    DEFAULT_MEMORY_PERCENTAGE
+}
+
+impl Default for Defaults {
+    /// `DEFAULT_MEMORY_PERCENTAGE` only, with no other defaults or policy.
+    fn default() -> Self {
+        Defaults {
+            memory_percentage: DEFAULT_MEMORY_PERCENTAGE,
+            version: None,
+            spreading: None,
+            node_selector: BTreeMap::new(),
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+impl Defaults {
+    /// Parses defaults from a YAML document. Missing fields are empty, the memory percentage defaults to
+    /// `DEFAULT_MEMORY_PERCENTAGE`.
+    ///
+    /// # Arguments
+    /// `yaml` - YAML document with the defaults
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::TemplateSerializationError` if the document is malformed, and `Error::UserError` if the memory
+    /// percentage is out of the <1,100> range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deployment::defaults::Defaults;
+    /// let defaults: Defaults = Defaults::from_yaml("version: latest").unwrap();
+    /// assert_eq!(Some("latest".to_string()), defaults.version);
+    /// assert_eq!(deployment::quantity::DEFAULT_MEMORY_PERCENTAGE, defaults.memory_percentage);
+    /// ```
+    pub fn from_yaml(yaml: &str) -> Result<Self, Error> {
+        let defaults: Defaults = serde_yaml::from_str(yaml)?;
+        if defaults.memory_percentage == 0 || defaults.memory_percentage > 100 {
+            return Err(Error::UserError(format!("Default memory percentage ('memoryPercentage') must be within the <1,100> range, got {}.",
+                                                defaults.memory_percentage)));
+        }
+        Ok(defaults)
+    }
+
+    /// Fills the defaults into the parts of the `specification` not defined. Returns whether the specification changed.
+    ///
+    /// # Arguments
+    /// `specification` - Specification of the H2O cluster to complete
+    pub fn apply(&self, specification: &mut H2OSpec) -> bool {
+        let original: H2OSpec = specification.clone();
+        specification.resources.memory_percentage.get_or_insert(self.memory_percentage);
+        if specification.version.is_none() && specification.custom_image.is_none() {
+            specification.version = self.version.clone();
+        }
+        if specification.spreading.is_none() {
+            specification.spreading = self.spreading;
+        }
+        if specification.node_selector.is_empty() {
+            specification.node_selector = self.node_selector.clone();
+        }
+        *specification != original
+    }
+
+    /// Adds the default labels not present in `labels`. Returns whether any label was added.
+    ///
+    /// # Arguments
+    /// `labels` - Labels of an `H2O` resource
+    pub fn apply_labels(&self, labels: &mut BTreeMap<String, String>) -> bool {
+        let mut added: bool = false;
+        for (key, value) in self.labels.iter() {
+            if !labels.contains_key(key) {
+                labels.insert(key.clone(), value.clone());
+                added = true;
+            }
+        }
+        added
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::crd::{CustomImage, H2OSpec, Resources, Spreading, SpreadingPolicy, SpreadingTopology};
+
+    use super::Defaults;
+
+    fn defaults() -> Defaults {
+        Defaults::from_yaml(r#"
+memoryPercentage: 80
+version: "3.32.0.1"
+spreading:
+  policy: Preferred
+nodeSelector:
+  pool: h2o
+labels:
+  cost-center: data-science
+"#).unwrap()
+    }
+
+    #[test]
+    fn test_from_yaml() {
+        let defaults: Defaults = defaults();
+        assert_eq!(80, defaults.memory_percentage);
+        assert_eq!(Some(Spreading::new(SpreadingPolicy::Preferred, SpreadingTopology::Hostname)), defaults.spreading);
+        assert_eq!(Defaults::default(), Defaults::from_yaml("{}").unwrap());
+        assert!(Defaults::from_yaml("memoryPercentage: 120").is_err());
+        assert!(Defaults::from_yaml("labels: none").is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut empty: H2OSpec = H2OSpec::new(1, None, Resources::new(1, "1Gi".to_string(), None), None);
+        assert!(defaults().apply(&mut empty));
+        assert_eq!(Some(80), empty.resources.memory_percentage);
+        assert_eq!(Some("3.32.0.1".to_string()), empty.version);
+        assert_eq!(SpreadingPolicy::Preferred, empty.spreading.unwrap().policy);
+        assert_eq!("h2o", empty.node_selector["pool"]);
+        assert!(!defaults().apply(&mut empty));
+
+        let mut custom: H2OSpec = H2OSpec::new(1, None, Resources::new(1, "1Gi".to_string(), Some(90)),
+                                               Some(CustomImage::new("registry/h2o:latest".to_string(), None)));
+        custom.spreading = Some(Spreading::new(SpreadingPolicy::None, SpreadingTopology::Hostname));
+        custom.node_selector.insert("pool".to_string(), "highmem".to_string());
+        let original: H2OSpec = custom.clone();
+        assert!(!defaults().apply(&mut custom));
+        assert_eq!(original, custom);
+    }
+
+    #[test]
+    fn test_apply_labels() {
+        let mut labels: BTreeMap<String, String> = BTreeMap::new();
+        assert!(defaults().apply_labels(&mut labels));
+        assert_eq!("data-science", labels["cost-center"]);
+        labels.insert("cost-center".to_string(), "research".to_string());
+        assert!(!defaults().apply_labels(&mut labels));
+        assert_eq!("research", labels["cost-center"]);
+    }
+}
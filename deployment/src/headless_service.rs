@@ -1,34 +1,21 @@
-use k8s_openapi::api::core::v1::Service;
+use k8s_openapi::api::core::v1::{Service, ServicePort, ServiceSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
 use kube::{Api, Client};
-use kube::api::{DeleteParams, PostParams};
+use kube::api::PostParams;
 
 use crate::Error;
+use crate::statefulset::H2O_REST_PORT;
 
-const SERVICE_TEMPLATE: &str = r#"
-apiVersion: v1
-kind: Service
-metadata:
-  name: <name>
-  namespace: <namespace>
-  labels:
-    app: <name>
-spec:
-  type: ClusterIP
-  clusterIP: None
-  selector:
-    app: <name>
-  ports:
-  - protocol: TCP
-    port: 80
-    targetPort: 54321
-"#;
+/// Port the headless service exposes H2O REST API on.
+pub const HEADLESS_SERVICE_PORT: i32 = 80;
 
-/// Creates an H2O `Service` object from given parameters for further deployment into Kubernetes cluster
-/// from a YAML template.
+/// Creates an H2O headless `Service` object from given parameters for further deployment into Kubernetes cluster.
 ///
 /// # Arguments
 /// `name` - Name of the Service. Typically corresponds to the rest of H2O deployment Also used to label the service.
 /// `namespace` - Namespace the services belongs to.
+/// `owner` - Optional owner of the service, typically the `H2O` custom resource.
 ///
 /// # Examples
 ///
@@ -36,17 +23,27 @@ spec:
 /// use k8s_openapi::api::core::v1::Service;
 /// let service: Service = deployment::headless_service::h2o_service(
 /// "any-name",
-/// "default"
-/// )
-/// .expect("Could not create service from YAML template.");
+/// "default",
+/// Option::None
+/// );
 /// ```
-pub fn h2o_service(name: &str, namespace: &str) -> Result<Service, Error> {
-    let service_definition: String = SERVICE_TEMPLATE
-        .replace("<name>", name)
-        .replace("<namespace>", namespace);
-
-    let service: Service = serde_yaml::from_str(&service_definition)?;
-    return Ok(service);
+pub fn h2o_service(name: &str, namespace: &str, owner: Option<&OwnerReference>) -> Service {
+    Service {
+        metadata: crate::h2o_metadata(name, namespace, owner),
+        spec: Some(ServiceSpec {
+            type_: Some("ClusterIP".to_string()),
+            cluster_ip: Some("None".to_string()),
+            selector: Some(crate::h2o_labels(name)),
+            ports: Some(vec![ServicePort {
+                protocol: Some("TCP".to_string()),
+                port: HEADLESS_SERVICE_PORT,
+                target_port: Some(IntOrString::Int(H2O_REST_PORT)),
+                ..ServicePort::default()
+            }]),
+            ..ServiceSpec::default()
+        }),
+        status: None,
+    }
 }
 
 /// Invokes asynchronous creation of a headless `Service`.
@@ -69,19 +66,35 @@ pub fn h2o_service(name: &str, namespace: &str) -> Result<Service, Error> {
 /// ```
 pub async fn create(client: Client, namespace: &str, name: &str) -> Result<Service, Error> {
     let service_api: Api<Service> = Api::namespaced(client.clone(), namespace);
-    let service: Service = h2o_service(name, namespace)?;
+    let service: Service = h2o_service(name, namespace, Option::None);
     let created_service: Service =  service_api.create(&PostParams::default(), &service).await?;
     Ok(created_service)
 }
 
-/// Invokes asynchronous deletion of a `StatefulSet` of H2O pods from a Kubernetes cluster.
+/// Idempotently creates or updates a headless `Service`. If the `Service` already exists and matches
+/// the desired state, it is left untouched. See `apply::apply` for details.
 ///
 /// # Arguments
+/// `client` - Client to apply the Service with
+/// `namespace` - namespace to deploy the Service to
+/// `name` - Name of the service, used to label the service instance as well
+/// `owner` - Optional owner of the service, typically the `H2O` custom resource.
+pub async fn apply(client: Client, namespace: &str, name: &str, owner: Option<&OwnerReference>) -> Result<Service, Error> {
+    let service_api: Api<Service> = Api::namespaced(client.clone(), namespace);
+    let service: Service = h2o_service(name, namespace, owner);
+    crate::apply::apply(&service_api, name, &service).await
+}
+
+/// Invokes asynchronous deletion of the headless `Service` used for H2O node discovery from a Kubernetes cluster.
+///
+/// # Arguments
+///
+/// `client` - Client to delete the Service with
+/// `namespace` - Namespace to delete the Service from. User is responsible to provide
+/// correct namespace.
+/// `name` - Name of the Service to invoke deletion for.
 ///
-/// `client` - Client to delete the statefulset with
-/// `namespace` - Namespace to delete the statefulset from. User is responsible to provide
-/// correct namespace. Otherwise `Result::Err` is returned.
-/// `name` - Name of the statefulset to invoke deletion for.
+/// A headless service already deleted or never created is not an error, the deletion may be repeated.
 ///
 /// # Examples
 ///
@@ -94,7 +107,5 @@ pub async fn create(client: Client, namespace: &str, name: &str) -> Result<Servi
 /// }
 /// ```
 pub async fn delete(client: Client, namespace: &str, name: &str) -> Result<(), Error> {
-    let statefulset_api: Api<Service> = Api::namespaced(client.clone(), namespace);
-    statefulset_api.delete(name, &DeleteParams::default()).await?;
-    Ok(())
+    crate::delete_if_exists::<Service>(client, namespace, name).await
 }
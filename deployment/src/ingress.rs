@@ -1,78 +1,277 @@
-use k8s_openapi::api::networking::v1beta1::Ingress;
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::LoadBalancerStatus;
+use k8s_openapi::api::networking::{v1, v1beta1};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
 use kube::{Api, Client};
-use kube::api::{DeleteParams, PostParams};
+use kube::api::Meta;
+use kube::Error as KubeError;
+use log::debug;
+use serde::de::DeserializeOwned;
 
+use crate::crd::IngressSpec;
 use crate::Error;
+use crate::headless_service::HEADLESS_SERVICE_PORT;
+use crate::leader_service::{leader_service_name, leader_service_port};
+
+/// Minimal minor version of Kubernetes 1.x serving the `networking.k8s.io/v1` Ingress.
+const INGRESS_V1_MINIMAL_MINOR_VERSION: u32 = 19;
+/// Ingress path type matching the path the same way `networking.k8s.io/v1beta1` ingresses do by default.
+const PATH_TYPE: &str = "ImplementationSpecific";
+
+/// API versions of the `Ingress` resource an H2O ingress may be created with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngressApiVersion {
+    /// `networking.k8s.io/v1`, served since Kubernetes 1.19
+    V1,
+    /// `networking.k8s.io/v1beta1`, removed in Kubernetes 1.22
+    V1beta1,
+}
+
+/// `Ingress` API version assumed whenever the version served by the Kubernetes cluster can't be detected. Kubernetes 1.22
+/// and newer serve `networking.k8s.io/v1` only.
+pub const FALLBACK_API_VERSION: IngressApiVersion = IngressApiVersion::V1;
+
+/// An H2O `Ingress` of either of the supported API versions.
+#[derive(Debug, Clone)]
+pub enum H2OIngress {
+    V1(v1::Ingress),
+    V1beta1(v1beta1::Ingress),
+}
+
+/// `Service` an H2O ingress forwards requests to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// The leader service of the H2O deployment, see `leader_service`. `tls` is set if H2O serves its REST API over TLS.
+    Leader { tls: bool },
+    /// The headless service of the H2O deployment, see `headless_service`. H2O deployments created by older versions
+    /// of h2ok have no leader service.
+    Headless,
+}
+
+/// Detects the newest `Ingress` API version served by the Kubernetes cluster, based on the API server version.
+///
+/// # Arguments
+/// `client` - Client to query the API server version with
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use deployment::ingress::IngressApiVersion;
+/// use kube::Client;
+/// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
+/// let api_version: IngressApiVersion = deployment::ingress::api_version(&client).await.unwrap();
+/// }
+/// ```
+pub async fn api_version(client: &Client) -> Result<IngressApiVersion, Error> {
+    let server_version = client.apiserver_version().await?;
+    let api_version: IngressApiVersion = api_version_of(&server_version.major, &server_version.minor);
+    debug!("Kubernetes {}.{} detected, using {:?} Ingress.", server_version.major, server_version.minor, api_version);
+    Ok(api_version)
+}
+
+/// Determines the newest `Ingress` API version served by Kubernetes of given version. Some distributions
+/// report the minor version with a suffix, e.g. `19+`, only the leading digits are taken into account.
+/// Unparseable versions fall back to `FALLBACK_API_VERSION`.
+///
+/// # Arguments
+/// `major` - Major version of the Kubernetes API server
+/// `minor` - Minor version of the Kubernetes API server
+fn api_version_of(major: &str, minor: &str) -> IngressApiVersion {
+    let leading_digits = |version: &str| -> Option<u32> {
+        version.chars()
+            .take_while(|character| character.is_ascii_digit())
+            .collect::<String>()
+            .parse()
+            .ok()
+    };
+    match (leading_digits(major), leading_digits(minor)) {
+        (Some(major), _) if major > 1 => IngressApiVersion::V1,
+        (Some(1), Some(minor)) if minor >= INGRESS_V1_MINIMAL_MINOR_VERSION => IngressApiVersion::V1,
+        (Some(1), Some(_)) => IngressApiVersion::V1beta1,
+        _ => FALLBACK_API_VERSION,
+    }
+}
 
-const INGRESS_TEMPLATE: &str = r#"
-apiVersion: networking.k8s.io/v1beta1
-kind: Ingress
-metadata:
-  name: <name>
-  annotations:
-    nginx.ingress.kubernetes.io/rewrite-target: /$2
-    traefik.frontend.rule.type: PathPrefixStrip
-spec:
-  rules:
-  - http:
-      paths:
-      - path: /<name>
-        pathType: Exact
-        backend:
-          serviceName: <name>
-          servicePort: 80
-"#;
-
-/// Creates an H2O `Ingress` targeting a service of the same `name` to be further deployed into a Kubernetes
-/// cluster. It is assumed the servicePort is 80 and the target port is 54321 (the default H2O port).
+/// Annotation selecting the ingress controller, used by `networking.k8s.io/v1beta1` ingresses instead of
+/// the `ingressClassName` field unknown to Kubernetes older than 1.18.
+const INGRESS_CLASS_ANNOTATION: &str = "kubernetes.io/ingress.class";
+
+/// Annotations of an H2O ingress. By default, the most common ingress controllers are instructed to strip
+/// the `/<name>` prefix before forwarding requests to H2O, and to connect to H2O over TLS if `backend_tls` is set.
+/// User-defined annotations take precedence.
+fn h2o_ingress_annotations(ingress_spec: &IngressSpec, backend_tls: bool) -> BTreeMap<String, String> {
+    let mut annotations: BTreeMap<String, String> = BTreeMap::new();
+    annotations.insert("nginx.ingress.kubernetes.io/rewrite-target".to_string(), "/$2".to_string());
+    annotations.insert("traefik.frontend.rule.type".to_string(), "PathPrefixStrip".to_string());
+    if backend_tls {
+        annotations.insert("nginx.ingress.kubernetes.io/backend-protocol".to_string(), "HTTPS".to_string());
+        annotations.insert("ingress.kubernetes.io/protocol".to_string(), "https".to_string());
+    }
+    annotations.extend(ingress_spec.annotations.clone());
+    annotations
+}
+
+/// Creates an H2O `Ingress` of given `api_version` targeting the `backend` service of the H2O deployment of given `name`
+/// to be further deployed into a Kubernetes cluster.
 ///
 /// # Arguments
+/// `api_version` - API version of the ingress to create
 /// `name` - Name of the H2O deployment. Also used to label the the ingress.
 /// `namespace` - Namespace the ingress will be created in.
-fn h2o_ingress(name: &str, namespace: &str) -> Result<Ingress, Error> {
-    let ingress_definition = INGRESS_TEMPLATE
-        .replace("<name>", name)
-        .replace("<namespace>", namespace);
+/// `ingress_spec` - Host, TLS, ingress class and annotations of the ingress.
+/// `backend` - Service of the H2O deployment the ingress forwards requests to.
+/// `owner` - Optional owner of the ingress, typically the `H2O` custom resource.
+pub fn h2o_ingress(api_version: IngressApiVersion, name: &str, namespace: &str, ingress_spec: &IngressSpec,
+                   backend: Backend, owner: Option<&OwnerReference>) -> H2OIngress {
+    let mut metadata: ObjectMeta = crate::h2o_metadata(name, namespace, owner);
+    let backend_tls: bool = backend == Backend::Leader { tls: true };
+    let mut annotations: BTreeMap<String, String> = h2o_ingress_annotations(ingress_spec, backend_tls);
+    let (service_name, service_port): (String, i32) = match backend {
+        Backend::Leader { tls } => (leader_service_name(name), leader_service_port(tls)),
+        Backend::Headless => (name.to_string(), HEADLESS_SERVICE_PORT),
+    };
+    let tls_hosts: Option<Vec<String>> = ingress_spec.host.clone().map(|host| vec![host]);
 
-    let ingress: Ingress = serde_yaml::from_str(&ingress_definition)?;
-    return Ok(ingress);
+    match api_version {
+        IngressApiVersion::V1 => {
+            metadata.annotations = Some(annotations);
+            H2OIngress::V1(v1::Ingress {
+                metadata,
+                spec: Some(v1::IngressSpec {
+                    ingress_class_name: ingress_spec.ingress_class.clone(),
+                    tls: ingress_spec.tls_secret.as_ref().map(|tls_secret| vec![v1::IngressTLS {
+                        hosts: tls_hosts,
+                        secret_name: Some(tls_secret.clone()),
+                    }]),
+                    rules: Some(vec![v1::IngressRule {
+                        host: ingress_spec.host.clone(),
+                        http: Some(v1::HTTPIngressRuleValue {
+                            paths: vec![v1::HTTPIngressPath {
+                                path: Some(format!("/{}", name)),
+                                path_type: Some(PATH_TYPE.to_string()),
+                                backend: v1::IngressBackend {
+                                    service: Some(v1::IngressServiceBackend {
+                                        name: service_name,
+                                        port: Some(v1::ServiceBackendPort {
+                                            number: Some(service_port),
+                                            name: None,
+                                        }),
+                                    }),
+                                    resource: None,
+                                },
+                            }],
+                        }),
+                    }]),
+                    ..v1::IngressSpec::default()
+                }),
+                status: None,
+            })
+        }
+        IngressApiVersion::V1beta1 => {
+            if let Some(ingress_class) = ingress_spec.ingress_class.as_ref() {
+                annotations.entry(INGRESS_CLASS_ANNOTATION.to_string()).or_insert_with(|| ingress_class.clone());
+            }
+            metadata.annotations = Some(annotations);
+            H2OIngress::V1beta1(v1beta1::Ingress {
+                metadata,
+                spec: Some(v1beta1::IngressSpec {
+                    tls: ingress_spec.tls_secret.as_ref().map(|tls_secret| vec![v1beta1::IngressTLS {
+                        hosts: tls_hosts,
+                        secret_name: Some(tls_secret.clone()),
+                    }]),
+                    rules: Some(vec![v1beta1::IngressRule {
+                        host: ingress_spec.host.clone(),
+                        http: Some(v1beta1::HTTPIngressRuleValue {
+                            paths: vec![v1beta1::HTTPIngressPath {
+                                path: Some(format!("/{}", name)),
+                                path_type: Some(PATH_TYPE.to_string()),
+                                backend: v1beta1::IngressBackend {
+                                    service_name: Some(service_name),
+                                    service_port: Some(IntOrString::Int(service_port)),
+                                    resource: None,
+                                },
+                            }],
+                        }),
+                    }]),
+                    ..v1beta1::IngressSpec::default()
+                }),
+                status: None,
+            })
+        }
+    }
 }
 
-/// Invokes asynchronous creation of an `Ingress`.
-///
+/// Detects the `Service` an `Ingress` of an existing H2O deployment of given `name` should forward requests to.
+/// The leader service is preferred, whether H2O serves its REST API over TLS is detected from its ports. H2O deployments
+/// without a leader service are served by their headless service over plain HTTP.
 ///
 /// # Arguments
-/// `client` - Client to create the StatefulSet with
-/// `specification` - Specification of the H2O cluster
-/// `namespace` - namespace to deploy the statefulset to
-/// `name` - Name of the statefulset, used for statefulset and pod labeling as well.
+/// `client` - Client to look the services up with
+/// `namespace` - Namespace the H2O deployment resides in
+/// `name` - Name of the H2O deployment
 ///
 /// # Examples
 ///
 /// ```no_run
 /// #[tokio::main]
 /// async fn main() {
-/// use k8s_openapi::api::networking::v1beta1::Ingress;
+/// use deployment::crd::IngressSpec;
+/// use deployment::ingress::{Backend, H2OIngress, IngressApiVersion};
 /// use kube::Client;
 /// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
-/// let ingress: Ingress = deployment::ingress::create(client, &namespace, "any-name").await.unwrap();
+/// let api_version: IngressApiVersion = deployment::ingress::api_version(&client).await.unwrap();
+/// let backend: Backend = deployment::ingress::backend(client.clone(), &namespace, "any-name").await.unwrap();
+/// let ingress: H2OIngress = deployment::ingress::apply(client, api_version, &namespace, "any-name",
+///                                                      &IngressSpec::default(), backend, Option::None)
+///     .await.unwrap();
 /// }
 /// ```
-pub async fn create(client: Client, namespace: &str, name: &str) -> Result<Ingress, Error> {
-    let api: Api<Ingress> = Api::namespaced(client, namespace);
-    let ingress_template: Ingress = h2o_ingress(name, namespace)?;
+pub async fn backend(client: Client, namespace: &str, name: &str) -> Result<Backend, Error> {
+    let backend: Backend = match crate::leader_service::uses_tls(client, namespace, name).await? {
+        Some(tls) => Backend::Leader { tls },
+        None => Backend::Headless,
+    };
+    debug!("Ingress of H2O deployment '{}' forwards requests to {:?} service.", name, backend);
+    Ok(backend)
+}
 
-    let created_ingress: Ingress = api.create(&PostParams::default(), &ingress_template).await?;
-    Ok(created_ingress)
+/// Idempotently creates or updates an `Ingress` of given `api_version`. If the `Ingress` already exists and matches
+/// the desired state, it is left untouched. See `apply::apply` for details.
+///
+/// # Arguments
+/// `client` - Client to apply the Ingress with
+/// `api_version` - API version of the Ingress, see `api_version` to detect the one served by the Kubernetes cluster
+/// `namespace` - namespace to deploy the Ingress to
+/// `name` - Name of the H2O deployment the Ingress points to.
+/// `ingress_spec` - Host, TLS, ingress class and annotations of the Ingress.
+/// `backend` - Service of the H2O deployment the Ingress forwards requests to, see `backend`.
+/// `owner` - Optional owner of the Ingress, typically the `H2O` custom resource.
+pub async fn apply(client: Client, api_version: IngressApiVersion, namespace: &str, name: &str,
+                   ingress_spec: &IngressSpec, backend: Backend, owner: Option<&OwnerReference>) -> Result<H2OIngress, Error> {
+    match h2o_ingress(api_version, name, namespace, ingress_spec, backend, owner) {
+        H2OIngress::V1(ingress) => {
+            let api: Api<v1::Ingress> = Api::namespaced(client, namespace);
+            Ok(H2OIngress::V1(crate::apply::apply(&api, name, &ingress).await?))
+        }
+        H2OIngress::V1beta1(ingress) => {
+            let api: Api<v1beta1::Ingress> = Api::namespaced(client, namespace);
+            Ok(H2OIngress::V1beta1(crate::apply::apply(&api, name, &ingress).await?))
+        }
+    }
 }
 
-/// Invokes asynchronous deletion of an `Ingress` from a Kubernetes cluster.
+/// Invokes asynchronous deletion of an `Ingress` from a Kubernetes cluster. An Ingress already deleted or never created
+/// is not an error, the deletion may be repeated.
 ///
 /// # Arguments
 ///
 /// `client` - Client to delete the Ingress with
-/// `namespace` - Namespace to delete the Ingress from. User is responsible to provide
-/// correct namespace. Otherwise `Result::Err` is returned.
+/// `api_version` - API version of the Ingress, see `api_version` to detect the one served by the Kubernetes cluster
+/// `namespace` - Namespace to delete the Ingress from.
 /// `name` - Name of the Ingress to invoke deletion for.
 ///
 /// # Examples
@@ -80,15 +279,52 @@ pub async fn create(client: Client, namespace: &str, name: &str) -> Result<Ingre
 /// ```no_run
 /// #[tokio::main]
 /// async fn main() {
+/// use deployment::ingress::IngressApiVersion;
 /// use kube::Client;
 /// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
-/// deployment::ingress::delete(client, &namespace, "any-name").await.unwrap();
+/// deployment::ingress::delete(client, IngressApiVersion::V1, &namespace, "any-name").await.unwrap();
 /// }
 /// ```
-pub async fn delete(client: Client, namespace: &str, name: &str) -> Result<(), Error> {
-    let api: Api<Ingress> = Api::namespaced(client, namespace);
-    api.delete(name, &DeleteParams::default()).await?;
-    Ok(())
+pub async fn delete(client: Client, api_version: IngressApiVersion, namespace: &str, name: &str) -> Result<(), Error> {
+    match api_version {
+        IngressApiVersion::V1 => crate::delete_if_exists::<v1::Ingress>(client, namespace, name).await,
+        IngressApiVersion::V1beta1 => crate::delete_if_exists::<v1beta1::Ingress>(client, namespace, name).await,
+    }
+}
+
+/// Invokes asynchronous deletion of an `Ingress` from a Kubernetes cluster, only if it is owned by `owner`.
+/// Ingresses created independently of the owner, e.g. by the `h2ok ingress` command, are left untouched.
+///
+/// # Arguments
+///
+/// `client` - Client to delete the Ingress with
+/// `api_version` - API version of the Ingress, see `api_version` to detect the one served by the Kubernetes cluster
+/// `namespace` - Namespace to delete the Ingress from.
+/// `name` - Name of the Ingress to invoke deletion for.
+/// `owner` - Owner the Ingress must reference to be deleted, typically the `H2O` custom resource.
+pub async fn delete_owned(client: Client, api_version: IngressApiVersion, namespace: &str, name: &str,
+                          owner: &OwnerReference) -> Result<(), Error> {
+    let owned: bool = match api_version {
+        IngressApiVersion::V1 => is_owned::<v1::Ingress>(client.clone(), namespace, name, owner).await?,
+        IngressApiVersion::V1beta1 => is_owned::<v1beta1::Ingress>(client.clone(), namespace, name, owner).await?,
+    };
+    if owned {
+        delete(client, api_version, namespace, name).await
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns `true` if a resource of given `name` exists and references `owner`, otherwise `false`.
+async fn is_owned<K>(client: Client, namespace: &str, name: &str, owner: &OwnerReference) -> Result<bool, Error>
+    where K: Clone + DeserializeOwned + Meta {
+    let api: Api<K> = Api::namespaced(client, namespace);
+    match api.get(name).await {
+        Ok(resource) => Ok(resource.meta().owner_references.iter().flatten()
+            .any(|reference| reference.uid == owner.uid)),
+        Err(KubeError::Api(error_response)) if error_response.code == 404 => Ok(false),
+        Err(error) => Err(error.into()),
+    }
 }
 
 /// Returns the first IP assigned to an Ingresses load balancer, if found. Otherwise returns `Option::None`.
@@ -96,26 +332,115 @@ pub async fn delete(client: Client, namespace: &str, name: &str) -> Result<(), E
 /// # Arguments
 ///
 /// `ingress` - Ingress to search for IP
-pub fn any_lb_external_ip(ingress: &Ingress) -> Option<String> {
-    return ingress
-        .status.as_ref()?
-        .load_balancer.as_ref()?
+pub fn any_lb_external_ip(ingress: &H2OIngress) -> Option<String> {
+    let load_balancer: &LoadBalancerStatus = match ingress {
+        H2OIngress::V1(ingress) => ingress.status.as_ref()?.load_balancer.as_ref()?,
+        H2OIngress::V1beta1(ingress) => ingress.status.as_ref()?.load_balancer.as_ref()?,
+    };
+    load_balancer
         .ingress.as_ref()?
         .last()?
         .ip
-        .clone();
+        .clone()
 }
 
 /// Returns the first Path assigned to an Ingress found, if found. Otherwise returns None.
 ///
 /// # Arguments:
 /// `ingress` - Ingress to search for Path
-pub fn any_path(ingress: &Ingress) -> Option<String> {
-    return ingress
-        .spec.as_ref()?
-        .rules.as_ref()?
-        .last()?.http
-        .as_ref()?
-        .paths.last()?
-        .path.clone();
+pub fn any_path(ingress: &H2OIngress) -> Option<String> {
+    match ingress {
+        H2OIngress::V1(ingress) => ingress
+            .spec.as_ref()?
+            .rules.as_ref()?
+            .last()?.http
+            .as_ref()?
+            .paths.last()?
+            .path.clone(),
+        H2OIngress::V1beta1(ingress) => ingress
+            .spec.as_ref()?
+            .rules.as_ref()?
+            .last()?.http
+            .as_ref()?
+            .paths.last()?
+            .path.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+
+    use crate::crd::IngressSpec;
+
+    use super::{Backend, H2OIngress, IngressApiVersion};
+
+    #[test]
+    fn test_api_version_of() {
+        assert_eq!(IngressApiVersion::V1beta1, super::api_version_of("1", "17"));
+        assert_eq!(IngressApiVersion::V1beta1, super::api_version_of("1", "18+"));
+        assert_eq!(IngressApiVersion::V1, super::api_version_of("1", "19"));
+        assert_eq!(IngressApiVersion::V1, super::api_version_of("1", "22+"));
+        assert_eq!(super::FALLBACK_API_VERSION, super::api_version_of("", ""));
+        assert_eq!(super::FALLBACK_API_VERSION, super::api_version_of("1", "beta"));
+    }
+
+    #[test]
+    fn test_any_path() {
+        for api_version in &[IngressApiVersion::V1, IngressApiVersion::V1beta1] {
+            let ingress: H2OIngress = super::h2o_ingress(*api_version, "h2o", "default", &IngressSpec::default(),
+                                                         Backend::Leader { tls: false }, Option::None);
+            assert_eq!(Some("/h2o".to_string()), super::any_path(&ingress));
+            assert_eq!(None, super::any_lb_external_ip(&ingress));
+        }
+    }
+
+    #[test]
+    fn test_configured_ingress() {
+        let mut annotations: BTreeMap<String, String> = BTreeMap::new();
+        annotations.insert("nginx.ingress.kubernetes.io/rewrite-target".to_string(), "/".to_string());
+        let ingress_spec: IngressSpec = IngressSpec::new(Some("h2o.example.com".to_string()),
+                                                         Some("h2o-tls".to_string()), Some("nginx".to_string()),
+                                                         annotations);
+
+        let ingress = match super::h2o_ingress(IngressApiVersion::V1, "h2o", "default", &ingress_spec,
+                                               Backend::Leader { tls: false }, Option::None) {
+            H2OIngress::V1(ingress) => ingress,
+            H2OIngress::V1beta1(_) => panic!("networking.k8s.io/v1 Ingress expected."),
+        };
+        let spec = ingress.spec.unwrap();
+        assert_eq!(Some("nginx".to_string()), spec.ingress_class_name);
+        assert_eq!(Some("h2o.example.com".to_string()), spec.rules.unwrap()[0].host);
+        let tls = &spec.tls.unwrap()[0];
+        assert_eq!(Some("h2o-tls".to_string()), tls.secret_name);
+        assert_eq!(Some(vec!["h2o.example.com".to_string()]), tls.hosts);
+        assert_eq!("/", ingress.metadata.annotations.unwrap()["nginx.ingress.kubernetes.io/rewrite-target"]);
+
+        let ingress = match super::h2o_ingress(IngressApiVersion::V1beta1, "h2o", "default", &ingress_spec,
+                                               Backend::Leader { tls: true }, Option::None) {
+            H2OIngress::V1beta1(ingress) => ingress,
+            H2OIngress::V1(_) => panic!("networking.k8s.io/v1beta1 Ingress expected."),
+        };
+        let annotations: BTreeMap<String, String> = ingress.metadata.annotations.unwrap();
+        assert_eq!("nginx", annotations[super::INGRESS_CLASS_ANNOTATION]);
+        assert_eq!("HTTPS", annotations["nginx.ingress.kubernetes.io/backend-protocol"]);
+        let rules = ingress.spec.unwrap().rules.unwrap();
+        assert_eq!(Some(IntOrString::Int(443)), rules[0].http.as_ref().unwrap().paths[0].backend.service_port);
+    }
+
+    #[test]
+    fn test_headless_backend() {
+        let ingress = match super::h2o_ingress(IngressApiVersion::V1beta1, "h2o", "default", &IngressSpec::default(),
+                                               Backend::Headless, Option::None) {
+            H2OIngress::V1beta1(ingress) => ingress,
+            H2OIngress::V1(_) => panic!("networking.k8s.io/v1beta1 Ingress expected."),
+        };
+        assert!(!ingress.metadata.annotations.unwrap().contains_key("nginx.ingress.kubernetes.io/backend-protocol"));
+        let rules = ingress.spec.unwrap().rules.unwrap();
+        let backend = &rules[0].http.as_ref().unwrap().paths[0].backend;
+        assert_eq!(Some("h2o".to_string()), backend.service_name);
+        assert_eq!(Some(IntOrString::Int(80)), backend.service_port);
+    }
 }
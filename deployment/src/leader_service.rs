@@ -0,0 +1,150 @@
+use k8s_openapi::api::core::v1::{Service, ServicePort, ServiceSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::{Api, Client};
+use kube::Error as KubeError;
+
+use crate::Error;
+use crate::statefulset::H2O_REST_PORT;
+
+/// Port the leader service exposes H2O REST API on.
+pub const LEADER_SERVICE_PORT: i32 = 80;
+/// Port the leader service exposes H2O REST API on, if H2O serves it over TLS (see `crd::Tls`).
+pub const LEADER_SERVICE_TLS_PORT: i32 = 443;
+/// Name of the leader service port if H2O serves its REST API over TLS.
+const TLS_PORT_NAME: &str = "https";
+
+/// Name of the leader service of an H2O deployment of given `name`.
+///
+/// # Arguments
+/// `name` - Name of the H2O deployment
+pub fn leader_service_name(name: &str) -> String {
+    format!("{}-leader", name)
+}
+
+/// Port of the leader service, depending on whether H2O serves its REST API over TLS.
+///
+/// # Arguments
+/// `tls` - Whether H2O serves its REST API over TLS
+pub fn leader_service_port(tls: bool) -> i32 {
+    if tls { LEADER_SERVICE_TLS_PORT } else { LEADER_SERVICE_PORT }
+}
+
+/// In-cluster URL of the H2O REST API, as exposed by the leader service of an H2O deployment of given `name`.
+///
+/// # Arguments
+/// `namespace` - Namespace the H2O deployment resides in
+/// `name` - Name of the H2O deployment
+/// `tls` - Whether H2O serves its REST API over TLS
+pub fn leader_service_url(namespace: &str, name: &str, tls: bool) -> String {
+    format!("{}://{}.{}.svc.cluster.local:{}", if tls { "https" } else { "http" }, leader_service_name(name), namespace,
+            leader_service_port(tls))
+}
+
+/// Creates a `Service` with a cluster IP routing client traffic to the pod with H2O leader node. As only the pod
+/// with H2O leader node passes the readiness probe, it is the only endpoint of the service. Unlike the headless
+/// service used for H2O node discovery, clients get a stable virtual IP instead of a DNS round-robin across pods.
+///
+/// The service is named `<name>-leader`, see `leader_service_name`. If H2O serves its REST API over TLS,
+/// the service port is named `https` and exposed on `LEADER_SERVICE_TLS_PORT`.
+///
+/// # Arguments
+/// `name` - Name of the H2O deployment. Used to select H2O pods and label the service.
+/// `namespace` - Namespace the service belongs to.
+/// `tls` - Whether H2O serves its REST API over TLS
+/// `owner` - Optional owner of the service, typically the `H2O` custom resource.
+///
+/// # Examples
+///
+/// ```no_run
+/// use k8s_openapi::api::core::v1::Service;
+/// let service: Service = deployment::leader_service::h2o_leader_service(
+/// "any-name",
+/// "default",
+/// false,
+/// Option::None
+/// );
+/// ```
+pub fn h2o_leader_service(name: &str, namespace: &str, tls: bool, owner: Option<&OwnerReference>) -> Service {
+    let mut metadata: ObjectMeta = crate::h2o_metadata(name, namespace, owner);
+    metadata.name = Some(leader_service_name(name));
+    Service {
+        metadata,
+        spec: Some(ServiceSpec {
+            type_: Some("ClusterIP".to_string()),
+            selector: Some(crate::h2o_labels(name)),
+            ports: Some(vec![ServicePort {
+                name: Some(if tls { TLS_PORT_NAME } else { "http" }.to_string()),
+                protocol: Some("TCP".to_string()),
+                port: leader_service_port(tls),
+                target_port: Some(IntOrString::Int(H2O_REST_PORT)),
+                ..ServicePort::default()
+            }]),
+            ..ServiceSpec::default()
+        }),
+        status: None,
+    }
+}
+
+/// Idempotently creates or updates the leader `Service` of an H2O deployment. If the `Service` already exists
+/// and matches the desired state, it is left untouched. See `apply::apply` for details.
+///
+/// # Arguments
+/// `client` - Client to apply the Service with
+/// `namespace` - namespace to deploy the Service to
+/// `name` - Name of the H2O deployment
+/// `tls` - Whether H2O serves its REST API over TLS
+/// `owner` - Optional owner of the service, typically the `H2O` custom resource.
+pub async fn apply(client: Client, namespace: &str, name: &str, tls: bool, owner: Option<&OwnerReference>)
+                   -> Result<Service, Error> {
+    let service_api: Api<Service> = Api::namespaced(client, namespace);
+    let service: Service = h2o_leader_service(name, namespace, tls, owner);
+    crate::apply::apply(&service_api, &leader_service_name(name), &service).await
+}
+
+/// Returns `Some(true)` if the leader `Service` of an existing H2O deployment exposes H2O REST API served over TLS,
+/// otherwise `Some(false)`. Useful when the `H2OSpec` of the deployment is not at hand. H2O deployments created
+/// by older versions of h2ok have no leader service, `Option::None` is returned then.
+///
+/// # Arguments
+/// `client` - Client to get the Service with
+/// `namespace` - Namespace the H2O deployment resides in
+/// `name` - Name of the H2O deployment
+pub async fn uses_tls(client: Client, namespace: &str, name: &str) -> Result<Option<bool>, Error> {
+    let service_api: Api<Service> = Api::namespaced(client, namespace);
+    let service: Service = match service_api.get(&leader_service_name(name)).await {
+        Ok(service) => service,
+        Err(KubeError::Api(error_response)) if error_response.code == 404 => return Ok(Option::None),
+        Err(error) => return Err(error.into()),
+    };
+    Ok(Some(service.spec
+        .and_then(|spec| spec.ports)
+        .unwrap_or_default()
+        .iter()
+        .any(|port| port.name.as_deref() == Some(TLS_PORT_NAME))))
+}
+
+/// Invokes asynchronous deletion of the leader `Service` of an H2O deployment from a Kubernetes cluster.
+///
+/// # Arguments
+///
+/// `client` - Client to delete the Service with
+/// `namespace` - Namespace to delete the Service from. User is responsible to provide
+/// correct namespace.
+/// `name` - Name of the H2O deployment the leader Service belongs to.
+///
+/// A leader service already deleted or never created is not an error, the deletion may be repeated.
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use kube::Client;
+/// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
+/// deployment::leader_service::delete(client, &namespace, "any-name").await.unwrap();
+/// }
+/// ```
+pub async fn delete(client: Client, namespace: &str, name: &str) -> Result<(), Error> {
+    crate::delete_if_exists::<Service>(client, namespace, &leader_service_name(name)).await
+}
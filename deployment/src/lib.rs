@@ -3,20 +3,44 @@ extern crate kube;
 extern crate log;
 extern crate thiserror;
 
-use kube::Client;
+use std::collections::BTreeMap;
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
+use kube::{Api, Client};
+use kube::api::{DeleteParams, Meta};
 use kube::Error as KubeError;
+use log::debug;
+use serde::de::DeserializeOwned;
 use serde_yaml::Error as YamlError;
 use serde_json::Error as JsonError;
 use thiserror::Error as ThisError;
 
 use crate::crd::H2OSpec;
+use crate::ingress::{Backend, IngressApiVersion};
 
+pub mod apply;
+pub mod auth;
+pub mod clustering;
+pub mod conversion;
 pub mod crd;
+pub mod crd_manifest;
+pub mod crd_v1;
+pub mod defaults;
 pub mod finalizer;
 pub mod ingress;
 pub mod headless_service;
+pub mod leader_service;
 pub mod statefulset;
 pub mod client;
+pub mod pod;
+pub mod quantity;
+pub mod rbac;
+pub mod security;
+pub mod status;
+pub mod storage;
+pub mod tls;
+pub mod validation;
+pub mod webhook;
 
 /// Error during handling Kubernetes cluster-related requests.
 #[derive(ThisError, Debug)]
@@ -32,6 +56,12 @@ pub enum Error {
     Timeout(String),
     #[error("Failed to serialize template. Reason: {0}")]
     TemplateSerializationError(String),
+    /// No H2O node claims leadership, the H2O cluster is not formed (yet)
+    #[error("No H2O leader node found. Reason: {0}")]
+    NoLeader(String),
+    /// More than one H2O node claims leadership, the H2O nodes formed more than one cluster
+    #[error("Multiple H2O leader nodes found. Reason: {0}")]
+    MultipleLeaders(String),
 }
 impl From<KubeError> for Error{
     fn from(kube_error: KubeError) -> Self {
@@ -51,24 +81,81 @@ impl From<JsonError> for Error{
     }
 }
 
+/// Labels shared by all resources belonging to an H2O deployment of given `name`. Also used as a selector
+/// for H2O pods.
+pub(crate) fn h2o_labels(name: &str) -> BTreeMap<String, String> {
+    let mut labels: BTreeMap<String, String> = BTreeMap::new();
+    labels.insert("app".to_string(), name.to_string());
+    labels
+}
+
+/// Deletes a namespaced resource of given `name`, ignoring the resource not being found. Deletion of H2O deployments
+/// is therefore idempotent - it may be retried after a partial failure, and resources never created, e.g. by older
+/// versions, do not prevent the rest from being deleted.
+pub(crate) async fn delete_if_exists<K>(client: Client, namespace: &str, name: &str) -> Result<(), Error>
+    where K: Clone + DeserializeOwned + Meta {
+    let api: Api<K> = Api::namespaced(client, namespace);
+    match api.delete(name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(KubeError::Api(error_response)) if error_response.code == 404 => {
+            debug!("No {} '{}' to delete.", K::KIND, name);
+            Ok(())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Metadata shared by all resources belonging to an H2O deployment of given `name`.
+///
+/// # Arguments
+/// `name` - Name of the resource, typically the name of the H2O deployment. Also used to label the resource.
+/// `namespace` - Namespace the resource belongs to.
+/// `owner` - Optional owner of the resource. When present, the resource is garbage collected by Kubernetes
+/// once the owner is deleted.
+pub(crate) fn h2o_metadata(name: &str, namespace: &str, owner: Option<&OwnerReference>) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(name.to_string()),
+        namespace: Some(namespace.to_string()),
+        labels: Some(h2o_labels(name)),
+        owner_references: owner.map(|owner_reference| vec![owner_reference.clone()]),
+        ..ObjectMeta::default()
+    }
+}
+
 /// Creates all the resources necessary to start an H2O cluster according to specification.
 /// Only the resources necessary for the H2O cluster to be up and running are created (exhaustive list):
 /// 1. Pods, each pod with one H2O instance (one H2O JVM). With resources limits and requests set equally
-/// according to the `H2OSpec` given.
+///    according to the `H2OSpec` given.
 /// 2. A headless service to make the clustering possible. Address of the service is provided to the underlying pods
-/// via an environment variable.
+///    via an environment variable.
+/// 3. A leader service with a cluster IP, routing client traffic to the pod with H2O leader node (`<name>-leader`).
+/// 4. An ingress pointing to the leader service, only if `specification.ingress` is defined. The newest `Ingress` API version served
+///    by the Kubernetes cluster is used.
+/// 5. A dedicated service account of H2O pods with a role and a role binding, only if requested by `specification.service_account`.
+///
+/// The service account, role and role binding (see `rbac::apply`) are created before any other resources, so H2O pods
+/// never start without their service account.
 ///
 /// The resources are invoked asynchronously and possibly in parallel. There is no guarantee the underlying
 /// resources are created and the H2O cluster itself is clustered, ready and running when this function returns.
+/// Use `clustering::wait_until_clustered` to wait for the H2O cluster to form.
 ///
-/// All resources share the same `name`.
+/// The resources are applied idempotently (see `apply::apply`) - missing resources are created, resources drifted
+/// from the specification are patched and resources matching the specification are left untouched. It is therefore
+/// safe to call this function repeatedly, e.g. after a partial failure.
+///
+/// All resources share the same `name`, except for the leader service.
 ///
 /// # Arguments
 /// - `client` - A Kubernetes client from the `kube` crate to create the resources with.
 /// - `specification` - An instance of `H2OSpec` prescribing the size, resources and settings of an H2O cluster
 /// - `namespace` - Namespace to deploy the H2O cluster resources to. It is the caller's responsibility to make sure
-/// the client has permissions to deploy all the resources listed above into this namespace.
+///   the client has permissions to deploy all the resources listed above into this namespace.
 /// - `name` - Name of the H2O deployment.
+/// - `ingress_api_version` - API version of the ingress, if any is specified (see `ingress::api_version` to detect
+///   the one served by the Kubernetes cluster).
+/// - `owner` - Optional owner of all the resources created, typically the `H2O` custom resource (see
+///   `crd::owner_reference`). Once the owner is deleted, the resources are garbage collected by Kubernetes.
 ///
 /// # Examples
 ///
@@ -76,6 +163,7 @@ impl From<JsonError> for Error{
 /// #[tokio::main]
 /// async fn main() {
 /// use deployment::crd::{Resources, H2OSpec};
+/// use deployment::ingress::IngressApiVersion;
 /// use kube::Client;
 /// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
 /// let name: &str = "test-cluster";
@@ -87,7 +175,7 @@ impl From<JsonError> for Error{
 ///     Option::None,
 ///  );
 ///
-/// deployment::create_h2o_cluster(client, &specification, &namespace, name);
+/// deployment::create_h2o_cluster(client, &specification, &namespace, name, IngressApiVersion::V1, Option::None);
 /// }
 /// ```
 pub async fn create_h2o_cluster(
@@ -95,19 +183,35 @@ pub async fn create_h2o_cluster(
     specification: &H2OSpec,
     namespace: &str,
     name: &str,
+    ingress_api_version: IngressApiVersion,
+    owner: Option<&OwnerReference>,
 ) -> Result<(), Error> {
-    let service_future = headless_service::create(client.clone(), namespace, name);
-    let statefulset_future = statefulset::create(client.clone(), specification, namespace, name);
-    tokio::try_join!(service_future, statefulset_future)?;
+    rbac::apply(client.clone(), specification, namespace, name, owner).await?;
+    let service_future = headless_service::apply(client.clone(), namespace, name, owner);
+    let tls: bool = specification.tls.is_some();
+    let leader_service_future = leader_service::apply(client.clone(), namespace, name, tls, owner);
+    let statefulset_future = statefulset::apply(client.clone(), specification, namespace, name, owner);
+    let ingress_future = async {
+        if let Some(ingress_spec) = specification.ingress.as_ref() {
+            ingress::apply(client.clone(), ingress_api_version, namespace, name, ingress_spec, Backend::Leader { tls },
+                           owner).await?;
+        }
+        Ok::<(), Error>(())
+    };
+    tokio::try_join!(service_future, leader_service_future, statefulset_future, ingress_future)?;
     return Ok(());
 }
 
 /// Deletes basic resources tied to an `H2O` deployment of given `name` from the Kubernetes cluster.
 /// By all resources, it is meant:
 /// 1. Pods with H2O nodes,
-/// 2. Headless service for clustering.
+/// 2. Headless service for clustering,
+/// 3. Leader service for client traffic,
+/// 4. Persistent volume claims of H2O pods, if their deletion policy is `Delete` (see `storage::delete_claims`),
+/// 5. The dedicated service account, role and role binding, if any (see `rbac::delete`).
 ///
-/// No other resources are deleted.
+/// No other resources are deleted. Resources already deleted are skipped, so the deletion may be repeated,
+/// e.g. after a partial failure.
 ///
 /// The deletion is invoked asynchronously and potentially in parallel. Therefore, there is no guarantee
 /// the resources are actually deleted at the time this function returns. The deletion itself is taken care of
@@ -136,8 +240,11 @@ pub async fn delete_h2o_cluster(
     name: &str,
 ) -> Result<(), Error> {
     let service_future = headless_service::delete(client.clone(), namespace, name);
+    let leader_service_future = leader_service::delete(client.clone(), namespace, name);
     let statefulset_future = statefulset::delete(client.clone(), namespace, name);
-    tokio::try_join!(service_future, statefulset_future)?;
+    let claims_future = storage::delete_claims(client.clone(), namespace, name);
+    let rbac_future = rbac::delete(client.clone(), namespace, name);
+    tokio::try_join!(service_future, leader_service_future, statefulset_future, claims_future, rbac_future)?;
     return Ok(());
 }
 
@@ -146,9 +253,10 @@ mod tests {
     extern crate tests_common;
 
     use std::path::PathBuf;
+    use std::time::Duration;
 
     use k8s_openapi::api::apps::v1::StatefulSet;
-    use k8s_openapi::api::core::v1::Service;
+    use k8s_openapi::api::core::v1::{Pod, Service};
     use kube::Api;
     use kube::api::ListParams;
 
@@ -177,7 +285,13 @@ mod tests {
             Option::None,
         );
 
-        super::create_h2o_cluster(client.clone(), &specification, &namespace, name)
+        super::create_h2o_cluster(client.clone(), &specification, &namespace, name, super::ingress::FALLBACK_API_VERSION,
+                                   Option::None)
+            .await
+            .unwrap();
+        // Repeated deployment of the very same cluster must not fail
+        super::create_h2o_cluster(client.clone(), &specification, &namespace, name, super::ingress::FALLBACK_API_VERSION,
+                                   Option::None)
             .await
             .unwrap();
 
@@ -192,8 +306,9 @@ mod tests {
                 .len()
         );
         let service_api: Api<Service> = Api::namespaced(client.clone(), &namespace);
+        // Headless service for clustering and leader service for client traffic
         assert_eq!(
-            1,
+            2,
             service_api
                 .list(&ListParams::default().labels(&format!("app={}", &name)))
                 .await
@@ -202,6 +317,16 @@ mod tests {
                 .len()
         );
 
+        let leader: Pod = super::clustering::wait_until_clustered(client.clone(), &namespace, name,
+                                                                  Duration::from_secs(180), |_| {})
+            .await
+            .unwrap();
+        assert!(super::pod::is_ready(&leader));
+
+        super::delete_h2o_cluster(client.clone(), &namespace, name)
+            .await
+            .unwrap();
+        // Repeated deletion of the very same cluster must not fail
         super::delete_h2o_cluster(client.clone(), &namespace, name)
             .await
             .unwrap();
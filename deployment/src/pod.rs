@@ -0,0 +1,222 @@
+use k8s_openapi::api::core::v1::{ContainerStatus, Pod};
+use kube::{Api, Client};
+use kube::api::ListParams;
+
+use crate::Error;
+
+/// Reasons of a waiting container which do not resolve without user's intervention.
+const UNRECOVERABLE_WAITING_REASONS: [&str; 6] = ["CrashLoopBackOff", "ErrImagePull", "ImagePullBackOff",
+    "InvalidImageName", "CreateContainerConfigError", "CreateContainerError"];
+
+/// Lists all pods with H2O nodes belonging to an H2O deployment of given `name`.
+///
+/// # Arguments
+/// `client` - Client to list the pods with
+/// `namespace` - Namespace the H2O deployment resides in
+/// `name` - Name of the H2O deployment
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use k8s_openapi::api::core::v1::Pod;
+/// use kube::Client;
+/// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
+/// let pods: Vec<Pod> = deployment::pod::list(client, &namespace, "any-name").await.unwrap();
+/// }
+/// ```
+pub async fn list(client: Client, namespace: &str, name: &str) -> Result<Vec<Pod>, Error> {
+    let pod_api: Api<Pod> = Api::namespaced(client, namespace);
+    let list_params: ListParams = ListParams::default().labels(&label_selector(name));
+    Ok(pod_api.list(&list_params).await?.items)
+}
+
+/// The pod with H2O leader node. Once H2O nodes form a cluster, the leader node is the entry point
+/// to the H2O cluster.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaderNode {
+    /// Name of the pod with H2O leader node
+    pub pod_name: String,
+    /// IP address of the pod with H2O leader node
+    pub pod_ip: String,
+    /// Name of the Kubernetes node the pod with H2O leader node is scheduled on
+    pub node_name: String,
+}
+
+impl From<&Pod> for LeaderNode {
+    fn from(pod: &Pod) -> Self {
+        let status = pod.status.as_ref();
+        LeaderNode {
+            pod_name: pod.metadata.name.clone().unwrap_or_default(),
+            pod_ip: status.and_then(|status| status.pod_ip.clone()).unwrap_or_default(),
+            node_name: pod.spec.as_ref().and_then(|spec| spec.node_name.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Finds the pod with H2O leader node of an H2O deployment of given `name`. Only the pod with H2O leader node
+/// passes the `/kubernetes/isLeaderNode` readiness probe, therefore the leader is the only ready pod.
+///
+/// # Arguments
+/// `client` - Client to list the pods with
+/// `namespace` - Namespace the H2O deployment resides in
+/// `name` - Name of the H2O deployment
+///
+/// # Errors
+///
+/// Returns `Error::NoLeader` if no pod is ready, e.g. H2O is not clustered yet, and `Error::MultipleLeaders`
+/// if more than one pod is ready, which means the H2O nodes formed more than one H2O cluster.
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use deployment::pod::LeaderNode;
+/// use kube::Client;
+/// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
+/// let leader: LeaderNode = deployment::pod::leader(client, &namespace, "any-name").await.unwrap();
+/// println!("H2O leader node '{}' is available at {}", leader.pod_name, leader.pod_ip);
+/// }
+/// ```
+pub async fn leader(client: Client, namespace: &str, name: &str) -> Result<LeaderNode, Error> {
+    let pods: Vec<Pod> = list(client, namespace, name).await?;
+    Ok(LeaderNode::from(leader_of(name, &pods)?))
+}
+
+/// Finds the pod with H2O leader node among the pods of an H2O deployment of given `name`.
+/// See `leader` for details.
+///
+/// # Arguments
+/// `name` - Name of the H2O deployment, used in error messages
+/// `pods` - Pods with H2O nodes belonging to the H2O deployment
+pub fn leader_of<'a, I>(name: &str, pods: I) -> Result<&'a Pod, Error>
+    where I: IntoIterator<Item=&'a Pod> {
+    let leaders: Vec<&Pod> = pods.into_iter()
+        .filter(|pod| is_ready(pod))
+        .collect();
+    match leaders.as_slice() {
+        [leader] => Ok(leader),
+        [] => Err(Error::NoLeader(format!("No pod of H2O cluster '{}' passed the leader node readiness probe.",
+                                          name))),
+        _ => {
+            let names: Vec<String> = leaders.iter()
+                .map(|pod| pod.metadata.name.clone().unwrap_or_default())
+                .collect();
+            Err(Error::MultipleLeaders(format!("Pods {} of H2O cluster '{}' all claim to host the leader node.",
+                                               names.join(", "), name)))
+        }
+    }
+}
+
+/// Label selector matching all the pods with H2O nodes belonging to an H2O deployment of given `name`.
+pub fn label_selector(name: &str) -> String {
+    format!("app={}", name)
+}
+
+/// Returns `true` if the pod is in the `Running` phase and all its containers are running, otherwise `false`.
+///
+/// # Arguments
+/// `pod` - Pod to examine
+pub fn is_running(pod: &Pod) -> bool {
+    let status = match pod.status.as_ref() {
+        Some(status) => status,
+        None => return false,
+    };
+    let containers_running: bool = match status.container_statuses.as_ref() {
+        Some(container_statuses) => !container_statuses.is_empty() && container_statuses.iter()
+            .all(|container_status| container_status.state.as_ref()
+                .map(|state| state.running.is_some())
+                .unwrap_or(false)),
+        None => false,
+    };
+    status.phase.as_deref() == Some("Running") && containers_running
+}
+
+/// Returns `true` if the pod passes its readiness probe. As the readiness probe of H2O pods
+/// queries the `/kubernetes/isLeaderNode` endpoint, only the pod with H2O leader node is ready once H2O is clustered.
+///
+/// # Arguments
+/// `pod` - Pod to examine
+pub fn is_ready(pod: &Pod) -> bool {
+    pod.status.as_ref()
+        .and_then(|status| status.conditions.as_ref())
+        .map(|conditions| conditions.iter()
+            .any(|condition| condition.type_ == "Ready" && condition.status == "True"))
+        .unwrap_or(false)
+}
+
+/// Returns a human-readable reason if the pod has failed or is stuck in a state it will not recover from
+/// without user's intervention, e.g. the image can not be pulled. Otherwise returns `Option::None`.
+///
+/// # Arguments
+/// `pod` - Pod to examine
+pub fn failure_reason(pod: &Pod) -> Option<String> {
+    let status = pod.status.as_ref()?;
+    let pod_name: String = pod.metadata.name.clone().unwrap_or_default();
+    if status.phase.as_deref() == Some("Failed") {
+        return Some(format!("Pod '{}' failed: {}", pod_name,
+                            status.message.as_deref().or(status.reason.as_deref()).unwrap_or("unknown reason")));
+    }
+
+    status.container_statuses.as_ref()?
+        .iter()
+        .find_map(waiting_failure)
+        .map(|reason| format!("Pod '{}' failed: {}", pod_name, reason))
+}
+
+/// Extracts an unrecoverable reason the container is waiting for, if any.
+fn waiting_failure(container_status: &ContainerStatus) -> Option<String> {
+    let waiting = container_status.state.as_ref()?.waiting.as_ref()?;
+    let reason: &str = waiting.reason.as_deref()?;
+    if !UNRECOVERABLE_WAITING_REASONS.contains(&reason) {
+        return None;
+    }
+    Some(match waiting.message.as_deref() {
+        Some(message) => match crate::security::failure_hint(message) {
+            Some(hint) => format!("{} - {} {}", reason, message, hint),
+            None => format!("{} - {}", reason, message),
+        },
+        None => reason.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_openapi::api::core::v1::Pod;
+    use serde_json::json;
+
+    use crate::Error;
+    use crate::pod::LeaderNode;
+
+    fn pod(name: &str, ready: bool) -> Pod {
+        serde_json::from_value(json!({
+            "metadata": { "name": name },
+            "spec": { "containers": [], "nodeName": "node-1" },
+            "status": {
+                "phase": "Running",
+                "podIP": "10.0.0.1",
+                "conditions": [{ "type": "Ready", "status": if ready { "True" } else { "False" } }]
+            }
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_leader_of() {
+        let pods: Vec<Pod> = vec![pod("h2o-0", false), pod("h2o-1", true)];
+        let leader: LeaderNode = LeaderNode::from(super::leader_of("h2o", &pods).unwrap());
+        assert_eq!("h2o-1", leader.pod_name);
+        assert_eq!("10.0.0.1", leader.pod_ip);
+        assert_eq!("node-1", leader.node_name);
+
+        let no_leader: Vec<Pod> = vec![pod("h2o-0", false), pod("h2o-1", false)];
+        assert!(matches!(super::leader_of("h2o", &no_leader), Err(Error::NoLeader(_))));
+
+        let split_brain: Vec<Pod> = vec![pod("h2o-0", true), pod("h2o-1", true)];
+        match super::leader_of("h2o", &split_brain) {
+            Err(Error::MultipleLeaders(message)) => assert!(message.contains("h2o-0, h2o-1")),
+            _ => panic!("Multiple leaders expected."),
+        }
+    }
+}
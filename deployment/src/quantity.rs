@@ -0,0 +1,188 @@
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::crd::Resources;
+use crate::Error;
+
+/// Minimum memory of each H2O node. H2O nodes with less memory fail to start or are unusable.
+pub const MIN_MEMORY: &str = "256Mi";
+/// Minimum memory of each H2O node in bytes, see `MIN_MEMORY`.
+pub const MIN_MEMORY_BYTES: u64 = 256 * 1024 * 1024;
+/// Percentage of the memory limit allocated by the JVM heap, unless `memoryPercentage` is defined.
+pub const DEFAULT_MEMORY_PERCENTAGE: u8 = 50;
+
+/// Binary suffixes used to display memory quantities, from the largest one.
+const BINARY_SUFFIXES: [(&str, u64); 6] = [
+    ("Ei", 1 << 60),
+    ("Pi", 1 << 50),
+    ("Ti", 1 << 40),
+    ("Gi", 1 << 30),
+    ("Mi", 1 << 20),
+    ("Ki", 1 << 10),
+];
+
+/// Amount of memory in bytes, parsed from a Kubernetes quantity, e.g. `4Gi`, `512M` or `1e9`.
+/// Quantities resulting in a fraction of a byte, e.g. `0.1Ki`, are refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MemoryQuantity(u64);
+
+impl MemoryQuantity {
+    /// Constructor for `MemoryQuantity`
+    ///
+    /// # Arguments
+    /// `bytes` - Amount of memory in bytes
+    pub fn from_bytes(bytes: u64) -> Self {
+        MemoryQuantity(bytes)
+    }
+
+    /// Amount of memory in bytes.
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+
+    /// Given `percentage` of this amount of memory, rounded down to whole bytes.
+    ///
+    /// # Arguments
+    /// `percentage` - Percentage in the <0,100> range
+    pub fn percentage(&self, percentage: u8) -> MemoryQuantity {
+        MemoryQuantity((self.0 as u128 * percentage as u128 / 100) as u64)
+    }
+}
+
+impl FromStr for MemoryQuantity {
+    type Err = Error;
+
+    /// Parses a Kubernetes quantity into bytes. Supported are binary suffixes (`Ki` to `Ei`), decimal suffixes
+    /// (`k` to `E`), the milli suffix (`m`) and decimal exponents (`e3`).
+    fn from_str(quantity: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::UserError(format!(
+            "Invalid memory quantity '{}'. Expected a Kubernetes quantity, e.g. 1Gi or 1024Mi.", quantity));
+
+        let unsigned: &str = quantity.strip_prefix('+').unwrap_or(quantity);
+        let number_end: usize = unsigned.find(|character: char| !(character.is_ascii_digit() || character == '.'))
+            .unwrap_or(unsigned.len());
+        let (number, suffix): (&str, &str) = unsigned.split_at(number_end);
+        let (integer, fraction): (&str, &str) = number.split_once('.').unwrap_or((number, ""));
+        if (integer.is_empty() && fraction.is_empty()) || fraction.contains('.') {
+            return Err(invalid());
+        }
+        let digits: u128 = format!("{}{}", integer, fraction).parse().map_err(|_| invalid())?;
+
+        let (multiplier, exponent): (u128, i32) = match suffix {
+            "" => (1, 0),
+            "Ki" => (1 << 10, 0),
+            "Mi" => (1 << 20, 0),
+            "Gi" => (1 << 30, 0),
+            "Ti" => (1 << 40, 0),
+            "Pi" => (1 << 50, 0),
+            "Ei" => (1 << 60, 0),
+            "m" => (1, -3),
+            "k" => (1, 3),
+            "M" => (1, 6),
+            "G" => (1, 9),
+            "T" => (1, 12),
+            "P" => (1, 15),
+            "E" => (1, 18),
+            _ if suffix.starts_with('e') || suffix.starts_with('E') => (1, suffix[1..].parse().map_err(|_| invalid())?),
+            _ => return Err(invalid()),
+        };
+
+        let value: u128 = digits.checked_mul(multiplier).ok_or_else(invalid)?;
+        let power: i32 = exponent - fraction.len() as i32;
+        let bytes: u128 = if power >= 0 {
+            10u128.checked_pow(power as u32).and_then(|scale| value.checked_mul(scale)).ok_or_else(invalid)?
+        } else {
+            let scale: u128 = 10u128.checked_pow(power.unsigned_abs()).ok_or_else(invalid)?;
+            let (whole, remainder): (u128, u128) = (value / scale, value % scale);
+            if remainder != 0 {
+                return Err(Error::UserError(format!("Invalid memory quantity '{}'. Memory must be a whole number of bytes.", quantity)));
+            }
+            whole
+        };
+        u64::try_from(bytes).map(MemoryQuantity).map_err(|_| invalid())
+    }
+}
+
+impl Display for MemoryQuantity {
+    /// Displays the amount of memory with the largest binary suffix possible, e.g. `1Gi` or `460.8Mi`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match BINARY_SUFFIXES.iter().find(|(_, unit)| self.0 >= *unit) {
+            Some((suffix, unit)) => match (self.0 / unit, self.0 % unit) {
+                (whole, 0) => write!(f, "{}{}", whole, suffix),
+                _ => write!(f, "{:.1}{}", self.0 as f64 / *unit as f64, suffix),
+            },
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// Memory limit of each H2O node, enforcing the minimum memory of `MIN_MEMORY`.
+///
+/// # Arguments
+/// `resources` - Resources allocated by each H2O pod
+pub fn memory_limit(resources: &Resources) -> Result<MemoryQuantity, Error> {
+    let memory: MemoryQuantity = resources.memory.parse()?;
+    if memory.bytes() < MIN_MEMORY_BYTES {
+        return Err(Error::UserError(format!("Memory of each H2O node must be at least {}, got '{}'.",
+                                            MIN_MEMORY, resources.memory)));
+    }
+    Ok(memory)
+}
+
+/// Maximum JVM heap of each H2O node - `memory_percentage` of the memory limit. The memory percentage is expected
+/// to be filled in by operator defaults, see `defaults::Defaults`. Returns `Error::UserError` if not defined.
+///
+/// # Arguments
+/// `resources` - Resources allocated by each H2O pod
+///
+/// # Examples
+///
+/// ```
+/// use deployment::crd::Resources;
+/// let resources: Resources = Resources::new(1, "1Gi".to_string(), Option::Some(90));
+/// assert_eq!("921.6Mi", deployment::quantity::jvm_heap(&resources).unwrap().to_string());
+/// ```
+pub fn jvm_heap(resources: &Resources) -> Result<MemoryQuantity, Error> {
+    let percentage: u8 = resources.memory_percentage
+        .ok_or_else(|| Error::UserError("No memory percentage ('resources.memoryPercentage') defined.".to_string()))?;
+    Ok(memory_limit(resources)?.percentage(percentage))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crd::Resources;
+
+    use super::MemoryQuantity;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(1 << 30, "1Gi".parse::<MemoryQuantity>().unwrap().bytes());
+        assert_eq!(1536 << 20, "1.5Gi".parse::<MemoryQuantity>().unwrap().bytes());
+        assert_eq!(512_000_000, "512M".parse::<MemoryQuantity>().unwrap().bytes());
+        assert_eq!(1000, "1e3".parse::<MemoryQuantity>().unwrap().bytes());
+        assert_eq!(2_000_000_000_000_000_000, "2E".parse::<MemoryQuantity>().unwrap().bytes());
+        assert_eq!(1, "1000m".parse::<MemoryQuantity>().unwrap().bytes());
+        assert!("0.1Ki".parse::<MemoryQuantity>().is_err());
+        assert!("1.2.3Gi".parse::<MemoryQuantity>().is_err());
+        assert!("-1Gi".parse::<MemoryQuantity>().is_err());
+        assert!("1GB".parse::<MemoryQuantity>().is_err());
+        assert!("Gi".parse::<MemoryQuantity>().is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!("4Gi", MemoryQuantity::from_bytes(4 << 30).to_string());
+        assert_eq!("460.8Mi", MemoryQuantity::from_bytes(512 << 20).percentage(90).to_string());
+        assert_eq!("1000", MemoryQuantity::from_bytes(1000).to_string());
+    }
+
+    #[test]
+    fn test_jvm_heap() {
+        let resources: Resources = Resources::new(1, "4Gi".to_string(), Option::Some(50));
+        assert_eq!(2 << 30, super::jvm_heap(&resources).unwrap().bytes());
+        assert!(super::jvm_heap(&Resources::new(1, "4Gi".to_string(), Option::None)).is_err());
+        assert!(super::jvm_heap(&Resources::new(1, "1e3".to_string(), Option::Some(50))).is_err());
+        assert!(super::jvm_heap(&Resources::new(1, "255Mi".to_string(), Option::Some(50))).is_err());
+    }
+}
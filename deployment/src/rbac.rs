@@ -0,0 +1,163 @@
+use k8s_openapi::api::core::v1::ServiceAccount;
+use k8s_openapi::api::rbac::v1::{PolicyRule, Role, RoleBinding, RoleRef, Subject};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::{Api, Client};
+
+use crate::crd::{H2OSpec, ServiceAccountSpec};
+use crate::delete_if_exists;
+use crate::Error;
+
+/// Name of the service account H2O pods run under, if other than the namespace's default service account.
+/// A dedicated service account shares the name of the H2O deployment.
+///
+/// # Arguments
+/// `name` - Name of the H2O deployment
+/// `specification` - Specification of the H2O cluster
+pub fn service_account_name(name: &str, specification: &H2OSpec) -> Option<String> {
+    let service_account: &ServiceAccountSpec = specification.service_account.as_ref()?;
+    if service_account.create {
+        Some(name.to_string())
+    } else {
+        service_account.name.clone()
+    }
+}
+
+/// Creates a dedicated `ServiceAccount` of H2O pods, named after the H2O deployment.
+///
+/// # Arguments
+/// `name` - Name of the H2O deployment. Also used to label the service account.
+/// `namespace` - Namespace the service account belongs to.
+/// `owner` - Optional owner of the service account, typically the `H2O` custom resource.
+pub fn h2o_service_account(name: &str, namespace: &str, owner: Option<&OwnerReference>) -> ServiceAccount {
+    ServiceAccount {
+        metadata: crate::h2o_metadata(name, namespace, owner),
+        ..ServiceAccount::default()
+    }
+}
+
+/// Creates a `Role` with exactly the permissions H2O needs - H2O nodes may look up pods of the H2O cluster
+/// to form the cluster.
+///
+/// # Arguments
+/// `name` - Name of the H2O deployment. Also used to label the role.
+/// `namespace` - Namespace the role belongs to.
+/// `owner` - Optional owner of the role, typically the `H2O` custom resource.
+pub fn h2o_role(name: &str, namespace: &str, owner: Option<&OwnerReference>) -> Role {
+    Role {
+        metadata: crate::h2o_metadata(name, namespace, owner),
+        rules: Some(vec![PolicyRule {
+            api_groups: Some(vec!["".to_string()]),
+            resources: Some(vec!["pods".to_string()]),
+            verbs: vec!["get".to_string(), "list".to_string(), "watch".to_string()],
+            ..PolicyRule::default()
+        }]),
+    }
+}
+
+/// Creates a `RoleBinding` granting the permissions of the `Role` created by `h2o_role` to the dedicated
+/// `ServiceAccount` created by `h2o_service_account`.
+///
+/// # Arguments
+/// `name` - Name of the H2O deployment. Also used to label the role binding.
+/// `namespace` - Namespace the role binding belongs to.
+/// `owner` - Optional owner of the role binding, typically the `H2O` custom resource.
+pub fn h2o_role_binding(name: &str, namespace: &str, owner: Option<&OwnerReference>) -> RoleBinding {
+    RoleBinding {
+        metadata: crate::h2o_metadata(name, namespace, owner),
+        role_ref: RoleRef {
+            api_group: "rbac.authorization.k8s.io".to_string(),
+            kind: "Role".to_string(),
+            name: name.to_string(),
+        },
+        subjects: Some(vec![Subject {
+            api_group: None,
+            kind: "ServiceAccount".to_string(),
+            name: name.to_string(),
+            namespace: Some(namespace.to_string()),
+        }]),
+    }
+}
+
+/// Idempotently creates or updates the dedicated `ServiceAccount`, `Role` and `RoleBinding` of an H2O deployment,
+/// if `specification.service_account` requests them to be created. Otherwise nothing is applied.
+/// See `apply::apply` for details.
+///
+/// # Arguments
+/// `client` - Client to apply the resources with. Must hold the permissions granted by the `Role`.
+/// `specification` - Specification of the H2O cluster
+/// `namespace` - Namespace to deploy the resources to
+/// `name` - Name of the H2O deployment
+/// `owner` - Optional owner of the resources, typically the `H2O` custom resource.
+pub async fn apply(
+    client: Client,
+    specification: &H2OSpec,
+    namespace: &str,
+    name: &str,
+    owner: Option<&OwnerReference>,
+) -> Result<(), Error> {
+    if !matches!(specification.service_account.as_ref(), Some(service_account) if service_account.create) {
+        return Ok(());
+    }
+    let service_account_api: Api<ServiceAccount> = Api::namespaced(client.clone(), namespace);
+    let role_api: Api<Role> = Api::namespaced(client.clone(), namespace);
+    let role_binding_api: Api<RoleBinding> = Api::namespaced(client, namespace);
+    crate::apply::apply(&service_account_api, name, &h2o_service_account(name, namespace, owner)).await?;
+    crate::apply::apply(&role_api, name, &h2o_role(name, namespace, owner)).await?;
+    crate::apply::apply(&role_binding_api, name, &h2o_role_binding(name, namespace, owner)).await?;
+    Ok(())
+}
+
+/// Invokes asynchronous deletion of the dedicated `ServiceAccount`, `Role` and `RoleBinding` of an H2O deployment.
+/// As the resources are optional, resources not found are ignored.
+///
+/// # Arguments
+///
+/// `client` - Client to delete the resources with
+/// `namespace` - Namespace to delete the resources from
+/// `name` - Name of the H2O deployment the resources belong to.
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use kube::Client;
+/// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
+/// deployment::rbac::delete(client, &namespace, "any-name").await.unwrap();
+/// }
+/// ```
+pub async fn delete(client: Client, namespace: &str, name: &str) -> Result<(), Error> {
+    delete_if_exists::<RoleBinding>(client.clone(), namespace, name).await?;
+    delete_if_exists::<Role>(client.clone(), namespace, name).await?;
+    delete_if_exists::<ServiceAccount>(client, namespace, name).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_openapi::api::rbac::v1::RoleBinding;
+
+    use crate::crd::{H2OSpec, Resources, ServiceAccountSpec};
+
+    #[test]
+    fn test_service_account_name() {
+        let mut specification: H2OSpec = H2OSpec::new(1, Option::Some("latest".to_string()),
+                                                      Resources::new(1, "1Gi".to_string(), Option::None), Option::None);
+        assert!(super::service_account_name("h2o", &specification).is_none());
+
+        specification.service_account = Some(ServiceAccountSpec::new(false, Some("existing".to_string())));
+        assert_eq!(Some("existing".to_string()), super::service_account_name("h2o", &specification));
+
+        specification.service_account = Some(ServiceAccountSpec::new(true, Some("existing".to_string())));
+        assert_eq!(Some("h2o".to_string()), super::service_account_name("h2o", &specification));
+    }
+
+    #[test]
+    fn test_role_binding() {
+        let role_binding: RoleBinding = super::h2o_role_binding("h2o", "h2o-namespace", Option::None);
+        assert_eq!("h2o", role_binding.role_ref.name);
+        let subject = &role_binding.subjects.unwrap()[0];
+        assert_eq!("h2o", subject.name);
+        assert_eq!(Some("h2o-namespace".to_string()), subject.namespace);
+    }
+}
@@ -0,0 +1,143 @@
+use k8s_openapi::api::core::v1::{Capabilities, EmptyDirVolumeSource, PodSecurityContext, SeccompProfile, SecurityContext, Volume, VolumeMount};
+
+use crate::crd::{H2OSpec, SecurityContextSpec};
+
+/// User ID, group ID and filesystem group H2O containers run as, unless overridden.
+pub const DEFAULT_USER_ID: i64 = 1000;
+/// Name of the writable volume mounted to `/tmp`, as the root filesystem of H2O containers is read-only.
+pub const TMP_VOLUME_NAME: &str = "tmp";
+/// Path the writable temporary volume is mounted to in H2O containers.
+pub const TMP_MOUNT_PATH: &str = "/tmp";
+
+/// Security context of H2O pods. H2O pods run as a non-root user with the runtime's default seccomp profile,
+/// as required by the restricted pod security standard. User, group and filesystem group may be overridden
+/// by `specification.security_context`.
+///
+/// # Arguments
+/// `specification` - Specification of the H2O cluster
+pub fn pod_security_context(specification: &H2OSpec) -> PodSecurityContext {
+    let overrides: SecurityContextSpec = specification.security_context.clone().unwrap_or_default();
+    PodSecurityContext {
+        run_as_non_root: Some(overrides.run_as_non_root.unwrap_or(true)),
+        run_as_user: Some(overrides.run_as_user.unwrap_or(DEFAULT_USER_ID)),
+        run_as_group: Some(overrides.run_as_group.unwrap_or(DEFAULT_USER_ID)),
+        fs_group: Some(overrides.fs_group.unwrap_or(DEFAULT_USER_ID)),
+        seccomp_profile: Some(SeccompProfile {
+            type_: "RuntimeDefault".to_string(),
+            localhost_profile: None,
+        }),
+        ..PodSecurityContext::default()
+    }
+}
+
+/// Security context of H2O containers. Privilege escalation is disallowed, all capabilities are dropped and
+/// the root filesystem is read-only unless overridden by `specification.security_context`.
+///
+/// # Arguments
+/// `specification` - Specification of the H2O cluster
+pub fn container_security_context(specification: &H2OSpec) -> SecurityContext {
+    let read_only_root_filesystem: bool = specification.security_context.as_ref()
+        .and_then(|overrides| overrides.read_only_root_filesystem)
+        .unwrap_or(true);
+    SecurityContext {
+        allow_privilege_escalation: Some(false),
+        capabilities: Some(Capabilities {
+            add: None,
+            drop: Some(vec!["ALL".to_string()]),
+        }),
+        privileged: Some(false),
+        read_only_root_filesystem: Some(read_only_root_filesystem),
+        ..SecurityContext::default()
+    }
+}
+
+/// Writable volume backing `/tmp` of H2O containers. The JVM and H2O's default ice directory both live in `/tmp`.
+pub fn tmp_volume() -> Volume {
+    Volume {
+        name: TMP_VOLUME_NAME.to_string(),
+        empty_dir: Some(EmptyDirVolumeSource::default()),
+        ..Volume::default()
+    }
+}
+
+/// Mount of the writable temporary volume, see `tmp_volume`.
+pub fn tmp_volume_mount() -> VolumeMount {
+    VolumeMount {
+        name: TMP_VOLUME_NAME.to_string(),
+        mount_path: TMP_MOUNT_PATH.to_string(),
+        ..VolumeMount::default()
+    }
+}
+
+/// Human-readable warnings about H2O pods likely unable to start with the security context rendered
+/// for the `specification`, or not complying with the restricted pod security standard. An empty vector
+/// is returned if there is nothing to warn about.
+///
+/// The official H2O images are started with a command only writing to `/tmp` and the H2O data volume. Custom images
+/// are not inspected, the user is warned instead unless the defaults have been explicitly overridden.
+///
+/// # Arguments
+/// `specification` - Specification of the H2O cluster
+pub fn warnings(specification: &H2OSpec) -> Vec<String> {
+    let overrides: SecurityContextSpec = specification.security_context.clone().unwrap_or_default();
+    let mut warnings: Vec<String> = Vec::new();
+    if let Some(custom_image) = specification.custom_image.as_ref() {
+        if specification.security_context.is_none() {
+            warnings.push(format!("Custom image '{}' must be able to run as user {} with a read-only root filesystem, only '{}' and mounted volumes are writable. Use 'securityContext' to override the defaults.",
+                                  custom_image.image, DEFAULT_USER_ID, TMP_MOUNT_PATH));
+        }
+    }
+    if overrides.run_as_non_root == Some(false) || overrides.run_as_user == Some(0) {
+        warnings.push("H2O pods allowed to run as root do not comply with the restricted pod security standard.".to_string());
+    }
+    if overrides.run_as_user == Some(0) && overrides.run_as_non_root != Some(false) {
+        warnings.push("H2O pods set to run as user 0 will not start unless 'runAsNonRoot' is disabled.".to_string());
+    }
+    warnings
+}
+
+/// Hint to resolve a container failure caused by the security context, if the failure `message` reported
+/// by Kubernetes is recognized, e.g. an image with a non-numeric user refused by the `runAsNonRoot` check.
+/// Otherwise returns `Option::None`.
+///
+/// # Arguments
+/// `message` - Message of a waiting container as reported by Kubernetes
+pub fn failure_hint(message: &str) -> Option<&'static str> {
+    if message.contains("runAsNonRoot") {
+        Some("The image runs as root or as a non-numeric user. Set 'securityContext.runAsUser' to a non-root user ID the image supports.")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crd::{CustomImage, H2OSpec, Resources, SecurityContextSpec};
+
+    fn specification(custom_image: Option<CustomImage>) -> H2OSpec {
+        H2OSpec::new(1, Option::Some("latest".to_string()), Resources::new(1, "1Gi".to_string(), Option::None),
+                     custom_image)
+    }
+
+    #[test]
+    fn test_warnings() {
+        assert!(super::warnings(&specification(Option::None)).is_empty());
+
+        let mut custom: H2OSpec = specification(Option::Some(CustomImage::new("registry/h2o:latest".to_string(), Option::None)));
+        let warnings: Vec<String> = super::warnings(&custom);
+        assert_eq!(1, warnings.len());
+        assert!(warnings[0].contains("registry/h2o:latest"));
+
+        custom.security_context = Some(SecurityContextSpec {
+            run_as_user: Some(0),
+            ..SecurityContextSpec::default()
+        });
+        assert_eq!(2, super::warnings(&custom).len());
+    }
+
+    #[test]
+    fn test_failure_hint() {
+        assert!(super::failure_hint("container has runAsNonRoot and image will run as root").is_some());
+        assert!(super::failure_hint("secret \"any\" not found").is_none());
+    }
+}
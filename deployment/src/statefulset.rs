@@ -1,123 +1,319 @@
-use k8s_openapi::api::apps::v1::StatefulSet;
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::apps::v1::{StatefulSet, StatefulSetSpec};
+use k8s_openapi::api::core::v1::{Affinity, Container, ContainerPort, EnvVar, HTTPGetAction, LocalObjectReference, PodAffinityTerm, PodAntiAffinity, PodSpec, PodTemplateSpec, Probe, ResourceRequirements, TopologySpreadConstraint, Volume, VolumeMount, WeightedPodAffinityTerm};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta, OwnerReference};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
 use kube::{Api, Client};
-use kube::api::{DeleteParams, PostParams};
+use kube::api::PostParams;
 use log::debug;
 
-use crate::crd::H2OSpec;
+use crate::crd::{H2OSpec, Resources, Spreading, SpreadingPolicy, SpreadingTopology};
 use crate::Error;
 
-const STATEFUL_SET_TEMPLATE: &str = r#"
-apiVersion: apps/v1
-kind: StatefulSet
-metadata:
-  name: <name>
-  namespace: <namespace>
-  labels:
-    app: <name>
-spec:
-  serviceName: <name>
-  podManagementPolicy: "Parallel"
-  replicas: <nodes>
-  selector:
-    matchLabels:
-      app: <name>
-  template:
-    metadata:
-      labels:
-        app: <name>
-    spec:
-      containers:
-        - name: <name>
-          image: '<h2o-image>'
-<command-line>
-          ports:
-            - containerPort: 54321
-              protocol: TCP
-            - containerPort: 54322
-              protocol: TCP
-          readinessProbe:
-            httpGet:
-              path: /kubernetes/isLeaderNode
-              port: 8081
-            initialDelaySeconds: 5
-            periodSeconds: 5
-            failureThreshold: 1
-          resources:
-            limits:
-              cpu: '<num-cpu>'
-              memory: <memory>
-            requests:
-              cpu: '<num-cpu>'
-              memory: <memory>
-          env:
-          - name: H2O_KUBERNETES_SERVICE_DNS
-            value: <name>.<namespace>.svc.cluster.local
-          - name: H2O_NODE_EXPECTED_COUNT
-            value: '<nodes>'
-          - name: H2O_KUBERNETES_API_PORT
-            value: '8081'
-"#;
-
-/// Creates an H2O `StatefulSet` object from given parameters for further deployment into Kubernetes cluster
-/// from a YAML template.
+/// Repository with official H2O Docker images for Kubernetes. H2O version is used as a tag.
+const OFFICIAL_IMAGE_REPOSITORY: &str = "h2oai/h2o-open-source-k8s";
+/// Port H2O's REST API and Flow are served on.
+pub const H2O_REST_PORT: i32 = 54321;
+/// Port used by H2O nodes to communicate with each other.
+pub const H2O_INTERNAL_PORT: i32 = 54322;
+/// Port of H2O's Kubernetes clustering API, serving the leader node readiness probe.
+pub const H2O_KUBERNETES_API_PORT: i32 = 8081;
+
+/// Creates an H2O `StatefulSet` object from given parameters for further deployment into Kubernetes cluster.
+/// The object is constructed directly from the `specification` - no user input is interpreted as a part
+/// of any template.
 ///
 /// # Arguments
 /// `name` - Name of the H2O deployment. Also used to label the resources.
 /// `namespace` - Namespace the resources belong to - used in resources metadata.
-/// `docker_image` - The Docker image with H2O to use
-/// `command` - Custom command for the `docker_image` with H2O
-/// `nodes` - Number of H2O nodes to spown - translated to a number of pods/replicas in a statefulset.
-/// `memory` - Amount of memory limits and requests for each pod. These are set to equal values in order
-/// for H2O to be reproducible. Kubernetes-compliant string expected.
-/// `num_cpu` - Number of virtual CPUs for each pod (and therefore each H2O node). Same value is set to
-/// both requests and limits to ensure reproducibility of H2O's operations.
+/// `specification` - Specification of the H2O cluster. Either H2O version or a custom image must be defined.
+/// `owner` - Optional owner of the `StatefulSet`, typically the `H2O` custom resource.
+///
+/// # Errors
+///
+/// Returns `Error::UserError` listing all the violations if the specification is invalid, see `validation::violations`.
 ///
 /// # Examples
 ///
 /// ```no_run
-///     use k8s_openapi::api::apps::v1::StatefulSet;
+/// use k8s_openapi::api::apps::v1::StatefulSet;
+/// use deployment::crd::{H2OSpec, Resources};
 /// use deployment::statefulset::h2o_stateful_set;
-/// let stateful_set: StatefulSet = h2o_stateful_set(
-/// "any-name",
-/// "default",
-/// "h2oai/h2o-open-source-k8s:latest",
-/// Option::None,
-/// 3,
-/// "32Gi",
-/// 8
-/// )
-/// .expect("Could not create StatefulSet from YAML template");
+/// let specification: H2OSpec = H2OSpec::new(
+///     3,
+///     Option::Some("latest".to_string()),
+///     Resources::new(8, "32Gi".to_string(), Option::Some(50)),
+///     Option::None,
+/// );
+/// let stateful_set: StatefulSet = h2o_stateful_set("any-name", "default", &specification, Option::None)
+///     .expect("Could not create StatefulSet from specification");
 /// ```
 pub fn h2o_stateful_set(
     name: &str,
     namespace: &str,
-    docker_image: &str,
-    command: Option<&str>,
-    nodes: u32,
-    memory: &str,
-    num_cpu: u32,
+    specification: &H2OSpec,
+    owner: Option<&OwnerReference>,
 ) -> Result<StatefulSet, Error> {
-    let mut command_line: String = "          command: <command>".to_string(); // with proper indentation
-    match command {
-        None => command_line = "".to_string(),
-        Some(custom_command) => {
-            command_line = command_line.replace("<command>", custom_command);
+    // Specifications not admitted by the validating webhook, e.g. created before it was registered, are rejected here
+    crate::validation::validate(specification)?;
+    let docker_image: String;
+    let command: Option<Vec<String>>;
+    let args: Option<Vec<String>>;
+    // Arguments required by the H2O data volume, authentication and TLS precede user-defined arguments
+    let mut h2o_args: Vec<String> = specification.storage.as_ref()
+        .map(crate::storage::h2o_args)
+        .unwrap_or_default();
+    h2o_args.extend(specification.auth.as_ref().map(crate::auth::h2o_args).unwrap_or_default());
+    h2o_args.extend(specification.tls.as_ref().map(crate::tls::h2o_args).unwrap_or_default());
+    h2o_args.extend(specification.h2o_args.iter().cloned());
+    let mut jvm_args: Vec<String> = specification.auth.as_ref()
+        .map(crate::auth::jvm_args)
+        .unwrap_or_default();
+    jvm_args.extend(specification.jvm_args.iter().cloned());
+    let mut env: Vec<EnvVar> = vec![
+        env_var("H2O_KUBERNETES_SERVICE_DNS", &format!("{}.{}.svc.cluster.local", name, namespace)),
+        env_var("H2O_NODE_EXPECTED_COUNT", &specification.nodes.to_string()),
+        env_var("H2O_KUBERNETES_API_PORT", &H2O_KUBERNETES_API_PORT.to_string()),
+    ];
+    env.extend(specification.tls.as_ref().map(crate::tls::password_env_var));
+
+    // H2O version and custom image are mutually exclusive, as enforced by the validation above
+    if let Some(image) = specification.custom_image.as_ref() {
+        docker_image = image.image.clone();
+        // The command line of a custom image is unknown, JVM arguments are therefore passed in the environment
+        // variable recognized by the JVM itself and H2O arguments are passed as container arguments.
+        if !jvm_args.is_empty() {
+            env.push(env_var("JAVA_TOOL_OPTIONS", &jvm_args.join(" ")));
         }
+        // The user optionally sets a custom command to be used for the custom image. If none is set,
+        // the image's entrypoint is used. H2O arguments are passed as container arguments in both cases.
+        command = image.command.as_deref().map(exec_command);
+        args = Some(h2o_args).filter(|h2o_args| !h2o_args.is_empty());
+    } else if let Some(version) = specification.version.as_ref() {
+        let repository: &str = specification.image_repository.as_deref().unwrap_or(OFFICIAL_IMAGE_REPOSITORY);
+        docker_image = format!("{}:{}", repository, version);
+        command = Option::Some(official_image_command(specification, &jvm_args, &h2o_args)?);
+        args = None;
+    } else {
+        // At least one of the above has to be specified - H2O version that serves as a Docker image tag,
+        // or a full definition of custom image.
+        return Err(Error::UserError("Unable to create H2O statefulset. Either H2O version or a complete custom image specification must be provided. None obtained."
+            .to_string()));
     }
 
-    let stateful_set_definition = STATEFUL_SET_TEMPLATE
-        .replace("<name>", name)
-        .replace("<namespace>", namespace)
-        .replace("<h2o-image>", docker_image)
-        .replace("<command-line>", &command_line)
-        .replace("<nodes>", &nodes.to_string())
-        .replace("<memory>", memory)
-        .replace("<num-cpu>", &num_cpu.to_string());
+    // User-defined environment variables take precedence
+    env.retain(|default_var| !specification.env.iter().any(|user_var| user_var.name == default_var.name));
+    env.extend(specification.env.iter().map(|user_var| env_var(&user_var.name, &user_var.value)));
 
-    debug!("Stateful set result:\n{}", stateful_set_definition);
+    let labels: BTreeMap<String, String> = crate::h2o_labels(name);
+    // The root filesystem is read-only by default, writable `/tmp` is always provided
+    let mut volume_mounts: Vec<VolumeMount> = vec![crate::security::tmp_volume_mount()];
+    volume_mounts.extend(specification.storage.as_ref().map(crate::storage::volume_mount));
+    if specification.auth.is_some() {
+        volume_mounts.push(crate::auth::volume_mount());
+    }
+    if specification.tls.is_some() {
+        volume_mounts.push(crate::tls::volume_mount());
+    }
+    let mut volumes: Vec<Volume> = vec![crate::security::tmp_volume()];
+    volumes.extend(specification.auth.as_ref().map(crate::auth::volume));
+    volumes.extend(specification.tls.as_ref().map(crate::tls::volume));
 
-    let stateful_set: StatefulSet = serde_yaml::from_str(&stateful_set_definition)?;
-    return Ok(stateful_set);
+    let container: Container = Container {
+        name: name.to_string(),
+        image: Some(docker_image),
+        image_pull_policy: specification.image_pull_policy.map(|policy| format!("{:?}", policy)),
+        command,
+        args,
+        ports: Some(vec![
+            ContainerPort {
+                container_port: H2O_REST_PORT,
+                protocol: Some("TCP".to_string()),
+                ..ContainerPort::default()
+            },
+            ContainerPort {
+                container_port: H2O_INTERNAL_PORT,
+                protocol: Some("TCP".to_string()),
+                ..ContainerPort::default()
+            },
+        ]),
+        readiness_probe: Some(Probe {
+            http_get: Some(HTTPGetAction {
+                path: Some("/kubernetes/isLeaderNode".to_string()),
+                port: IntOrString::Int(H2O_KUBERNETES_API_PORT),
+                ..HTTPGetAction::default()
+            }),
+            initial_delay_seconds: Some(5),
+            period_seconds: Some(5),
+            failure_threshold: Some(1),
+            ..Probe::default()
+        }),
+        resources: Some(resource_requirements(&specification.resources)),
+        env: Some(env),
+        volume_mounts: Some(volume_mounts),
+        security_context: Some(crate::security::container_security_context(specification)),
+        ..Container::default()
+    };
+
+    let stateful_set: StatefulSet = StatefulSet {
+        metadata: crate::h2o_metadata(name, namespace, owner),
+        spec: Some(StatefulSetSpec {
+            service_name: name.to_string(),
+            pod_management_policy: Some("Parallel".to_string()),
+            replicas: Some(specification.nodes as i32),
+            selector: h2o_pods_selector(name),
+            volume_claim_templates: specification.storage.as_ref()
+                .map(|storage| vec![crate::storage::volume_claim_template(name, storage)]),
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(labels),
+                    ..ObjectMeta::default()
+                }),
+                spec: Some(PodSpec {
+                    containers: vec![container],
+                    volumes: Some(volumes),
+                    security_context: Some(crate::security::pod_security_context(specification)),
+                    service_account_name: crate::rbac::service_account_name(name, specification),
+                    node_selector: Some(specification.node_selector.clone())
+                        .filter(|node_selector| !node_selector.is_empty()),
+                    tolerations: Some(specification.tolerations.clone())
+                        .filter(|tolerations| !tolerations.is_empty()),
+                    affinity: affinity(name, specification),
+                    topology_spread_constraints: topology_spread_constraints(name, specification),
+                    priority_class_name: specification.priority_class_name.clone(),
+                    image_pull_secrets: Some(specification.image_pull_secrets.iter()
+                        .map(|secret| LocalObjectReference { name: Some(secret.clone()) })
+                        .collect::<Vec<LocalObjectReference>>())
+                        .filter(|secrets| !secrets.is_empty()),
+                    ..PodSpec::default()
+                }),
+            },
+            ..StatefulSetSpec::default()
+        }),
+        status: None,
+    };
+
+    debug!("Stateful set result:\n{:?}", stateful_set);
+    Ok(stateful_set)
+}
+
+/// Affinity of H2O pods. User-defined affinity is extended with pod anti-affinity spreading the H2O pods
+/// across Kubernetes nodes, if requested by `specification.spreading`. Anti-affinity is only rendered for
+/// the `Hostname` topology, as required anti-affinity across zones would limit the H2O cluster to one H2O node per zone.
+fn affinity(name: &str, specification: &H2OSpec) -> Option<Affinity> {
+    let spreading: Spreading = match specification.spreading {
+        Some(spreading) if spreading.topology == SpreadingTopology::Hostname => spreading,
+        _ => return specification.affinity.clone(),
+    };
+    let term: PodAffinityTerm = PodAffinityTerm {
+        label_selector: Some(h2o_pods_selector(name)),
+        namespaces: None,
+        topology_key: spreading.topology.topology_key().to_string(),
+    };
+
+    let mut affinity: Affinity = specification.affinity.clone().unwrap_or_default();
+    let mut anti_affinity: PodAntiAffinity = affinity.pod_anti_affinity.take().unwrap_or_default();
+    match spreading.policy {
+        SpreadingPolicy::None => return specification.affinity.clone(),
+        SpreadingPolicy::Preferred => anti_affinity.preferred_during_scheduling_ignored_during_execution
+            .get_or_insert_with(Vec::new)
+            .push(WeightedPodAffinityTerm {
+                pod_affinity_term: term,
+                weight: 100,
+            }),
+        SpreadingPolicy::Required => anti_affinity.required_during_scheduling_ignored_during_execution
+            .get_or_insert_with(Vec::new)
+            .push(term),
+    }
+    affinity.pod_anti_affinity = Some(anti_affinity);
+    Some(affinity)
+}
+
+/// Topology spread constraints keeping the number of H2O pods in each topology domain even, as requested
+/// by `specification.spreading`.
+fn topology_spread_constraints(name: &str, specification: &H2OSpec) -> Option<Vec<TopologySpreadConstraint>> {
+    let spreading: Spreading = specification.spreading?;
+    let when_unsatisfiable: &str = match spreading.policy {
+        SpreadingPolicy::None => return None,
+        SpreadingPolicy::Preferred => "ScheduleAnyway",
+        SpreadingPolicy::Required => "DoNotSchedule",
+    };
+    Some(vec![TopologySpreadConstraint {
+        label_selector: Some(h2o_pods_selector(name)),
+        max_skew: 1,
+        topology_key: spreading.topology.topology_key().to_string(),
+        when_unsatisfiable: when_unsatisfiable.to_string(),
+    }])
+}
+
+/// Label selector matching the H2O pods of an H2O deployment of given `name`.
+fn h2o_pods_selector(name: &str) -> LabelSelector {
+    LabelSelector {
+        match_labels: Some(crate::h2o_labels(name)),
+        ..LabelSelector::default()
+    }
+}
+
+/// Limits and requests of H2O containers. Requests not defined by `resources.requests` equal the limits.
+/// Ephemeral storage, if defined, is both requested and limited.
+fn resource_requirements(resources: &Resources) -> ResourceRequirements {
+    let mut limits: BTreeMap<String, Quantity> = BTreeMap::new();
+    limits.insert("cpu".to_string(), Quantity(resources.cpu.to_string()));
+    limits.insert("memory".to_string(), Quantity(resources.memory.clone()));
+    if let Some(ephemeral_storage) = resources.ephemeral_storage.as_ref() {
+        limits.insert("ephemeral-storage".to_string(), Quantity(ephemeral_storage.clone()));
+    }
+
+    let mut requests: BTreeMap<String, Quantity> = limits.clone();
+    if let Some(resource_requests) = resources.requests.as_ref() {
+        if let Some(cpu) = resource_requests.cpu.as_ref() {
+            requests.insert("cpu".to_string(), Quantity(cpu.clone()));
+        }
+        if let Some(memory) = resource_requests.memory.as_ref() {
+            requests.insert("memory".to_string(), Quantity(memory.clone()));
+        }
+    }
+
+    ResourceRequirements {
+        limits: Some(limits),
+        requests: Some(requests),
+    }
+}
+
+/// Container command of the official H2O image in the exec form - the JVM is started directly, with no shell involved.
+/// JVM arguments precede the H2O jar, H2O arguments follow it. The JVM heap is derived from the container's memory limit,
+/// never from the memory request. The memory percentage is expected to be filled in by operator defaults, see `defaults::Defaults`.
+fn official_image_command(specification: &H2OSpec, jvm_args: &[String], h2o_args: &[String]) -> Result<Vec<String>, Error> {
+    let memory_percentage: u8 = specification.resources.memory_percentage
+        .ok_or_else(|| Error::UserError("Unable to create H2O statefulset. No memory percentage ('resources.memoryPercentage') defined.".to_string()))?;
+    let mut command: Vec<String> = vec![
+        "java".to_string(),
+        "-XX:+UseContainerSupport".to_string(),
+        format!("-XX:MaxRAMPercentage={}", memory_percentage),
+    ];
+    command.extend(jvm_args.iter().cloned());
+    command.push("-jar".to_string());
+    command.push("/opt/h2oai/h2o-3/h2o.jar".to_string());
+    command.extend(h2o_args.iter().cloned());
+    Ok(command)
+}
+
+/// Container command of a custom image in the exec form, run with no shell involved. The command is either a YAML sequence,
+/// e.g. `["/opt/start.sh", "-v"]`, or a plain command line split on whitespace.
+fn exec_command(command: &str) -> Vec<String> {
+    serde_yaml::from_str::<Vec<String>>(command)
+        .unwrap_or_else(|_| command.split_whitespace().map(str::to_string).collect())
+}
+
+/// Creates a plain environment variable with given `name` and `value`.
+fn env_var(name: &str, value: &str) -> EnvVar {
+    EnvVar {
+        name: name.to_string(),
+        value: Some(value.to_string()),
+        value_from: None,
+    }
 }
 
 /// Invokes asynchronous creation of `StatefulSet` of H2O pods in a Kubernetes cluster according to the specification.
@@ -134,46 +330,7 @@ pub async fn create(
     name: &str,
 ) -> Result<StatefulSet, Error> {
     let statefulset_api: Api<StatefulSet> = Api::namespaced(client.clone(), namespace);
-    let mut official_image_temp: String = String::from("h2oai/h2o-open-source-k8s:");
-    let docker_image: &str;
-    let command_string: String;
-    let command: Option<&str>;
-
-
-    // Custom image overrides H2O version in case both is specified
-    if let Some(image) = specification.custom_image.as_ref() {
-        docker_image = &image.image;
-        // The user optionally sets a custom entrypoint to be used for the custom image. If no
-        match &image.command {
-            None => {
-                command = Option::None;
-            }
-            Some(custom_command) => {
-                command = Option::Some(custom_command);
-            }
-        }
-    } else if specification.version.is_some() {
-        official_image_temp.push_str(specification.version.as_ref().unwrap());
-        docker_image = &official_image_temp;
-        command_string = format!(r#"["/bin/bash", "-c", "java -XX:+UseContainerSupport -XX:MaxRAMPercentage={} -jar /opt/h2oai/h2o-3/h2o.jar"]"#,
-                                 specification.resources.memory_percentage.unwrap_or(50)); // Must be saved to a String with the same lifetime as the optional command
-        command = Option::Some(&command_string);
-    } else {
-        // At least one of the above has to be specified - H2O version that serves as a Docker image tag,
-        // or a full definition of custom image.
-        return Err(Error::UserError("Unable to create H2O statefulset. Either H2O version or a complete custom image specification must be provided. None obtained."
-            .to_string()));
-    }
-
-    let stateful_set: StatefulSet = h2o_stateful_set(
-        name,
-        namespace,
-        docker_image,
-        command,
-        specification.nodes,
-        &specification.resources.memory,
-        specification.resources.cpu,
-    )?;
+    let stateful_set: StatefulSet = h2o_stateful_set(name, namespace, specification, Option::None)?;
 
     let statefulset : StatefulSet = statefulset_api
         .create(&PostParams::default(), &stateful_set)
@@ -181,15 +338,38 @@ pub async fn create(
     Ok(statefulset)
 }
 
+/// Idempotently creates or updates a `StatefulSet` of H2O pods according to the specification. If the `StatefulSet`
+/// already exists and matches the specification, it is left untouched. See `apply::apply` for details.
+///
+/// # Arguments
+/// `client` - Client to apply the StatefulSet with
+/// `specification` - Specification of the H2O cluster
+/// `namespace` - namespace to deploy the statefulset to
+/// `name` - Name of the statefulset, used for statefulset and pod labeling as well.
+/// `owner` - Optional owner of the statefulset, typically the `H2O` custom resource.
+pub async fn apply(
+    client: Client,
+    specification: &H2OSpec,
+    namespace: &str,
+    name: &str,
+    owner: Option<&OwnerReference>,
+) -> Result<StatefulSet, Error> {
+    let statefulset_api: Api<StatefulSet> = Api::namespaced(client.clone(), namespace);
+    let stateful_set: StatefulSet = h2o_stateful_set(name, namespace, specification, owner)?;
+    crate::apply::apply(&statefulset_api, name, &stateful_set).await
+}
+
 /// Invokes asynchronous deletion of a `StatefulSet` of H2O pods from a Kubernetes cluster.
 ///
 /// # Arguments
 ///
 /// `client` - Client to delete the statefulset with
 /// `namespace` - Namespace to delete the statefulset from. User is responsible to provide
-/// correct namespace. Otherwise `Result::Err` is returned.
+/// correct namespace.
 /// `name` - Name of the statefulset to invoke deletion for.
 ///
+/// A statefulset already deleted or never created is not an error, the deletion may be repeated.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -201,9 +381,300 @@ pub async fn create(
 /// }
 /// ```
 pub async fn delete(client: Client, namespace: &str, name: &str) -> Result<(), Error> {
-    let statefulset_api: Api<StatefulSet> = Api::namespaced(client.clone(), namespace);
-    let delete_params: DeleteParams = DeleteParams::default();
+    crate::delete_if_exists::<StatefulSet>(client, namespace, name).await
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_openapi::api::apps::v1::StatefulSet;
+    use k8s_openapi::api::core::v1::{Affinity, Container, PodSpec};
+    use serde_json::json;
+
+    use crate::crd::{Auth, AuthMethod, CustomImage, EnvVariable, H2OSpec, ImagePullPolicy, ResourceRequests, Resources,
+                     SecurityContextSpec, Spreading, SpreadingPolicy, SpreadingTopology, Storage, StorageDeletionPolicy, Tls};
 
-    statefulset_api.delete(name, &delete_params).await?;
-    Ok(())
+    #[test]
+    fn test_custom_command_is_not_interpreted() {
+        let command: &str = "[\"java\", \"-jar\", \"h2o.jar\", \"-name\", \"quoted: 'name'\"]";
+        let specification: H2OSpec = H2OSpec::new(
+            2,
+            Option::None,
+            Resources::new(1, "256Mi".to_string(), Option::None),
+            Option::Some(CustomImage::new("registry/h2o:latest".to_string(), Option::Some(command.to_string()))),
+        );
+
+        let stateful_set: StatefulSet = super::h2o_stateful_set("odd-name", "default", &specification, Option::None).unwrap();
+        let container: &Container = &stateful_set.spec.unwrap().template.spec.unwrap().containers[0];
+        assert_eq!("registry/h2o:latest", container.image.as_ref().unwrap());
+        assert_eq!(&vec!["java", "-jar", "h2o.jar", "-name", "quoted: 'name'"], container.command.as_ref().unwrap());
+        assert!(container.args.is_none());
+    }
+
+    #[test]
+    fn test_exec_command() {
+        assert_eq!(vec!["/opt/start.sh", "-v"], super::exec_command("[\"/opt/start.sh\", \"-v\"]"));
+        assert_eq!(vec!["/opt/start.sh", "-v"], super::exec_command("/opt/start.sh  -v"));
+        assert_eq!(vec!["/opt/start.sh"], super::exec_command("- /opt/start.sh"));
+    }
+
+    #[test]
+    fn test_official_image_arguments() {
+        let mut specification: H2OSpec = H2OSpec::new(2, Option::Some("latest".to_string()),
+                                                      Resources::new(1, "1Gi".to_string(), Option::Some(80)), Option::None);
+        specification.jvm_args = vec!["-XX:+UseG1GC".to_string()];
+        specification.h2o_args = vec!["-log_level".to_string(), "INFO".to_string()];
+        specification.env = vec![EnvVariable::new("H2O_NODE_EXPECTED_COUNT".to_string(), "3".to_string()),
+                                 EnvVariable::new("TZ".to_string(), "UTC".to_string())];
+
+        let stateful_set: StatefulSet = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap();
+        let container: &Container = &stateful_set.spec.unwrap().template.spec.unwrap().containers[0];
+        assert_eq!(&vec!["java", "-XX:+UseContainerSupport", "-XX:MaxRAMPercentage=80", "-XX:+UseG1GC", "-jar",
+                         "/opt/h2oai/h2o-3/h2o.jar", "-log_level", "INFO"], container.command.as_ref().unwrap());
+        assert!(container.args.is_none());
+        let env = container.env.as_ref().unwrap();
+        let expected_count: Vec<_> = env.iter().filter(|var| var.name == "H2O_NODE_EXPECTED_COUNT").collect();
+        assert_eq!(1, expected_count.len());
+        assert_eq!(Some("3".to_string()), expected_count[0].value);
+        assert!(env.iter().any(|var| var.name == "TZ"));
+
+        // The memory percentage is filled in by operator defaults, there is no implicit fallback
+        specification.resources.memory_percentage = None;
+        assert!(super::h2o_stateful_set("h2o", "default", &specification, Option::None).is_err());
+    }
+
+    #[test]
+    fn test_custom_image_arguments() {
+        let mut specification: H2OSpec = H2OSpec::new(2, Option::None, Resources::new(1, "1Gi".to_string(), Option::None),
+                                                      Option::Some(CustomImage::new("registry/h2o:latest".to_string(),
+                                                                                    Option::Some("/opt/start.sh".to_string()))));
+        specification.jvm_args = vec!["-XX:+UseG1GC".to_string(), "-Xss2m".to_string()];
+        specification.h2o_args = vec!["-nthreads".to_string(), "4".to_string()];
+
+        let stateful_set: StatefulSet = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap();
+        let container: &Container = &stateful_set.spec.unwrap().template.spec.unwrap().containers[0];
+        assert_eq!(&vec!["/opt/start.sh"], container.command.as_ref().unwrap());
+        assert_eq!(&vec!["-nthreads", "4"], container.args.as_ref().unwrap());
+        let java_tool_options = container.env.as_ref().unwrap().iter()
+            .find(|var| var.name == "JAVA_TOOL_OPTIONS").unwrap();
+        assert_eq!(Some("-XX:+UseG1GC -Xss2m".to_string()), java_tool_options.value);
+    }
+
+    #[test]
+    fn test_storage() {
+        let mut specification: H2OSpec = H2OSpec::new(2, Option::Some("latest".to_string()),
+                                                      Resources::new(1, "1Gi".to_string(), Option::Some(50)), Option::None);
+        specification.h2o_args = vec!["-log_level".to_string(), "INFO".to_string()];
+        specification.storage = Some(Storage::new("10Gi".to_string(), Some("fast".to_string()), Option::None,
+                                                  StorageDeletionPolicy::Delete));
+
+        let stateful_set: StatefulSet = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap();
+        let stateful_set_spec = stateful_set.spec.unwrap();
+        let claim = &stateful_set_spec.volume_claim_templates.unwrap()[0];
+        assert_eq!("Delete", claim.metadata.labels.as_ref().unwrap()[crate::storage::DELETION_POLICY_LABEL]);
+        assert_eq!(Some("fast".to_string()), claim.spec.as_ref().unwrap().storage_class_name);
+        let container: &Container = &stateful_set_spec.template.spec.unwrap().containers[0];
+        assert_eq!("/h2o-data", container.volume_mounts.as_ref().unwrap()[1].mount_path);
+        assert!(container.command.as_ref().unwrap().ends_with(&["-jar".to_string(), "/opt/h2oai/h2o-3/h2o.jar".to_string(),
+            "-ice_root".to_string(), "/h2o-data/ice".to_string(), "-auto_recovery_dir".to_string(),
+            "/h2o-data/recovery".to_string(), "-log_level".to_string(), "INFO".to_string()]));
+    }
+
+    #[test]
+    fn test_auth() {
+        let mut specification: H2OSpec = H2OSpec::new(2, Option::Some("latest".to_string()),
+                                                      Resources::new(1, "1Gi".to_string(), Option::Some(50)), Option::None);
+        specification.h2o_args = vec!["-log_level".to_string(), "INFO".to_string()];
+        specification.auth = Some(Auth::new(AuthMethod::Kerberos, "h2o-kerberos".to_string()));
+
+        let pod_spec: PodSpec = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap();
+        let container: &Container = &pod_spec.containers[0];
+        let command: &Vec<String> = container.command.as_ref().unwrap();
+        assert!(command.contains(&"-Djava.security.krb5.conf=/etc/h2o/auth/krb5.conf".to_string()));
+        assert!(command.ends_with(&["-kerberos_login".to_string(), "-login_conf".to_string(),
+            "/etc/h2o/auth/login.conf".to_string(), "-log_level".to_string(), "INFO".to_string()]));
+        assert!(container.volume_mounts.as_ref().unwrap().iter()
+            .any(|mount| mount.mount_path == "/etc/h2o/auth" && mount.read_only == Some(true)));
+        let secret = pod_spec.volumes.unwrap().into_iter()
+            .find_map(|volume| volume.secret).unwrap();
+        assert_eq!(Some("h2o-kerberos".to_string()), secret.secret_name);
+
+        specification.auth = Some(Auth::new(AuthMethod::Hash, "h2o-realm".to_string()));
+        let container: Container = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap().containers.remove(0);
+        let command: Vec<String> = container.command.unwrap();
+        assert!(command.contains(&"-hash_login".to_string()));
+        assert!(!command.iter().any(|argument| argument.starts_with("-Djava.security.krb5.conf")));
+    }
+
+    #[test]
+    fn test_tls() {
+        let mut specification: H2OSpec = H2OSpec::new(2, Option::None, Resources::new(1, "1Gi".to_string(), Option::None),
+                                                      Option::Some(CustomImage::new("registry/h2o:latest".to_string(), Option::None)));
+        specification.tls = Some(Tls::new("h2o-keystore".to_string()));
+
+        let pod_spec: PodSpec = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap();
+        let container: &Container = &pod_spec.containers[0];
+        assert_eq!(&vec!["-jks", "/etc/h2o/tls/keystore.jks", "-jks_pass", "$(H2O_JKS_PASSWORD)", "-internal_security_conf",
+                         "/etc/h2o/tls/security.properties"], container.args.as_ref().unwrap());
+        let password = container.env.as_ref().unwrap().iter()
+            .find(|var| var.name == "H2O_JKS_PASSWORD").unwrap();
+        let secret_key_ref = password.value_from.as_ref().unwrap().secret_key_ref.as_ref().unwrap();
+        assert_eq!(Some("h2o-keystore".to_string()), secret_key_ref.name);
+        assert_eq!("password", secret_key_ref.key);
+        assert!(container.volume_mounts.as_ref().unwrap().iter().any(|mount| mount.mount_path == "/etc/h2o/tls"));
+
+        specification.tls.as_mut().unwrap().internal = false;
+        let container: Container = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap().containers.remove(0);
+        assert!(!container.args.unwrap().contains(&"-internal_security_conf".to_string()));
+    }
+
+    #[test]
+    fn test_private_registry() {
+        let mut specification: H2OSpec = H2OSpec::new(2, Option::Some("3.32.0.1".to_string()),
+                                                      Resources::new(1, "1Gi".to_string(), Option::Some(50)), Option::None);
+        let pod_spec: PodSpec = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap();
+        assert_eq!(Some("h2oai/h2o-open-source-k8s:3.32.0.1".to_string()), pod_spec.containers[0].image);
+        assert!(pod_spec.containers[0].image_pull_policy.is_none());
+        assert!(pod_spec.image_pull_secrets.is_none());
+
+        specification.image_repository = Some("registry.example.com/h2oai/h2o-open-source-k8s".to_string());
+        specification.image_pull_policy = Some(ImagePullPolicy::IfNotPresent);
+        specification.image_pull_secrets = vec!["registry-credentials".to_string()];
+        let pod_spec: PodSpec = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap();
+        assert_eq!(Some("registry.example.com/h2oai/h2o-open-source-k8s:3.32.0.1".to_string()), pod_spec.containers[0].image);
+        assert_eq!(Some("IfNotPresent".to_string()), pod_spec.containers[0].image_pull_policy);
+        assert_eq!(Some("registry-credentials".to_string()), pod_spec.image_pull_secrets.unwrap()[0].name);
+    }
+
+    #[test]
+    fn test_scheduling() {
+        let mut specification: H2OSpec = H2OSpec::new(3, Option::Some("latest".to_string()),
+                                                      Resources::new(1, "1Gi".to_string(), Option::Some(50)), Option::None);
+        let pod_spec: PodSpec = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap();
+        assert!(pod_spec.node_selector.is_none());
+        assert!(pod_spec.tolerations.is_none());
+
+        specification.node_selector.insert("pool".to_string(), "highmem".to_string());
+        specification.tolerations.push(serde_json::from_value(json!({
+            "key": "dedicated", "operator": "Equal", "value": "h2o", "effect": "NoSchedule"
+        })).unwrap());
+        specification.priority_class_name = Some("high-priority".to_string());
+        let pod_spec: PodSpec = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap();
+        assert_eq!("highmem", pod_spec.node_selector.unwrap()["pool"]);
+        assert_eq!(Some("dedicated".to_string()), pod_spec.tolerations.unwrap()[0].key);
+        assert_eq!(Some("high-priority".to_string()), pod_spec.priority_class_name);
+    }
+
+    #[test]
+    fn test_spreading() {
+        let mut specification: H2OSpec = H2OSpec::new(3, Option::Some("latest".to_string()),
+                                                      Resources::new(1, "1Gi".to_string(), Option::Some(50)), Option::None);
+        specification.affinity = serde_json::from_value(json!({
+            "nodeAffinity": { "requiredDuringSchedulingIgnoredDuringExecution": { "nodeSelectorTerms": [] } }
+        })).unwrap();
+        specification.spreading = Some(Spreading::new(SpreadingPolicy::Required, SpreadingTopology::Hostname));
+        let pod_spec: PodSpec = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap();
+        let affinity: Affinity = pod_spec.affinity.unwrap();
+        assert!(affinity.node_affinity.is_some());
+        let anti_affinity_terms = affinity.pod_anti_affinity.unwrap().required_during_scheduling_ignored_during_execution.unwrap();
+        assert_eq!("kubernetes.io/hostname", anti_affinity_terms[0].topology_key);
+        let constraint = &pod_spec.topology_spread_constraints.unwrap()[0];
+        assert_eq!("DoNotSchedule", constraint.when_unsatisfiable);
+
+        specification.affinity = None;
+        specification.spreading = Some(Spreading::new(SpreadingPolicy::Preferred, SpreadingTopology::Zone));
+        let pod_spec: PodSpec = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap();
+        assert!(pod_spec.affinity.is_none());
+        let constraint = &pod_spec.topology_spread_constraints.unwrap()[0];
+        assert_eq!("topology.kubernetes.io/zone", constraint.topology_key);
+        assert_eq!("ScheduleAnyway", constraint.when_unsatisfiable);
+
+        specification.spreading = Some(Spreading::new(SpreadingPolicy::None, SpreadingTopology::Hostname));
+        let pod_spec: PodSpec = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap();
+        assert!(pod_spec.affinity.is_none());
+        assert!(pod_spec.topology_spread_constraints.is_none());
+    }
+
+    #[test]
+    fn test_security_context() {
+        let mut specification: H2OSpec = H2OSpec::new(2, Option::Some("latest".to_string()),
+                                                      Resources::new(1, "1Gi".to_string(), Option::Some(50)), Option::None);
+        let pod_spec: PodSpec = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap();
+        let pod_security_context = pod_spec.security_context.unwrap();
+        assert_eq!(Some(true), pod_security_context.run_as_non_root);
+        assert_eq!(Some(1000), pod_security_context.run_as_user);
+        assert_eq!("RuntimeDefault", pod_security_context.seccomp_profile.unwrap().type_);
+        let container_security_context = pod_spec.containers[0].security_context.clone().unwrap();
+        assert_eq!(Some(false), container_security_context.allow_privilege_escalation);
+        assert_eq!(Some(vec!["ALL".to_string()]), container_security_context.capabilities.unwrap().drop);
+        assert_eq!(Some(true), container_security_context.read_only_root_filesystem);
+        assert_eq!("/tmp", pod_spec.containers[0].volume_mounts.as_ref().unwrap()[0].mount_path);
+        assert!(pod_spec.volumes.unwrap()[0].empty_dir.is_some());
+
+        specification.security_context = Some(SecurityContextSpec {
+            run_as_user: Some(2000),
+            read_only_root_filesystem: Some(false),
+            ..SecurityContextSpec::default()
+        });
+        let pod_spec: PodSpec = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap();
+        let pod_security_context = pod_spec.security_context.unwrap();
+        assert_eq!(Some(2000), pod_security_context.run_as_user);
+        assert_eq!(Some(1000), pod_security_context.run_as_group);
+        assert_eq!(Some(false), pod_spec.containers[0].security_context.as_ref().unwrap().read_only_root_filesystem);
+    }
+
+    #[test]
+    fn test_resources() {
+        let mut specification: H2OSpec = H2OSpec::new(2, Option::Some("latest".to_string()),
+                                                      Resources::new(2, "4Gi".to_string(), Option::Some(50)), Option::None);
+        let requirements = super::resource_requirements(&specification.resources);
+        assert_eq!(requirements.limits, requirements.requests);
+        assert!(!requirements.limits.unwrap().contains_key("ephemeral-storage"));
+
+        specification.resources.requests = Some(ResourceRequests::new(Some("500m".to_string()), Option::None));
+        specification.resources.ephemeral_storage = Some("10Gi".to_string());
+        let container: Container = super::h2o_stateful_set("h2o", "default", &specification, Option::None).unwrap()
+            .spec.unwrap().template.spec.unwrap().containers.remove(0);
+        let requirements = container.resources.unwrap();
+        let limits = requirements.limits.unwrap();
+        let requests = requirements.requests.unwrap();
+        assert_eq!("2", limits["cpu"].0);
+        assert_eq!("500m", requests["cpu"].0);
+        assert_eq!("4Gi", requests["memory"].0);
+        assert_eq!("10Gi", limits["ephemeral-storage"].0);
+        assert_eq!("10Gi", requests["ephemeral-storage"].0);
+    }
+
+    #[test]
+    fn test_memory_below_minimum() {
+        let specification: H2OSpec = H2OSpec::new(1, Option::Some("latest".to_string()),
+                                                  Resources::new(1, "0.5Gi".to_string(), Option::Some(50)), Option::None);
+        assert!(super::h2o_stateful_set("h2o", "default", &specification, Option::None).is_ok());
+        let specification: H2OSpec = H2OSpec::new(1, Option::Some("latest".to_string()),
+                                                  Resources::new(1, "1e3".to_string(), Option::Some(50)), Option::None);
+        assert!(super::h2o_stateful_set("h2o", "default", &specification, Option::None).is_err());
+    }
+
+    #[test]
+    fn test_no_image_specified() {
+        let specification: H2OSpec = H2OSpec::new(
+            1,
+            Option::None,
+            Resources::new(1, "256Mi".to_string(), Option::None),
+            Option::None,
+        );
+        assert!(super::h2o_stateful_set("any-name", "default", &specification, Option::None).is_err());
+    }
 }
@@ -0,0 +1,257 @@
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::chrono::Utc;
+use kube::{Api, Client};
+use kube::api::PatchParams;
+use serde_json::json;
+
+use crate::crd::{H2O, H2OCondition, H2OPhase, H2OSpec, H2OStatus};
+use crate::Error;
+
+/// Condition type signalling whether all the H2O nodes are up and running.
+pub const CONDITION_NODES_RUNNING: &str = "NodesRunning";
+/// Condition type signalling whether the H2O cluster is clustered and ready to serve requests.
+pub const CONDITION_READY: &str = "Ready";
+
+/// Computes the status of an H2O cluster from the `H2O` resource and the pods with H2O nodes belonging to it.
+///
+/// # Arguments
+/// `h2o` - The `H2O` resource to compute the status for. Its current status, if any, is used to preserve
+/// transition times of conditions which did not change.
+/// `specification` - Specification the H2O cluster has been deployed with, i.e. `h2o.spec` completed by operator defaults.
+/// `pods` - Pods with H2O nodes belonging to the `h2o` resource.
+pub fn h2o_status(h2o: &H2O, specification: &H2OSpec, pods: &[Pod]) -> H2OStatus {
+    let expected_nodes: u32 = specification.nodes;
+    let running_nodes: u32 = pods.iter().filter(|pod| crate::pod::is_running(pod)).count() as u32;
+    let failure: Option<String> = pods.iter().find_map(crate::pod::failure_reason);
+    let name: String = h2o.metadata.name.clone().unwrap_or_default();
+    let leader: Option<&Pod> = crate::pod::leader_of(&name, pods).ok();
+
+    let (phase, message): (H2OPhase, String) = if crate::crd::has_deletion_stamp(h2o) {
+        (H2OPhase::Terminating, "H2O cluster is being deleted.".to_string())
+    } else if let Some(failure) = failure {
+        (H2OPhase::Failed, failure)
+    } else if running_nodes >= expected_nodes && leader.is_some() {
+        (H2OPhase::Ready, "H2O cluster is clustered and ready.".to_string())
+    } else if running_nodes >= expected_nodes {
+        (H2OPhase::Clustering, "All H2O nodes are running, waiting for H2O nodes to form a cluster.".to_string())
+    } else {
+        (H2OPhase::Pending, format!("{} out of {} H2O nodes running.", running_nodes, expected_nodes))
+    };
+
+    let namespace: String = h2o.metadata.namespace.clone().unwrap_or_else(|| "default".to_string());
+    let previous_conditions: &[H2OCondition] = h2o.status.as_ref()
+        .map_or(&[], |status| status.conditions.as_slice());
+
+    let conditions: Vec<H2OCondition> = vec![
+        condition(previous_conditions, CONDITION_NODES_RUNNING, running_nodes >= expected_nodes,
+                  if running_nodes >= expected_nodes { "AllNodesRunning" } else { "NodesNotRunning" },
+                  &format!("{} out of {} H2O nodes running.", running_nodes, expected_nodes)),
+        condition(previous_conditions, CONDITION_READY, phase == H2OPhase::Ready,
+                  &format!("{:?}", phase), &message),
+    ];
+
+    H2OStatus {
+        phase: Some(phase),
+        expected_nodes: Some(expected_nodes),
+        running_nodes: Some(running_nodes),
+        leader_pod: leader.and_then(|pod| pod.metadata.name.clone()),
+        leader_ip: leader.and_then(|pod| pod.status.as_ref()?.pod_ip.clone()),
+        url: Some(crate::leader_service::leader_service_url(&namespace, &name, specification.tls.is_some())),
+        jvm_heap: crate::quantity::jvm_heap(&specification.resources).ok().map(|heap| heap.to_string()),
+        observed_generation: h2o.metadata.generation,
+        conditions,
+    }
+}
+
+/// Computes the status of an `H2O` resource with an invalid specification, which is never deployed. The `Ready`
+/// condition carries the validation `message`.
+///
+/// # Arguments
+/// `h2o` - The `H2O` resource to compute the status for. Its current status, if any, is used to preserve
+/// transition times of conditions which did not change.
+/// `message` - Human-readable description of the specification violations, see `validation::validate`.
+pub fn invalid_status(h2o: &H2O, message: &str) -> H2OStatus {
+    let previous_conditions: &[H2OCondition] = h2o.status.as_ref()
+        .map_or(&[], |status| status.conditions.as_slice());
+    H2OStatus {
+        phase: Some(H2OPhase::Failed),
+        observed_generation: h2o.metadata.generation,
+        conditions: vec![condition(previous_conditions, CONDITION_READY, false, "InvalidSpecification", message)],
+        ..H2OStatus::default()
+    }
+}
+
+/// Creates a condition of given `type_`. If the status of the condition did not change compared to the
+/// previous conditions, the last transition time is preserved.
+fn condition(previous_conditions: &[H2OCondition], type_: &str, status: bool, reason: &str, message: &str) -> H2OCondition {
+    let status: String = if status { "True".to_string() } else { "False".to_string() };
+    let last_transition_time: Option<String> = previous_conditions.iter()
+        .find(|previous| previous.type_ == type_ && previous.status == status)
+        .and_then(|previous| previous.last_transition_time.clone())
+        .or_else(|| Some(Utc::now().to_rfc3339()));
+
+    H2OCondition {
+        type_: type_.to_string(),
+        status,
+        reason: Some(reason.to_string()),
+        message: Some(message.to_string()),
+        last_transition_time,
+    }
+}
+
+/// Computes the current status of the given `H2O` resource from the pods belonging to it and writes
+/// the status into the `status` subresource of the `H2O` resource.
+///
+/// # Arguments
+/// `client` - Client with permissions to list pods and patch the `H2O` resource status
+/// `h2o` - The `H2O` resource to update the status of
+/// `specification` - Specification the H2O cluster has been deployed with, i.e. `h2o.spec` completed by operator defaults.
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use deployment::crd::H2O;
+/// use kube::{Api, Client};
+/// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
+/// let api: Api<H2O> = Api::namespaced(client.clone(), &namespace);
+/// let h2o: H2O = api.get("any-name").await.unwrap();
+/// let updated: H2O = deployment::status::update(client, &h2o, &h2o.spec).await.unwrap();
+/// }
+/// ```
+pub async fn update(client: Client, h2o: &H2O, specification: &H2OSpec) -> Result<H2O, Error> {
+    let name: &str = h2o.metadata.name.as_ref()
+        .ok_or_else(|| Error::UserError("Unable to update H2O status. No H2O name provided.".to_string()))?;
+    let namespace: &str = h2o.metadata.namespace.as_ref()
+        .ok_or_else(|| Error::UserError("Unable to update H2O status. No namespace provided.".to_string()))?;
+
+    let pods: Vec<Pod> = crate::pod::list(client.clone(), namespace, name).await?;
+    let status: H2OStatus = h2o_status(h2o, specification, &pods);
+    patch_status(client, namespace, name, &status).await
+}
+
+/// Writes the status of an `H2O` resource with an invalid specification into its `status` subresource,
+/// see `invalid_status`.
+///
+/// # Arguments
+/// `client` - Client with permissions to patch the `H2O` resource status
+/// `h2o` - The `H2O` resource to update the status of
+/// `message` - Human-readable description of the specification violations, see `validation::validate`.
+pub async fn update_invalid(client: Client, h2o: &H2O, message: &str) -> Result<H2O, Error> {
+    let name: &str = h2o.metadata.name.as_ref()
+        .ok_or_else(|| Error::UserError("Unable to update H2O status. No H2O name provided.".to_string()))?;
+    let namespace: &str = h2o.metadata.namespace.as_ref()
+        .ok_or_else(|| Error::UserError("Unable to update H2O status. No namespace provided.".to_string()))?;
+    patch_status(client, namespace, name, &invalid_status(h2o, message)).await
+}
+
+/// Patches the `status` subresource of the `H2O` resource of given `name` with `status`.
+async fn patch_status(client: Client, namespace: &str, name: &str, status: &H2OStatus) -> Result<H2O, Error> {
+    let api: Api<H2O> = Api::namespaced(client, namespace);
+    let patch = json!({
+        "status": status
+    });
+    let updated_h2o: H2O = api.patch_status(name, &PatchParams::default(), serde_json::to_vec(&patch)?).await?;
+    Ok(updated_h2o)
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_openapi::api::core::v1::Pod;
+    use serde_json::json;
+
+    use crate::crd::{H2O, H2OPhase, H2OSpec, H2OStatus, Resources, Tls};
+    use crate::defaults::Defaults;
+
+    fn h2o(nodes: u32) -> H2O {
+        let specification: H2OSpec = H2OSpec::new(nodes, Option::Some("latest".to_string()),
+                                                  Resources::new(1, "256Mi".to_string(), Option::None), Option::None);
+        let mut h2o: H2O = H2O::new("test-status", specification);
+        h2o.metadata.namespace = Some("default".to_string());
+        h2o.metadata.generation = Some(3);
+        h2o
+    }
+
+    /// Specification of the `h2o` completed by the default operator defaults, as deployed by the operator.
+    fn completed(h2o: &H2O) -> H2OSpec {
+        let mut specification: H2OSpec = h2o.spec.clone();
+        Defaults::default().apply(&mut specification);
+        specification
+    }
+
+    fn pod(name: &str, running: bool, ready: bool) -> Pod {
+        serde_json::from_value(json!({
+            "metadata": { "name": name },
+            "status": {
+                "phase": if running { "Running" } else { "Pending" },
+                "podIP": "10.0.0.1",
+                "conditions": [{ "type": "Ready", "status": if ready { "True" } else { "False" } }],
+                "containerStatuses": [{
+                    "name": "h2o",
+                    "image": "h2oai/h2o-open-source-k8s:latest",
+                    "imageID": "",
+                    "ready": ready,
+                    "restartCount": 0,
+                    "state": if running { json!({ "running": {} }) } else { json!({ "waiting": { "reason": "ContainerCreating" } }) }
+                }]
+            }
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_status_phases() {
+        let pending: H2OStatus = super::h2o_status(&h2o(2), &completed(&h2o(2)), &[pod("h2o-0", true, false), pod("h2o-1", false, false)]);
+        assert_eq!(Some(H2OPhase::Pending), pending.phase);
+        assert_eq!(Some(1), pending.running_nodes);
+        assert_eq!(Some(2), pending.expected_nodes);
+        assert_eq!(Some(3), pending.observed_generation);
+        assert_eq!(Some("128Mi".to_string()), pending.jvm_heap);
+
+        let clustering: H2OStatus = super::h2o_status(&h2o(2), &completed(&h2o(2)), &[pod("h2o-0", true, false), pod("h2o-1", true, false)]);
+        assert_eq!(Some(H2OPhase::Clustering), clustering.phase);
+        assert!(clustering.leader_pod.is_none());
+
+        let ready: H2OStatus = super::h2o_status(&h2o(2), &completed(&h2o(2)), &[pod("h2o-0", true, false), pod("h2o-1", true, true)]);
+        assert_eq!(Some(H2OPhase::Ready), ready.phase);
+        assert_eq!(Some("h2o-1".to_string()), ready.leader_pod);
+        assert_eq!(Some("10.0.0.1".to_string()), ready.leader_ip);
+        assert_eq!(Some("http://test-status-leader.default.svc.cluster.local:80".to_string()), ready.url);
+        assert!(ready.conditions.iter().all(|condition| condition.status == "True"));
+
+        let mut tls_h2o: H2O = h2o(2);
+        tls_h2o.spec.tls = Some(Tls::new("h2o-keystore".to_string()));
+        let tls_ready: H2OStatus = super::h2o_status(&tls_h2o, &completed(&tls_h2o), &[pod("h2o-0", true, true), pod("h2o-1", true, false)]);
+        assert_eq!(Some("https://test-status-leader.default.svc.cluster.local:443".to_string()), tls_ready.url);
+    }
+
+    #[test]
+    fn test_status_of_completed_specification() {
+        let h2o: H2O = h2o(1);
+        let mut specification: H2OSpec = h2o.spec.clone();
+        specification.resources.memory_percentage = Some(80);
+        let status: H2OStatus = super::h2o_status(&h2o, &specification, &[pod("h2o-0", true, true)]);
+        assert_eq!(Some("204.8Mi".to_string()), status.jvm_heap);
+        assert!(super::h2o_status(&h2o, &h2o.spec, &[pod("h2o-0", true, true)]).jvm_heap.is_none());
+    }
+
+    #[test]
+    fn test_status_failed() {
+        let mut failed_pod: Pod = pod("h2o-0", false, false);
+        let container_status = &mut failed_pod.status.as_mut().unwrap().container_statuses.as_mut().unwrap()[0];
+        container_status.state = serde_json::from_value(json!({ "waiting": { "reason": "ImagePullBackOff" } })).unwrap();
+
+        let failed: H2OStatus = super::h2o_status(&h2o(1), &completed(&h2o(1)), &[failed_pod]);
+        assert_eq!(Some(H2OPhase::Failed), failed.phase);
+    }
+
+    #[test]
+    fn test_invalid_status() {
+        let invalid: H2OStatus = super::invalid_status(&h2o(0), "Invalid H2O specification");
+        assert_eq!(Some(H2OPhase::Failed), invalid.phase);
+        assert_eq!(None, invalid.running_nodes);
+        assert_eq!(super::CONDITION_READY, invalid.conditions[0].type_);
+        assert_eq!("False", invalid.conditions[0].status);
+        assert_eq!(Some("Invalid H2O specification".to_string()), invalid.conditions[0].message);
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, PersistentVolumeClaimSpec, ResourceRequirements, VolumeMount};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::{Api, Client};
+use kube::api::{DeleteParams, ListParams};
+use log::debug;
+
+use crate::crd::{Storage, StorageDeletionPolicy};
+use crate::Error;
+
+/// Name of the volume claim template, also used as a prefix of the persistent volume claims
+/// created by the StatefulSet, e.g. `h2o-data-<name>-0`.
+pub const VOLUME_NAME: &str = "h2o-data";
+/// Path the H2O data volume is mounted to, unless specified otherwise.
+pub const DEFAULT_MOUNT_PATH: &str = "/h2o-data";
+/// Label of persistent volume claims carrying the deletion policy, so the claims can be cleaned up without
+/// knowing the original `H2OSpec`.
+pub const DELETION_POLICY_LABEL: &str = "h2o.ai/storage-deletion-policy";
+
+/// Path the H2O data volume is mounted to in H2O containers.
+///
+/// # Arguments
+/// `storage` - Storage specification of the H2O cluster
+pub fn mount_path(storage: &Storage) -> &str {
+    storage.mount_path.as_deref().unwrap_or(DEFAULT_MOUNT_PATH)
+}
+
+/// Creates a volume claim template of the H2O `StatefulSet`. Each H2O pod is given its own persistent volume claim
+/// created from the template.
+///
+/// # Arguments
+/// `name` - Name of the H2O deployment. Used to label the persistent volume claims.
+/// `storage` - Storage specification of the H2O cluster
+pub fn volume_claim_template(name: &str, storage: &Storage) -> PersistentVolumeClaim {
+    let mut labels: BTreeMap<String, String> = crate::h2o_labels(name);
+    labels.insert(DELETION_POLICY_LABEL.to_string(), format!("{:?}", storage.deletion_policy));
+    let mut requests: BTreeMap<String, Quantity> = BTreeMap::new();
+    requests.insert("storage".to_string(), Quantity(storage.size.clone()));
+
+    PersistentVolumeClaim {
+        metadata: ObjectMeta {
+            name: Some(VOLUME_NAME.to_string()),
+            labels: Some(labels),
+            ..ObjectMeta::default()
+        },
+        spec: Some(PersistentVolumeClaimSpec {
+            access_modes: Some(vec!["ReadWriteOnce".to_string()]),
+            storage_class_name: storage.storage_class.clone(),
+            resources: Some(ResourceRequirements {
+                requests: Some(requests),
+                limits: None,
+            }),
+            ..PersistentVolumeClaimSpec::default()
+        }),
+        status: None,
+    }
+}
+
+/// Mount of the H2O data volume in H2O containers.
+///
+/// # Arguments
+/// `storage` - Storage specification of the H2O cluster
+pub fn volume_mount(storage: &Storage) -> VolumeMount {
+    VolumeMount {
+        name: VOLUME_NAME.to_string(),
+        mount_path: mount_path(storage).to_string(),
+        ..VolumeMount::default()
+    }
+}
+
+/// H2O arguments pointing H2O's ice directory and auto-recovery directory to the H2O data volume.
+///
+/// # Arguments
+/// `storage` - Storage specification of the H2O cluster
+pub fn h2o_args(storage: &Storage) -> Vec<String> {
+    let mount_path: &str = mount_path(storage).trim_end_matches('/');
+    vec![
+        "-ice_root".to_string(),
+        format!("{}/ice", mount_path),
+        "-auto_recovery_dir".to_string(),
+        format!("{}/recovery", mount_path),
+    ]
+}
+
+/// Deletes persistent volume claims of an H2O deployment of given `name`, if their deletion policy is `Delete`.
+/// Claims with the `Retain` deletion policy are kept. The claims are only removed by Kubernetes once no pod uses them.
+///
+/// # Arguments
+///
+/// `client` - Client to delete the persistent volume claims with
+/// `namespace` - Namespace of the H2O deployment
+/// `name` - Name of the H2O deployment
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use kube::Client;
+/// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
+/// deployment::storage::delete_claims(client, &namespace, "any-name").await.unwrap();
+/// }
+/// ```
+pub async fn delete_claims(client: Client, namespace: &str, name: &str) -> Result<(), Error> {
+    let api: Api<PersistentVolumeClaim> = Api::namespaced(client, namespace);
+    let list_params: ListParams = ListParams::default().labels(&format!("{},{}={:?}", crate::pod::label_selector(name),
+                                                                      DELETION_POLICY_LABEL, StorageDeletionPolicy::Delete));
+    api.delete_collection(&DeleteParams::default(), &list_params).await?;
+    debug!("Deleted persistent volume claims of H2O '{}'.", name);
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use k8s_openapi::api::core::v1::{EnvVar, EnvVarSource, SecretKeySelector, SecretVolumeSource, Volume, VolumeMount};
+
+use crate::crd::Tls;
+
+/// Name of the volume with the keystore material of H2O.
+pub const VOLUME_NAME: &str = "h2o-tls";
+/// Path the secret with the keystore material is mounted to in H2O containers.
+pub const MOUNT_PATH: &str = "/etc/h2o/tls";
+/// Environment variable holding the keystore password. Referenced by H2O arguments, so the password itself
+/// never appears in the `StatefulSet`.
+pub const PASSWORD_ENV: &str = "H2O_JKS_PASSWORD";
+/// Key of the Java keystore in the secret, unless specified otherwise.
+pub const DEFAULT_KEYSTORE_KEY: &str = "keystore.jks";
+/// Key of the keystore password in the secret, unless specified otherwise.
+pub const DEFAULT_PASSWORD_KEY: &str = "password";
+/// Key of H2O's internal security configuration in the secret, unless specified otherwise.
+pub const DEFAULT_INTERNAL_SECURITY_CONF_KEY: &str = "security.properties";
+
+/// Volume with the secret holding the keystore material. The files are only readable by the owner and the group,
+/// which is the filesystem group of H2O pods.
+///
+/// # Arguments
+/// `tls` - TLS settings of the H2O cluster
+pub fn volume(tls: &Tls) -> Volume {
+    Volume {
+        name: VOLUME_NAME.to_string(),
+        secret: Some(SecretVolumeSource {
+            secret_name: Some(tls.secret_name.clone()),
+            default_mode: Some(0o440),
+            ..SecretVolumeSource::default()
+        }),
+        ..Volume::default()
+    }
+}
+
+/// Read-only mount of the volume with the keystore material in H2O containers.
+pub fn volume_mount() -> VolumeMount {
+    VolumeMount {
+        name: VOLUME_NAME.to_string(),
+        mount_path: MOUNT_PATH.to_string(),
+        read_only: Some(true),
+        ..VolumeMount::default()
+    }
+}
+
+/// Environment variable with the keystore password, taken from the secret.
+///
+/// # Arguments
+/// `tls` - TLS settings of the H2O cluster
+pub fn password_env_var(tls: &Tls) -> EnvVar {
+    EnvVar {
+        name: PASSWORD_ENV.to_string(),
+        value: None,
+        value_from: Some(EnvVarSource {
+            secret_key_ref: Some(SecretKeySelector {
+                name: Some(tls.secret_name.clone()),
+                key: tls.password_key.clone().unwrap_or_else(|| DEFAULT_PASSWORD_KEY.to_string()),
+                optional: None,
+            }),
+            ..EnvVarSource::default()
+        }),
+    }
+}
+
+/// H2O arguments serving H2O's REST API over TLS and, unless disabled, encrypting communication between H2O nodes.
+/// The keystore password is referenced as `$(H2O_JKS_PASSWORD)`, which is expanded by Kubernetes.
+///
+/// # Arguments
+/// `tls` - TLS settings of the H2O cluster
+pub fn h2o_args(tls: &Tls) -> Vec<String> {
+    let keystore_key: &str = tls.keystore_key.as_deref().unwrap_or(DEFAULT_KEYSTORE_KEY);
+    let mut args: Vec<String> = vec![
+        "-jks".to_string(),
+        format!("{}/{}", MOUNT_PATH, keystore_key),
+        "-jks_pass".to_string(),
+        format!("$({})", PASSWORD_ENV),
+    ];
+    if tls.internal {
+        let conf_key: &str = tls.internal_security_conf_key.as_deref().unwrap_or(DEFAULT_INTERNAL_SECURITY_CONF_KEY);
+        args.push("-internal_security_conf".to_string());
+        args.push(format!("{}/{}", MOUNT_PATH, conf_key));
+    }
+    args
+}
@@ -0,0 +1,184 @@
+use std::path::Path;
+
+use crate::crd::H2OSpec;
+use crate::Error;
+use crate::quantity::MemoryQuantity;
+
+/// Violations of an H2O specification that would prevent the H2O cluster from being created or from starting,
+/// as human-readable messages referring to the `v1beta` fields. Empty if the specification is valid.
+///
+/// Shared by the CLI, the validating admission webhook and the creation of H2O statefulsets, so invalid specifications
+/// are rejected with the same messages, regardless of how they are submitted.
+///
+/// # Arguments
+/// `specification` - Specification of the H2O cluster
+///
+/// # Examples
+///
+/// ```
+/// use deployment::crd::{H2OSpec, Resources};
+/// let specification: H2OSpec = H2OSpec::new(0, Option::None, Resources::new(1, "1Gi".to_string(), Option::None), Option::None);
+/// assert_eq!(2, deployment::validation::violations(&specification).len());
+/// ```
+pub fn violations(specification: &H2OSpec) -> Vec<String> {
+    let mut violations: Vec<String> = Vec::new();
+    if specification.nodes == 0 {
+        violations.push("Number of H2O nodes ('nodes') must be at least 1.".to_string());
+    }
+
+    match (specification.version.as_deref(), specification.custom_image.as_ref()) {
+        (None, None) => violations.push("Either H2O version ('version') or a custom image ('customImage') must be defined.".to_string()),
+        (Some(_), Some(_)) => violations.push("H2O version ('version') and custom image ('customImage') are mutually exclusive. \
+        Define the version only to use the official H2O image, or the custom image only.".to_string()),
+        (Some(version), None) if version.trim().is_empty() => violations.push("H2O version ('version') must not be empty.".to_string()),
+        (None, Some(custom_image)) if custom_image.image.trim().is_empty() => {
+            violations.push("Custom image ('customImage.image') must not be empty.".to_string())
+        }
+        _ => {}
+    }
+
+    let resources = &specification.resources;
+    if resources.cpu == 0 {
+        violations.push("Number of CPUs ('resources.cpu') must be at least 1.".to_string());
+    }
+    let memory: Option<MemoryQuantity> = match crate::quantity::memory_limit(resources) {
+        Ok(memory) => Some(memory),
+        Err(error) => {
+            violations.push(format!("Invalid memory ('resources.memory'): {}", message(error)));
+            None
+        }
+    };
+    if let Some(percentage) = resources.memory_percentage {
+        if percentage == 0 || percentage > 100 {
+            violations.push(format!("Memory percentage ('resources.memoryPercentage') must be within the <1,100> range, got {}.", percentage));
+        }
+    }
+    if let Some(memory_request) = resources.requests.as_ref().and_then(|requests| requests.memory.as_ref()) {
+        match (memory_request.parse::<MemoryQuantity>(), memory) {
+            (Err(error), _) => violations.push(format!("Invalid memory request ('resources.requests.memory'): {}", message(error))),
+            (Ok(request), Some(limit)) if request > limit => {
+                violations.push(format!("Memory request ('resources.requests.memory') of '{}' exceeds the memory limit of '{}'.",
+                                        memory_request, resources.memory))
+            }
+            _ => {}
+        }
+    }
+    if let Some(ephemeral_storage) = resources.ephemeral_storage.as_ref() {
+        if let Err(error) = ephemeral_storage.parse::<MemoryQuantity>() {
+            violations.push(format!("Invalid ephemeral storage ('resources.ephemeralStorage'): {}", message(error)));
+        }
+    }
+
+    if let Some(storage) = specification.storage.as_ref() {
+        match storage.size.parse::<MemoryQuantity>() {
+            Ok(size) if size.bytes() == 0 => violations.push("Storage size ('storage.size') must be greater than zero.".to_string()),
+            Ok(_) => {}
+            Err(error) => violations.push(format!("Invalid storage size ('storage.size'): {}", message(error))),
+        }
+        if let Some(mount_path) = storage.mount_path.as_ref() {
+            if !Path::new(mount_path).is_absolute() {
+                violations.push(format!("Storage mount path ('storage.mountPath') must be absolute, got '{}'.", mount_path));
+            }
+        }
+    }
+
+    if specification.env.iter().any(|variable| variable.name.trim().is_empty()) {
+        violations.push("Names of environment variables ('env[].name') must not be empty.".to_string());
+    }
+    if matches!(specification.auth.as_ref(), Some(auth) if auth.secret_name.trim().is_empty()) {
+        violations.push("Name of the secret with the login configuration ('auth.secretName') must not be empty.".to_string());
+    }
+    if matches!(specification.tls.as_ref(), Some(tls) if tls.secret_name.trim().is_empty()) {
+        violations.push("Name of the secret with the keystore material ('tls.secretName') must not be empty.".to_string());
+    }
+    violations
+}
+
+/// Validates an H2O specification, see `violations`.
+///
+/// # Arguments
+/// `specification` - Specification of the H2O cluster
+///
+/// # Errors
+///
+/// Returns `Error::UserError` listing all the violations found, one per line.
+pub fn validate(specification: &H2OSpec) -> Result<(), Error> {
+    let violations: Vec<String> = violations(specification);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::UserError(format!("Invalid H2O specification:\n{}", violations.join("\n"))))
+    }
+}
+
+/// Message of a user error without the generic prefix of `Error`'s display.
+fn message(error: Error) -> String {
+    match error {
+        Error::UserError(message) => message,
+        error => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crd::{CustomImage, H2OSpec, ResourceRequests, Resources, Storage, StorageDeletionPolicy};
+
+    fn specification() -> H2OSpec {
+        H2OSpec::new(3, Some("latest".to_string()), Resources::new(2, "4Gi".to_string(), Some(80)), None)
+    }
+
+    #[test]
+    fn test_valid() {
+        assert!(super::violations(&specification()).is_empty());
+        assert!(super::validate(&specification()).is_ok());
+        let custom: H2OSpec = H2OSpec::new(1, None, Resources::new(1, "1Gi".to_string(), None),
+                                           Some(CustomImage::new("registry/h2o:latest".to_string(), None)));
+        assert!(super::violations(&custom).is_empty());
+    }
+
+    #[test]
+    fn test_image() {
+        let mut neither: H2OSpec = specification();
+        neither.version = None;
+        assert_eq!(vec!["Either H2O version ('version') or a custom image ('customImage') must be defined."],
+                   super::violations(&neither));
+
+        let mut both: H2OSpec = specification();
+        both.custom_image = Some(CustomImage::new("registry/h2o:latest".to_string(), None));
+        assert!(super::violations(&both)[0].contains("mutually exclusive"));
+
+        let mut empty: H2OSpec = specification();
+        empty.version = Some(" ".to_string());
+        assert_eq!(1, super::violations(&empty).len());
+    }
+
+    #[test]
+    fn test_resources() {
+        let mut specification: H2OSpec = specification();
+        specification.nodes = 0;
+        specification.resources = Resources::new(0, "lots".to_string(), Some(0));
+        specification.resources.ephemeral_storage = Some("10GB".to_string());
+        let violations: Vec<String> = super::violations(&specification);
+        assert_eq!(5, violations.len(), "{:?}", violations);
+        assert!(violations[2].starts_with("Invalid memory ('resources.memory'): Invalid memory quantity 'lots'"));
+
+        specification = super::tests::specification();
+        specification.resources.memory = "128Mi".to_string();
+        assert_eq!(vec!["Invalid memory ('resources.memory'): Memory of each H2O node must be at least 256Mi, got '128Mi'."],
+                   super::violations(&specification));
+
+        specification = super::tests::specification();
+        specification.resources.requests = Some(ResourceRequests::new(Some("500m".to_string()), Some("8Gi".to_string())));
+        assert_eq!(vec!["Memory request ('resources.requests.memory') of '8Gi' exceeds the memory limit of '4Gi'."],
+                   super::violations(&specification));
+    }
+
+    #[test]
+    fn test_storage() {
+        let mut specification: H2OSpec = specification();
+        specification.storage = Some(Storage::new("0".to_string(), None, Some("data".to_string()), StorageDeletionPolicy::Retain));
+        assert_eq!(2, super::violations(&specification).len());
+        let error: String = super::validate(&specification).unwrap_err().to_string();
+        assert!(error.contains("Invalid H2O specification:\nStorage size ('storage.size') must be greater than zero.\n"));
+    }
+}
@@ -0,0 +1,462 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::admissionregistration::v1::{MutatingWebhook, MutatingWebhookConfiguration, RuleWithOperations,
+                                                   ServiceReference, ValidatingWebhook, ValidatingWebhookConfiguration,
+                                                   WebhookClientConfig};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta, Status};
+use k8s_openapi::{ByteString, Resource};
+use kube::{Api, Client};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::crd;
+use crate::crd::H2OSpec;
+use crate::defaults::Defaults;
+use crate::Error;
+
+/// Name of the service in front of the operator's webhook server, unless specified otherwise.
+pub const DEFAULT_SERVICE: &str = "h2o-operator";
+/// Namespace of the service in front of the operator's webhook server in the generated manifests.
+pub const DEFAULT_NAMESPACE: &str = "h2o-operator";
+/// Port of the service in front of the operator's webhook server.
+pub const SERVICE_PORT: i32 = 443;
+/// Path the validating admission webhook is served on.
+pub const VALIDATION_PATH: &str = "/validate";
+/// Path the mutating admission webhook is served on.
+pub const MUTATION_PATH: &str = "/mutate";
+/// Label of namespaces holding their name, set by Kubernetes 1.21 and newer. Admission webhooks of an operator
+/// are limited to its own namespace by this label.
+pub const NAMESPACE_NAME_LABEL: &str = "kubernetes.io/metadata.name";
+
+/// Service the Kubernetes API server reaches the webhooks served by the operator through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookService {
+    /// Namespace of the service.
+    pub namespace: String,
+    /// Name of the service.
+    pub service: String,
+    /// PEM-encoded CA certificate(s) the webhook server's certificate is verified with. If not defined, the CA bundle
+    /// is expected to be injected externally, e.g. by cert-manager's CA injector.
+    pub ca_bundle: Option<Vec<u8>>,
+}
+
+impl WebhookService {
+    /// Constructor for `WebhookService`
+    ///
+    /// # Arguments
+    /// `namespace` - Namespace of the service in front of the webhook server
+    /// `service` - Name of the service in front of the webhook server
+    /// `ca_bundle` - Optional PEM-encoded CA certificate(s) the webhook server's certificate is verified with
+    pub fn new(namespace: String, service: String, ca_bundle: Option<Vec<u8>>) -> Self {
+        WebhookService { namespace, service, ca_bundle }
+    }
+}
+
+impl Default for WebhookService {
+    /// Service `DEFAULT_SERVICE` in `DEFAULT_NAMESPACE`, with no CA bundle.
+    fn default() -> Self {
+        WebhookService::new(DEFAULT_NAMESPACE.to_string(), DEFAULT_SERVICE.to_string(), None)
+    }
+}
+
+/// An `AdmissionReview` sent by the Kubernetes API server to an admission webhook, and sent back with the `response`.
+/// Both `admission.k8s.io/v1` and `v1beta1` reviews share this form. Only the fields used by the webhooks are mapped.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AdmissionReview {
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request: Option<AdmissionRequest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<AdmissionResponse>,
+}
+
+/// Operation on an `object` to admit.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AdmissionRequest {
+    pub uid: String,
+    /// `CREATE`, `UPDATE`, `DELETE` or `CONNECT`
+    pub operation: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object: Option<Value>,
+}
+
+/// Whether the operation is `allowed`. Rejections are explained by the `status` message, warnings are displayed
+/// to the user either way. Mutating webhooks modify the admitted object by a `JSONPatch` in `patch`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AdmissionResponse {
+    pub uid: String,
+    pub allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// JSON patch of the admitted object, base64-encoded when serialized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch: Option<ByteString>,
+    #[serde(rename = "patchType", default, skip_serializing_if = "Option::is_none")]
+    pub patch_type: Option<String>,
+}
+
+impl AdmissionResponse {
+    /// Response allowing the operation, with given `warnings`.
+    fn allow(uid: String, warnings: Vec<String>) -> Self {
+        AdmissionResponse { uid, allowed: true, status: None, warnings, patch: None, patch_type: None }
+    }
+
+    /// Response allowing the operation with the object modified by given JSON `patch`.
+    fn patch(uid: String, patch: Value) -> Self {
+        AdmissionResponse {
+            patch: Some(ByteString(patch.to_string().into_bytes())),
+            patch_type: Some("JSONPatch".to_string()),
+            ..AdmissionResponse::allow(uid, Vec::new())
+        }
+    }
+
+    /// Response rejecting the operation with given `code` and human-readable `message`.
+    fn deny(uid: String, code: i32, message: String) -> Self {
+        AdmissionResponse {
+            uid,
+            allowed: false,
+            status: Some(Status {
+                code: Some(code),
+                message: Some(message),
+                status: Some("Failure".to_string()),
+                ..Status::default()
+            }),
+            warnings: Vec::new(),
+            patch: None,
+            patch_type: None,
+        }
+    }
+}
+
+/// Answers an `AdmissionReview` of an `H2O` resource by validating its specification, see `validation::violations`.
+/// Invalid specifications are rejected with all the violations listed, valid ones are admitted with security warnings,
+/// if any (see `security::warnings`). Resources being deleted are always admitted, so their finalizers may be removed.
+///
+/// # Arguments
+/// `review` - `AdmissionReview` as received from the Kubernetes API server
+pub fn validate(review: AdmissionReview) -> AdmissionReview {
+    let response: Option<AdmissionResponse> = review.request.map(|request| {
+        if deleted(request.object.as_ref()) {
+            return AdmissionResponse::allow(request.uid, Vec::new());
+        }
+        match request.object.map(h2o_spec).transpose() {
+            Ok(Some(specification)) => match crate::validation::validate(&specification) {
+                Ok(()) => AdmissionResponse::allow(request.uid, crate::security::warnings(&specification)),
+                Err(Error::UserError(message)) => AdmissionResponse::deny(request.uid, 422, message),
+                Err(error) => AdmissionResponse::deny(request.uid, 422, error.to_string()),
+            },
+            Ok(None) => AdmissionResponse::allow(request.uid, Vec::new()),
+            Err(error) => {
+                debug!("Unable to read H2O specification under admission: {}", error);
+                AdmissionResponse::deny(request.uid, 400, format!("Malformed H2O resource: {}", error))
+            }
+        }
+    });
+    AdmissionReview {
+        api_version: review.api_version,
+        kind: review.kind,
+        request: None,
+        response,
+    }
+}
+
+/// Whether the object under admission has a deletion timestamp.
+fn deleted(object: Option<&Value>) -> bool {
+    matches!(object.and_then(|object| object.pointer("/metadata/deletionTimestamp")), Some(timestamp) if !timestamp.is_null())
+}
+
+/// Answers an `AdmissionReview` of an `H2O` resource by filling in the operator-level `defaults` missing in its
+/// specification and labels, see `Defaults`. The specification is patched in the API version it was submitted in.
+/// Resources being deleted and malformed resources are admitted unchanged, the latter are rejected by `validate`.
+///
+/// # Arguments
+/// `review` - `AdmissionReview` as received from the Kubernetes API server
+/// `defaults` - Operator-level defaults to fill in
+pub fn mutate(review: AdmissionReview, defaults: &Defaults) -> AdmissionReview {
+    let response: Option<AdmissionResponse> = review.request.map(|request| {
+        let object: Value = match request.object {
+            Some(object) if !deleted(Some(&object)) => object,
+            _ => return AdmissionResponse::allow(request.uid, Vec::new()),
+        };
+        match defaults_patch(object, defaults) {
+            Ok(Some(patch)) => AdmissionResponse::patch(request.uid, patch),
+            Ok(None) => AdmissionResponse::allow(request.uid, Vec::new()),
+            Err(error) => {
+                debug!("Unable to fill in defaults of H2O resource under admission: {}", error);
+                AdmissionResponse::allow(request.uid, Vec::new())
+            }
+        }
+    });
+    AdmissionReview {
+        api_version: review.api_version,
+        kind: review.kind,
+        request: None,
+        response,
+    }
+}
+
+/// JSON patch filling the `defaults` into an `H2O` object of any served API version. `None` if nothing is missing.
+fn defaults_patch(object: Value, defaults: &Defaults) -> Result<Option<Value>, Error> {
+    let api_version: String = object.get("apiVersion").and_then(Value::as_str).unwrap_or_default().to_string();
+    let mut labels: BTreeMap<String, String> = match object.pointer("/metadata/labels") {
+        Some(labels) if !labels.is_null() => serde_json::from_value(labels.clone())?,
+        _ => BTreeMap::new(),
+    };
+    let mut specification: H2OSpec = h2o_spec(object.clone())?;
+
+    let mut patch: Vec<Value> = Vec::new();
+    if defaults.apply(&mut specification) {
+        let mut completed: Value = object;
+        completed["apiVersion"] = Value::String(crd::H2O::API_VERSION.to_string());
+        completed["spec"] = serde_json::to_value(&specification)?;
+        let spec: Value = crate::conversion::convert(completed, &api_version)?["spec"].take();
+        patch.push(json!({ "op": "add", "path": "/spec", "value": spec }));
+    }
+    if defaults.apply_labels(&mut labels) {
+        patch.push(json!({ "op": "add", "path": "/metadata/labels", "value": labels }));
+    }
+    Ok(if patch.is_empty() { None } else { Some(Value::Array(patch)) })
+}
+
+/// Specification of an `H2O` object of any served API version, converted to `v1beta` if necessary.
+fn h2o_spec(object: Value) -> Result<H2OSpec, Error> {
+    let mut object: Value = crate::conversion::convert(object, crd::H2O::API_VERSION)?;
+    let spec: Value = object.get_mut("spec").map(Value::take).unwrap_or(Value::Null);
+    Ok(serde_json::from_value(spec)?)
+}
+
+/// `ValidatingWebhookConfiguration` pointing the Kubernetes API server to the validating webhook behind the given
+/// `service`. Creation and updates of `H2O` resources in the service's namespace are validated, in any API version.
+/// Limited to the operator's namespace by the `NAMESPACE_NAME_LABEL`, as each namespace-scoped operator only
+/// validates the resources it manages.
+///
+/// # Arguments
+/// `service` - Service the validating webhook is reachable through, in the operator's namespace
+pub fn validating_webhook_configuration(service: &WebhookService) -> ValidatingWebhookConfiguration {
+    ValidatingWebhookConfiguration {
+        metadata: ObjectMeta {
+            name: Some(configuration_name(service)),
+            labels: Some(crate::h2o_labels(&service.service)),
+            ..ObjectMeta::default()
+        },
+        webhooks: Some(vec![ValidatingWebhook {
+            name: "validate.h2os.h2o.ai".to_string(),
+            admission_review_versions: vec!["v1".to_string(), "v1beta1".to_string()],
+            client_config: client_config(service, VALIDATION_PATH),
+            failure_policy: Some("Fail".to_string()),
+            match_policy: Some("Equivalent".to_string()),
+            namespace_selector: Some(namespace_selector(service)),
+            object_selector: None,
+            rules: Some(vec![h2o_rule()]),
+            side_effects: "None".to_string(),
+            timeout_seconds: Some(10),
+        }]),
+    }
+}
+
+/// `MutatingWebhookConfiguration` pointing the Kubernetes API server to the mutating webhook behind the given
+/// `service`. Creation and updates of `H2O` resources in the service's namespace are mutated, in any API version,
+/// before they are validated.
+///
+/// # Arguments
+/// `service` - Service the mutating webhook is reachable through, in the operator's namespace
+pub fn mutating_webhook_configuration(service: &WebhookService) -> MutatingWebhookConfiguration {
+    MutatingWebhookConfiguration {
+        metadata: ObjectMeta {
+            name: Some(configuration_name(service)),
+            labels: Some(crate::h2o_labels(&service.service)),
+            ..ObjectMeta::default()
+        },
+        webhooks: Some(vec![MutatingWebhook {
+            name: "mutate.h2os.h2o.ai".to_string(),
+            admission_review_versions: vec!["v1".to_string(), "v1beta1".to_string()],
+            client_config: client_config(service, MUTATION_PATH),
+            failure_policy: Some("Fail".to_string()),
+            match_policy: Some("Equivalent".to_string()),
+            namespace_selector: Some(namespace_selector(service)),
+            object_selector: None,
+            reinvocation_policy: Some("Never".to_string()),
+            rules: Some(vec![h2o_rule()]),
+            side_effects: "None".to_string(),
+            timeout_seconds: Some(10),
+        }]),
+    }
+}
+
+/// Selector of the operator's namespace by the `NAMESPACE_NAME_LABEL`.
+fn namespace_selector(service: &WebhookService) -> LabelSelector {
+    let mut namespace_labels: BTreeMap<String, String> = BTreeMap::new();
+    namespace_labels.insert(NAMESPACE_NAME_LABEL.to_string(), service.namespace.clone());
+    LabelSelector {
+        match_labels: Some(namespace_labels),
+        match_expressions: None,
+    }
+}
+
+/// Name of the admission webhook configurations of an operator, unique for each operator's namespace.
+fn configuration_name(service: &WebhookService) -> String {
+    format!("{}.{}.h2o.ai", service.service, service.namespace)
+}
+
+/// Client configuration of a webhook served on given `path` behind the `service`.
+fn client_config(service: &WebhookService, path: &str) -> WebhookClientConfig {
+    WebhookClientConfig {
+        ca_bundle: service.ca_bundle.clone().map(ByteString),
+        service: Some(ServiceReference {
+            name: service.service.clone(),
+            namespace: service.namespace.clone(),
+            path: Some(path.to_string()),
+            port: Some(SERVICE_PORT),
+        }),
+        url: None,
+    }
+}
+
+/// Rule matching creation and updates of `H2O` resources. Status updates are not matched.
+fn h2o_rule() -> RuleWithOperations {
+    RuleWithOperations {
+        api_groups: Some(vec![crd::H2O::GROUP.to_string()]),
+        api_versions: Some(vec!["*".to_string()]),
+        operations: Some(vec!["CREATE".to_string(), "UPDATE".to_string()]),
+        resources: Some(vec!["h2os".to_string()]),
+        scope: Some("Namespaced".to_string()),
+    }
+}
+
+/// Creates or updates the `MutatingWebhookConfiguration` and `ValidatingWebhookConfiguration` of the operator,
+/// see `mutating_webhook_configuration` and `validating_webhook_configuration`.
+///
+/// # Arguments
+/// `client` - Client with permissions to get, create and patch `MutatingWebhookConfiguration`s and `ValidatingWebhookConfiguration`s
+/// `service` - Service the admission webhooks are reachable through, in the operator's namespace
+///
+/// # Examples
+///
+/// ```no_run
+/// #[tokio::main]
+/// async fn main() {
+/// use kube::Client;
+/// use deployment::webhook::WebhookService;
+/// let (client, namespace): (Client, String) = deployment::client::try_default().await.unwrap();
+/// let service: WebhookService = WebhookService::new(namespace, "h2o-operator".to_string(), Option::None);
+/// deployment::webhook::install(client, &service).await.unwrap();
+/// }
+/// ```
+pub async fn install(client: Client, service: &WebhookService) -> Result<(), Error> {
+    let name: String = configuration_name(service);
+    let mutating_api: Api<MutatingWebhookConfiguration> = Api::all(client.clone());
+    crate::apply::apply(&mutating_api, &name, &mutating_webhook_configuration(service)).await?;
+    let validating_api: Api<ValidatingWebhookConfiguration> = Api::all(client);
+    crate::apply::apply(&validating_api, &name, &validating_webhook_configuration(service)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::defaults::Defaults;
+
+    use super::{AdmissionReview, WebhookService};
+
+    fn review(spec: Value) -> AdmissionReview {
+        serde_json::from_value(json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "kind": { "group": "h2o.ai", "version": "v1beta", "kind": "H2O" },
+                "operation": "CREATE",
+                "object": { "apiVersion": "h2o.ai/v1beta", "kind": "H2O", "metadata": { "name": "h2o-test" }, "spec": spec }
+            }
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid: AdmissionReview = super::validate(review(json!({
+            "nodes": 1, "customImage": { "image": "registry/h2o:latest" }, "resources": { "cpu": 1, "memory": "1Gi" }
+        })));
+        assert_eq!("admission.k8s.io/v1", valid.api_version);
+        let response = valid.response.unwrap();
+        assert!(response.allowed);
+        assert_eq!("705ab4f5-6393-11e8-b7cc-42010a800002", response.uid);
+        assert_eq!(1, response.warnings.len());
+
+        let invalid: AdmissionReview = super::validate(review(json!({
+            "nodes": 0, "version": "latest", "customImage": { "image": "registry/h2o:latest" }, "resources": { "cpu": 1, "memory": "1Gi" }
+        })));
+        let response = invalid.response.unwrap();
+        assert!(!response.allowed);
+        let status = response.status.unwrap();
+        assert_eq!(Some(422), status.code);
+        assert!(status.message.unwrap().contains("('nodes') must be at least 1.\nH2O version ('version') and custom image"));
+
+        let mut deleted: AdmissionReview = review(json!({ "nodes": 0, "resources": { "cpu": 1, "memory": "1Gi" } }));
+        deleted.request.as_mut().unwrap().object.as_mut().unwrap()["metadata"]["deletionTimestamp"] = json!("2021-01-01T00:00:00Z");
+        assert!(super::validate(deleted).response.unwrap().allowed);
+
+        let malformed: AdmissionReview = super::validate(review(json!({ "nodes": "many" })));
+        assert_eq!(Some(400), malformed.response.unwrap().status.unwrap().code);
+    }
+
+    #[test]
+    fn test_validate_v1() {
+        let mut v1: AdmissionReview = review(json!({ "nodes": 1, "image": {}, "resources": { "cpu": 1, "memory": "1Gi" } }));
+        v1.request.as_mut().unwrap().object.as_mut().unwrap()["apiVersion"] = json!("h2o.ai/v1");
+        let status = super::validate(v1).response.unwrap().status.unwrap();
+        assert_eq!(Some("Invalid H2O specification:\nEither H2O version ('version') or a custom image ('customImage') must be defined.".to_string()),
+                   status.message);
+    }
+
+    #[test]
+    fn test_mutate() {
+        let defaults: Defaults = Defaults::from_yaml("{ memoryPercentage: 80, version: latest, labels: { cost-center: research } }").unwrap();
+        let mutated = super::mutate(review(json!({ "nodes": 1, "resources": { "cpu": 1, "memory": "1Gi" } })), &defaults)
+            .response.unwrap();
+        assert!(mutated.allowed);
+        assert_eq!(Some("JSONPatch".to_string()), mutated.patch_type);
+        let patch: Value = serde_json::from_slice(&mutated.patch.unwrap().0).unwrap();
+        assert_eq!(json!([
+            { "op": "add", "path": "/spec", "value": { "nodes": 1, "version": "latest", "resources": { "cpu": 1, "memory": "1Gi", "memoryPercentage": 80 } } },
+            { "op": "add", "path": "/metadata/labels", "value": { "cost-center": "research" } }
+        ]), patch);
+
+        let mut v1: AdmissionReview = review(json!({ "nodes": 1, "image": {}, "resources": { "cpu": 1, "memory": "1Gi", "memoryPercentage": 90 } }));
+        let object: &mut Value = v1.request.as_mut().unwrap().object.as_mut().unwrap();
+        object["apiVersion"] = json!("h2o.ai/v1");
+        object["metadata"]["labels"] = json!({ "cost-center": "sales" });
+        let patch: Value = serde_json::from_slice(&super::mutate(v1, &defaults).response.unwrap().patch.unwrap().0).unwrap();
+        assert_eq!(json!([{ "op": "add", "path": "/spec", "value": { "nodes": 1, "image": { "version": "latest" }, "resources": { "cpu": 1, "memory": "1Gi", "memoryPercentage": 90 } } }]),
+                   patch);
+
+        let complete = super::mutate(review(json!({ "nodes": 1, "version": "3.32.0.1", "resources": { "cpu": 1, "memory": "1Gi", "memoryPercentage": 90 } })),
+                                     &Defaults::default()).response.unwrap();
+        assert!(complete.allowed && complete.patch.is_none());
+        let malformed = super::mutate(review(json!({ "nodes": "many" })), &defaults).response.unwrap();
+        assert!(malformed.allowed && malformed.patch.is_none());
+    }
+
+    #[test]
+    fn test_webhook_configurations() {
+        let service: WebhookService = WebhookService::new("h2o".to_string(), "h2o-operator".to_string(), Some(b"ca".to_vec()));
+        let configuration = super::validating_webhook_configuration(&service);
+        assert_eq!(Some("h2o-operator.h2o.h2o.ai".to_string()), configuration.metadata.name);
+        let webhook = &configuration.webhooks.unwrap()[0];
+        let reference = webhook.client_config.service.as_ref().unwrap();
+        assert_eq!(("h2o", "h2o-operator", Some("/validate".to_string())), (reference.namespace.as_str(), reference.name.as_str(), reference.path.clone()));
+        assert_eq!(Some(b"ca".to_vec()), webhook.client_config.ca_bundle.as_ref().map(|ca_bundle| ca_bundle.0.clone()));
+        assert_eq!("h2o", webhook.namespace_selector.as_ref().unwrap().match_labels.as_ref().unwrap()[super::NAMESPACE_NAME_LABEL]);
+
+        let mutating = super::mutating_webhook_configuration(&service);
+        assert_eq!(Some("h2o-operator.h2o.h2o.ai".to_string()), mutating.metadata.name);
+        let webhook = &mutating.webhooks.unwrap()[0];
+        assert_eq!(Some("/mutate".to_string()), webhook.client_config.service.as_ref().unwrap().path);
+        assert_eq!(Some(vec!["CREATE".to_string(), "UPDATE".to_string()]), webhook.rules.as_ref().unwrap()[0].operations);
+    }
+}
@@ -1,13 +1,19 @@
 use std::time::Duration;
 
 use futures::StreamExt;
+use k8s_openapi::api::apps::v1::StatefulSet;
+use k8s_openapi::api::core::v1::Service;
+use k8s_openapi::api::networking::{v1, v1beta1};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
 use kube::{Api, Client};
 use kube::api::{ListParams, Meta};
 use kube_runtime::controller::{Context, ReconcilerAction};
 use kube_runtime::Controller;
-use log::{error, info};
+use log::{debug, error, info, warn};
 
-use deployment::crd::H2O;
+use deployment::crd::{H2O, H2OPhase, H2OSpec};
+use deployment::defaults::Defaults;
+use deployment::ingress::IngressApiVersion;
 use deployment::Error;
 
 /// Creates and runs an instance of `kube_runtime::Controller` internally, endlessly waiting for incoming events
@@ -35,25 +41,53 @@ use deployment::Error;
 /// statefulsets, services and other resources are instructed to be deleted, it is the duty of the respective
 /// controllers to delete them and the finalizer record is removed, allowing for the `H2O` resource to be removed.
 ///
+/// # Owned resources
+/// All the sub-resources created by the operator carry a controller owner reference to their `H2O` resource.
+/// Therefore, even if the operator is not running at the time an `H2O` resource is deleted (or the finalizer
+/// is removed manually), the sub-resources are garbage collected by Kubernetes. Changes to the owned resources
+/// (statefulsets, services and ingresses) trigger reconciliation of their owning `H2O` resource, re-applying
+/// the resources - any drift from the `H2O` specification, e.g. a manually scaled statefulset, is reverted.
+/// Ingresses are watched in the newest API version served by the Kubernetes cluster.
+///
 /// # Arguments
 /// - `client` - A Kubernetes client from the `kube` crate. Required to create other resources representing the
 /// final H2O cluster in Kubernetes.
 /// - `namespace` - H2O operator is namespace-scoped. H2Os are deployed into the namespace the operator has been deployed to.
+/// - `defaults` - Operator-level defaults, filled into `H2O` resources admitted without the mutating webhook.
 ///
 /// # Examples
 ///
 /// ```no_run
 ///     let (client, namespace): (Client, String) = deployment::try_default().await?;
-///     controller::run(client, &namespace).await;
+///     controller::run(client, &namespace, Defaults::default()).await;
 /// ```
-pub async fn run(client: Client, namespace: &str) {
+pub async fn run(client: Client, namespace: &str, defaults: Defaults) {
     let api: Api<H2O> = Api::namespaced(client.clone(), namespace);
-    Controller::new(api.clone(), ListParams::default())
-        .owns(api, ListParams::default())
+    let statefulset_api: Api<StatefulSet> = Api::namespaced(client.clone(), namespace);
+    let service_api: Api<Service> = Api::namespaced(client.clone(), namespace);
+    let ingress_api_version: IngressApiVersion = deployment::ingress::api_version(&client).await
+        .unwrap_or_else(|error| {
+            error!("Unable to detect Ingress API version, assuming {:?}. Error:\n{}", deployment::ingress::FALLBACK_API_VERSION, error);
+            deployment::ingress::FALLBACK_API_VERSION
+        });
+    let controller: Controller<H2O> = Controller::new(api, ListParams::default())
+        .owns(statefulset_api, ListParams::default())
+        .owns(service_api, ListParams::default());
+    let controller: Controller<H2O> = match ingress_api_version {
+        IngressApiVersion::V1 => {
+            let ingress_api: Api<v1::Ingress> = Api::namespaced(client.clone(), namespace);
+            controller.owns(ingress_api, ListParams::default())
+        }
+        IngressApiVersion::V1beta1 => {
+            let ingress_api: Api<v1beta1::Ingress> = Api::namespaced(client.clone(), namespace);
+            controller.owns(ingress_api, ListParams::default())
+        }
+    };
+    controller
         .run(
             reconcile,
             error_policy,
-            Context::new(ContextData::new(client.clone(), namespace.to_string())),
+            Context::new(ContextData::new(client.clone(), namespace.to_string(), defaults, ingress_api_version)),
         )
         .for_each(|res| async move {
             match res {
@@ -70,6 +104,10 @@ struct ContextData {
     client: Client,
     /// Default namespace to deploy resources to - unless explicitly specified by the user
     default_namespace: String,
+    /// Operator-level defaults of H2O clusters
+    defaults: Defaults,
+    /// API version of the ingresses of H2O clusters, detected once on operator startup
+    ingress_api_version: IngressApiVersion,
 }
 
 impl ContextData {
@@ -79,18 +117,20 @@ impl ContextData {
     ///
     /// - `client` - Kubernetes client to manipulate Kubernetes resources
     /// - `default_namespace` - Default namespace to deploy resources to - unless explicitly specified by the user
-    pub fn new(client: Client, default_namespace: String) -> Self {
-        ContextData { client, default_namespace }
+    /// - `defaults` - Operator-level defaults of H2O clusters
+    /// - `ingress_api_version` - API version of the ingresses of H2O clusters
+    pub fn new(client: Client, default_namespace: String, defaults: Defaults, ingress_api_version: IngressApiVersion) -> Self {
+        ContextData { client, default_namespace, defaults, ingress_api_version }
     }
 }
 
 /// Action to be taken by the controller if there is a new event on `H2O` resource.
 enum ControllerAction {
-    /// Create a new H2O cluster
-    Create,
+    /// Create a new H2O cluster or bring the resources of an existing H2O cluster in line with the specification
+    Apply,
     /// Delete resources of an existing H2O Cluster
     Delete,
-    /// Updating existing H2O deployment is not supported - once H2O is clustered, it is immutable. Any events requiring on actions.
+    /// The `H2O` resource is being deleted and its resources have been deleted already. No action is taken.
     Noop,
 }
 
@@ -105,21 +145,85 @@ enum ControllerAction {
 /// No examples provided, as this method should be called only by the controller.
 async fn reconcile(h2o: H2O, context: Context<ContextData>) -> Result<ReconcilerAction, Error> {
     match examine_h2o_for_actions(&h2o) {
-        ControllerAction::Create => {
-            create_h2o_deployment(&h2o, &context).await?;
+        ControllerAction::Apply => {
+            let specification: H2OSpec = completed_specification(&h2o, &context.get_ref().defaults);
+            if let Err(Error::UserError(message)) = deployment::validation::validate(&specification) {
+                return reject_invalid_h2o(&h2o, &context, &message).await;
+            }
+            apply_h2o_deployment(&h2o, &context).await?;
+            update_h2o_status(&h2o, &context).await
         }
         ControllerAction::Delete => {
-            delete_h2o_deployment(&h2o, &context).await?;
+            delete_h2o_deployment(&h2o, &context).await
         }
         ControllerAction::Noop => {
             let h2o_serialized: String = serde_yaml::to_string(&h2o).unwrap_or(h2o.name());
-            info!("No action taken for an existing deployment:\n{}", h2o_serialized); // Log the whole incoming H2O description
+            debug!("No action taken for a deleted deployment:\n{}", h2o_serialized); // Log the whole incoming H2O description
+            // The resource may be gone already, its status is not updated
+            Ok(ReconcilerAction {
+                requeue_after: Option::None,
+            })
         }
     }
+}
 
-    return Ok(ReconcilerAction {
-        requeue_after: None,
-    });
+/// Updates the status of the `H2O` resource according to the observed state of its H2O pods.
+///
+/// Changes in the state of the pods do not necessarily trigger reconciliation of the `H2O` resource. Therefore,
+/// unless the H2O cluster is ready or failed, reconciliation is re-scheduled to keep the status up to date
+/// while the H2O cluster is forming.
+///
+/// # Arguments
+/// `h2o` - The `H2O` resource instance, representing the current state of the resource in Kubernetes cluster.
+/// `context` - An instance of `ContextData`, provided by the controller with each reconciliation event.
+async fn update_h2o_status(
+    h2o: &H2O,
+    context: &Context<ContextData>,
+) -> Result<ReconcilerAction, Error> {
+    let data: &ContextData = context.get_ref();
+    let specification: H2OSpec = completed_specification(h2o, &data.defaults);
+    let updated_h2o: H2O = deployment::status::update(data.client.clone(), h2o, &specification).await?;
+    let phase: Option<H2OPhase> = updated_h2o.status.as_ref().and_then(|status| status.phase);
+
+    let requeue_after: Option<Duration> = match phase {
+        Some(H2OPhase::Ready) | Some(H2OPhase::Failed) => Option::None,
+        _ => Option::Some(Duration::from_secs(5)),
+    };
+    Ok(ReconcilerAction { requeue_after })
+}
+
+/// Marks an `H2O` resource with an invalid specification as failed, with the violations found in its status.
+/// Resources stored before the validating webhook has been deployed may be invalid. Reconciliation is not
+/// re-scheduled, as it would keep failing the same way until the specification is changed.
+///
+/// # Arguments
+/// `h2o` - The `H2O` resource instance, representing the current state of the resource in Kubernetes cluster.
+/// `context` - An instance of `ContextData`, provided by the controller with each reconciliation event.
+/// `message` - Human-readable description of the specification violations
+async fn reject_invalid_h2o(
+    h2o: &H2O,
+    context: &Context<ContextData>,
+    message: &str,
+) -> Result<ReconcilerAction, Error> {
+    warn!("H2O '{}' not deployed. {}", h2o.name(), message);
+    deployment::status::update_invalid(context.get_ref().client.clone(), h2o, message).await?;
+    Ok(ReconcilerAction {
+        requeue_after: Option::None,
+    })
+}
+
+/// Specification of the H2O cluster deployed for the `H2O` resource - its specification completed by operator `defaults`.
+/// Resources admitted by the mutating webhook are complete already, others are completed the same way.
+///
+/// # Arguments
+/// `h2o` - The `H2O` resource instance, representing the current state of the resource in Kubernetes cluster.
+/// `defaults` - Operator-level defaults of H2O clusters
+fn completed_specification(h2o: &H2O, defaults: &Defaults) -> H2OSpec {
+    let mut specification: H2OSpec = h2o.spec.clone();
+    if defaults.apply(&mut specification) {
+        debug!("H2O '{}' completed by operator defaults.", h2o.name());
+    }
+    specification
 }
 
 /// Reconciliation failure logic, intended to be called by the controller itself. Logs the error
@@ -139,7 +243,8 @@ fn error_policy(error: &Error, _context: Context<ContextData>) -> ReconcilerActi
 }
 
 /// Examines the incoming `H2O` resource and determines the `ControllerAction` to be taken
-/// upon it.
+/// upon it. Unless the `H2O` resource is being deleted, its resources are applied on every reconciliation,
+/// so changes of the specification, as well as any drift of the owned resources, are reconciled.
 ///
 /// # Arguments
 ///
@@ -152,59 +257,75 @@ fn error_policy(error: &Error, _context: Context<ContextData>) -> ReconcilerActi
 fn examine_h2o_for_actions(h2o: &H2O) -> ControllerAction {
     let has_finalizer: bool = deployment::crd::has_h2o3_finalizer(&h2o);
     let has_deletion_timestamp: bool = deployment::crd::has_deletion_stamp(&h2o);
-    return if has_finalizer && has_deletion_timestamp {
+    return if !has_deletion_timestamp {
+        ControllerAction::Apply
+    } else if has_finalizer {
         ControllerAction::Delete
-    } else if !has_finalizer && !has_deletion_timestamp {
-        ControllerAction::Create
     } else {
         ControllerAction::Noop
     };
 }
 
-/// Creates an H2O deployment as dictated by the `H2O` resource specification obtained,
+/// Creates or updates an H2O deployment as dictated by the `H2O` resource specification obtained,
 /// including but not limited to statefulsets, including its respective pods and headless services to make H2O
 /// clustering possible. These sub-resources are built-in to each Kubernetes cluster and handled by their respective
 /// controllers. The order of creation of the sub-resources is not guaranteed and is invoked asynchronously.
 ///
+/// The sub-resources are applied idempotently (see `deployment::create_h2o_cluster`), missing ones are created
+/// and the drifted ones are patched to match the specification. The ingress owned by the `H2O` resource is deleted once
+/// `spec.ingress` is removed, ingresses created otherwise, e.g. by `h2ok ingress`, are left untouched.
+///
 /// Creates an H2O-specific finalizer on the existing `H2O` resources to indicate pre-deletion hooks must
-/// be handled by this operator before resource deletion.
+/// be handled by this operator before resource deletion. The finalizer is only added once all the sub-resources
+/// are successfully applied. Therefore, if the deployment fails, the `H2O` resource is reconciled again
+/// and the sub-resources are idempotently re-applied.
 ///
 /// # Arguments
 /// `h2o` - The `H2O` resource instance, representing the current state of the resource in Kubernetes cluster.
 /// `context` - An instance of `ContextData`, provided by the controller with each reconciliation event.
-async fn create_h2o_deployment(
+async fn apply_h2o_deployment(
     h2o: &H2O,
     context: &Context<ContextData>,
 ) -> Result<ReconcilerAction, Error> {
     match serde_yaml::to_string(h2o) {
         Ok(h2o_yaml) => {
-            info!("Attempting to create the following H2O cluster:\n{}", h2o_yaml)
+            debug!("Attempting to apply the following H2O cluster:\n{}", h2o_yaml)
         }
         Err(_) => {
-            error!("Attempting to create H2O cluster: {}", h2o.name());
+            error!("Attempting to apply H2O cluster: {}", h2o.name());
         }
     };
-    info!("{}", serde_yaml::to_string(h2o).unwrap());
     let data: &ContextData = context.get_ref();
     let name: String = h2o.metadata.name.clone()
         .ok_or(Error::UserError("Unable to create H2O deployment. No H2O name provided.".to_string()))?;
 
-    let deploy_future =
-        deployment::create_h2o_cluster(data.client.clone(), &h2o.spec, &data.default_namespace, &name);
-    let add_finalizer_future =
-        deployment::finalizer::add_finalizer(data.client.clone(), &data.default_namespace, &name);
+    let specification: H2OSpec = completed_specification(h2o, &data.defaults);
+    for warning in deployment::security::warnings(&specification) {
+        warn!("H2O '{}': {}", &name, warning);
+    }
+    let owner: Option<OwnerReference> = deployment::crd::owner_reference(h2o);
 
-    tokio::try_join!(deploy_future, add_finalizer_future)?;
-    deployment::crd::add_empty_status(data.client.clone(), &name, &data.default_namespace).await.unwrap();
+    deployment::create_h2o_cluster(data.client.clone(), &specification, &data.default_namespace, &name,
+                                   data.ingress_api_version, owner.as_ref()).await?;
+    if let (None, Some(owner)) = (specification.ingress.as_ref(), owner.as_ref()) {
+        deployment::ingress::delete_owned(data.client.clone(), data.ingress_api_version, &data.default_namespace,
+                                          &name, owner).await?;
+    }
 
-    info!("H2O '{}' successfully deployed.", &name);
+    if deployment::crd::has_h2o3_finalizer(h2o) {
+        debug!("H2O '{}' is in line with its specification.", &name);
+    } else {
+        deployment::finalizer::add_finalizer(data.client.clone(), &data.default_namespace, &name).await?;
+        info!("H2O '{}' successfully deployed.", &name);
+    }
     return Ok(ReconcilerAction {
         requeue_after: Option::None,
     });
 }
 
 /// Deletes all resources related to the given `H2O` resource intended for deletion,
-/// including but not necessarily limited to statefulsets, including its respective pods and headless services.
+/// including but not necessarily limited to statefulsets, including its respective pods, services and persistent volume claims
+/// with the `Delete` deletion policy (see `deployment::delete_h2o_cluster`).
 /// It is assumed an H2o-specific finalizer is present on the resource before. The finalizer is removed from the `H2O` resource
 /// right after deletion of all sub-resources is issued.
 ///
@@ -227,10 +348,9 @@ async fn delete_h2o_deployment(
     let namespace: &str = h2o.meta().namespace.as_ref()
         .ok_or(Error::UserError("Unable to delete H2O deployment. No namespace provided.".to_string()))?;
 
-    let statefulset_future = deployment::statefulset::delete(data.client.clone(), namespace, name);
-    let service_future = deployment::headless_service::delete(data.client.clone(), namespace, name);
+    deployment::status::update(data.client.clone(), h2o, &completed_specification(h2o, &data.defaults)).await?;
 
-    tokio::try_join!(statefulset_future, service_future)?;
+    deployment::delete_h2o_cluster(data.client.clone(), namespace, name).await?;
     deployment::finalizer::remove_finalizer(data.client.clone(), name, namespace).await?;
 
     info!("Deleted H2O '{}'.", &name);
@@ -1,16 +1,30 @@
+extern crate clap;
 extern crate deployment;
 extern crate futures;
 extern crate log;
 extern crate simple_logger;
 extern crate tokio;
 
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
 use kube::Client;
-use log::{info, error, LevelFilter};
+use kube::Error as KubeError;
+use log::{info, error, warn, LevelFilter};
 use simple_logger::SimpleLogger;
 
+use deployment::crd_manifest::{CrdApiVersion, CrdInstallation};
+use deployment::defaults::Defaults;
+use deployment::webhook::WebhookService;
 use deployment::Error;
 
 mod controller;
+mod webhook;
+
+/// Maximum time to wait for the H2O CRD to be established once created or upgraded.
+const CRD_ESTABLISHED_TIMEOUT: Duration = Duration::from_secs(60);
 
 /// Entrypoint to H2O Open Source Kubernetes operator executable. This operator acts upon H2O-related
 /// Custom Resource Definitions (CRDs), handling their state changes, creation and deletion.
@@ -19,8 +33,15 @@ mod controller;
 ///
 /// 1. First, utility libraries (logging etc.) are initialized.
 /// 2. An attempt to obtain a Kubernetes client from a Kubeconfig is made.
-/// 3. H2O Custom resource definition (CRD) presence in cluster is detected. If not present
-///     attempt to deploy it is made. If unsuccessful (permissions), the operator shuts down.
+/// 3. H2O Custom resource definition (CRD) presence in cluster is detected. If not present or older than the CRD
+///     generated by this operator, an attempt to create or upgrade it is made and the operator waits until the CRD
+///     is established. If unsuccessful (permissions), the operator shuts down. Skipped with `--skip_crd_install`.
+/// 4. If a webhook certificate is given, the webhook server is started in the background. The Kubernetes API server
+///     calls it to convert `H2O` resources between the `v1beta` and `v1` API versions (the `v1` version is only served
+///     with the webhook server), to fill operator-level defaults
+///     (`--defaults`) into `H2O` resources in the operator's namespace and to validate them before they are admitted.
+///     The `MutatingWebhookConfiguration` and `ValidatingWebhookConfiguration` are created or updated once the server
+///     is started.
 ///
 /// # Controller
 ///
@@ -40,16 +61,248 @@ mod controller;
 /// and a multi-threaded executor. The multi-threaded executor is [enabled by default](https://docs.rs/tokio/0.3.3/tokio/attr.main.html)
 /// and defaults to number of detected CPUs. To ensure optimal utilization of resources, the default option is kept.
 ///
+/// # Printing the CRD
+///
+/// With `h2o-operator crd print`, the H2O CRD generated from H2O resource types is printed and the operator exits.
+/// The checked-in CRD manifests are generated this way. With `--conversion_webhook <namespace>`, the printed CRD
+/// serves the `v1` API version as well, converted by the webhook of an operator running in the given namespace.
 #[tokio::main]
 async fn main() -> Result<(), Error> {
+    let args: ArgMatches = build_app().get_matches();
+    if let Some(print_args) = args.subcommand_matches("crd").and_then(|crd_args| crd_args.subcommand_matches("print")) {
+        let webhook: Option<WebhookService> = print_args.value_of("conversion_webhook")
+            .map(|namespace| webhook_service(&args, namespace));
+        print!("{}", deployment::crd_manifest::h2o_crd_yaml(crd_api_version(print_args), webhook.as_ref())?);
+        return Ok(());
+    }
+
     initialize_logging();
     info!("H2O Kubernetes Operator");
     let (client, namespace): (Client, String) = deployment::client::try_default().await?;
     print_startup_diagnostics(&client, &namespace).await;
-    controller::run(client.clone(), &namespace).await;
+    let defaults: Defaults = defaults(&args);
+    let service: Option<WebhookService> = start_webhook_server(&args, client.clone(), &namespace, &defaults).await;
+    if args.is_present("skip_crd_install") {
+        info!("Skipping installation of the H2O CRD, the CRD is expected to be managed externally.");
+        verify_crd_conversion(client.clone(), service.as_ref()).await;
+    } else {
+        install_crd(client.clone(), service.as_ref()).await;
+    }
+    controller::run(client.clone(), &namespace, defaults).await;
     Ok(())
 }
 
+/// Command line interface of the operator. Without a subcommand, the operator is started.
+fn build_app<'a>() -> App<'a, 'a> {
+    App::new("H2O Kubernetes Operator")
+        .arg(Arg::with_name("skip_crd_install")
+            .long("skip_crd_install")
+            .help("Do not create or upgrade the H2O CRD on start, e.g. when CRDs are managed externally by OLM."))
+        .arg(Arg::with_name("webhook_cert")
+            .long("webhook_cert")
+            .number_of_values(1)
+            .requires("webhook_key")
+            .help("Path to the PEM-encoded TLS certificate of the webhook server, e.g. 'tls.crt' of a mounted secret. Webhooks are not served if not defined."))
+        .arg(Arg::with_name("webhook_key")
+            .long("webhook_key")
+            .number_of_values(1)
+            .requires("webhook_cert")
+            .help("Path to the PEM-encoded private key of the webhook server's TLS certificate."))
+        .arg(Arg::with_name("webhook_ca")
+            .long("webhook_ca")
+            .number_of_values(1)
+            .help("Path to the PEM-encoded CA the webhook server's certificate is verified with. Set as the CA bundle of the H2O CRD's conversion webhook and of the validating webhook, unless injected externally."))
+        .arg(Arg::with_name("webhook_port")
+            .long("webhook_port")
+            .number_of_values(1)
+            .validator(validate_port)
+            .default_value(webhook::DEFAULT_WEBHOOK_PORT)
+            .help("Port the webhook server listens on."))
+        .arg(Arg::with_name("webhook_service")
+            .long("webhook_service")
+            .number_of_values(1)
+            .default_value(deployment::webhook::DEFAULT_SERVICE)
+            .help("Name of the service in the operator's namespace the Kubernetes API server reaches the webhook server through."))
+        .arg(Arg::with_name("defaults")
+            .long("defaults")
+            .number_of_values(1)
+            .help("Path to a YAML file with defaults filled into H2O resources missing them, e.g. a mounted ConfigMap. Keys: memoryPercentage, version, spreading, nodeSelector and labels."))
+        .subcommand(SubCommand::with_name("crd")
+            .about("Operations with the H2O CustomResourceDefinition")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("print")
+                .about("Prints the H2O CustomResourceDefinition generated from H2O resource types")
+                .arg(Arg::with_name("api_version")
+                    .long("api_version")
+                    .number_of_values(1)
+                    .possible_values(&["v1", "v1beta1"])
+                    .default_value("v1")
+                    .help("API version of the CustomResourceDefinition - v1beta1 for the OLM bundle."))
+                .arg(Arg::with_name("conversion_webhook")
+                    .long("conversion_webhook")
+                    .number_of_values(1)
+                    .value_name("namespace")
+                    .help("Serve the v1 API version as well, converted by the webhook of the operator in given namespace. The webhook must be served, see '--webhook_cert'."))))
+}
+
+/// Extracts the API version of the H2O CRD to print. Possible values are enforced and the arg has a default.
+fn crd_api_version(print_args: &ArgMatches) -> CrdApiVersion {
+    match print_args.value_of("api_version") {
+        Some("v1beta1") => CrdApiVersion::V1beta1,
+        _ => CrdApiVersion::V1,
+    }
+}
+
+/// Validates the port the webhook server listens on to be within the <1,65535> range.
+///
+/// # Arguments
+/// `port` - User's input in String
+fn validate_port(port: String) -> Result<(), String> {
+    match port.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(()),
+        _ => Err(format!("Invalid port '{}'. Expected a number in the <1,65535> range.", port)),
+    }
+}
+
+/// The webhook service in the operator's `namespace`, with the CA bundle read from the `webhook_ca` file, if any.
+/// Referenced by the conversion webhook of the H2O CRD and by the validating webhook.
+///
+/// # Panics
+/// The operator exits with a non-zero code if the CA bundle can not be read.
+fn webhook_service(args: &ArgMatches, namespace: &str) -> WebhookService {
+    let ca_bundle: Option<Vec<u8>> = args.value_of("webhook_ca").map(|path| match std::fs::read(path) {
+        Ok(ca_bundle) => ca_bundle,
+        Err(error) => {
+            error!("Unable to read the webhook CA bundle '{}': {}", path, error);
+            std::process::exit(1);
+        }
+    });
+    let service: &str = args.value_of("webhook_service").unwrap_or(deployment::webhook::DEFAULT_SERVICE);
+    WebhookService::new(namespace.to_string(), service.to_string(), ca_bundle)
+}
+
+/// Operator-level defaults read from the `defaults` file, or `Defaults::default()` if not given.
+///
+/// # Panics
+/// The operator exits with a non-zero code if the file can not be read or parsed.
+fn defaults(args: &ArgMatches) -> Defaults {
+    let path: &str = match args.value_of("defaults") {
+        Some(path) => path,
+        None => return Defaults::default(),
+    };
+    let parsed: Result<Defaults, String> = std::fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|yaml| Defaults::from_yaml(&yaml).map_err(|error| error.to_string()));
+    match parsed {
+        Ok(defaults) => defaults,
+        Err(error) => {
+            error!("Unable to read the H2O defaults '{}': {}", path, error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Starts the webhook server in the background if a webhook certificate is given, see `webhook::serve`, and registers
+/// the admission webhooks behind the webhook service in the operator's `namespace`, which is returned. Without
+/// the webhook server, `H2O` resources are only served in the `v1beta` API version, invalid specifications are only
+/// reported by the controller and `defaults` are applied by the controller without being stored in the `H2O` resources.
+///
+/// # Panics
+/// The operator exits with a non-zero code if the certificate is invalid or the port can not be bound.
+async fn start_webhook_server(args: &ArgMatches<'_>, client: Client, namespace: &str, defaults: &Defaults) -> Option<WebhookService> {
+    let (cert_path, key_path): (&str, &str) = match (args.value_of("webhook_cert"), args.value_of("webhook_key")) {
+        (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+        _ => {
+            warn!("No webhook certificate given, webhooks are not served. H2O resources are only served in the 'h2o.ai/v1beta' API version.");
+            return None;
+        }
+    };
+    let service: WebhookService = webhook_service(args, namespace);
+    // The port is validated and has a default, it is therefore safe to unwrap.
+    let port: u16 = args.value_of("webhook_port").unwrap().parse().unwrap();
+    let server = async {
+        let acceptor = webhook::tls_acceptor(Path::new(cert_path), Path::new(key_path))?;
+        let listener = webhook::bind(port).await?;
+        Ok::<_, Error>(webhook::serve(listener, acceptor, Arc::new(defaults.clone())))
+    };
+    match server.await {
+        Ok(serve) => {
+            tokio::spawn(async {
+                if let Err(error) = serve.await {
+                    error!("Webhook server failed. Error:\n{}", error);
+                    std::process::exit(1);
+                }
+            });
+        }
+        Err(error) => {
+            error!("Unable to start the webhook server. Error:\n{}", error);
+            std::process::exit(1);
+        }
+    }
+    install_admission_webhooks(client, &service).await;
+    Some(service)
+}
+
+/// Creates or updates the `MutatingWebhookConfiguration` and `ValidatingWebhookConfiguration` of the operator,
+/// see `deployment::webhook::install`. If the admission webhooks can not be registered, invalid `H2O` resources
+/// are still rejected by the controller and defaults are still applied, only not stored in the resources.
+async fn install_admission_webhooks(client: Client, service: &WebhookService) {
+    match deployment::webhook::install(client, service).await {
+        Ok(()) => info!("Admission webhooks registered for H2O resources in '{}' namespace.", service.namespace),
+        Err(Error::KubeError(KubeError::Api(error_response))) if error_response.code == 403 => {
+            warn!("The operator is not allowed to register the admission webhooks, H2O resources are not defaulted and validated on admission: {}\n\
+            Grant the operator 'get', 'create' and 'patch' permissions on 'mutatingwebhookconfigurations' and 'validatingwebhookconfigurations' in the 'admissionregistration.k8s.io' API group.",
+                  error_response.message);
+        }
+        Err(error) => warn!("Unable to register the admission webhooks, H2O resources are not defaulted and validated on admission. Error:\n{}", error),
+    }
+}
+
+/// Makes sure an externally managed H2O CRD does not require a conversion webhook not served by the operator,
+/// see `crd_manifest::verify_conversion`.
+///
+/// # Panics
+/// The operator exits with a non-zero code if the CRD requires a conversion webhook not served, as clients
+/// using the `v1` API version would fail otherwise.
+async fn verify_crd_conversion(client: Client, webhook: Option<&WebhookService>) {
+    if let Err(error) = deployment::crd_manifest::verify_conversion(client, webhook).await {
+        error!("Unable to verify the H2O CRD. Error:\n{}", error);
+        std::process::exit(1);
+    }
+}
+
+/// Creates or upgrades the H2O CRD and waits until it is established, see `crd_manifest::install`.
+///
+/// # Panics
+/// The operator exits with a non-zero code if the CRD can not be installed, as no `H2O` resources can be
+/// served without it, or if the present CRD requires a conversion webhook not served by the operator.
+/// Missing permissions are reported with a hint on how to resolve them.
+async fn install_crd(client: Client, webhook: Option<&WebhookService>) {
+    match deployment::crd_manifest::install(client, webhook, CRD_ESTABLISHED_TIMEOUT).await {
+        Ok(CrdInstallation::Created) => info!("H2O CRD created."),
+        Ok(CrdInstallation::Upgraded(revision)) => info!("H2O CRD upgraded from revision {} to {}.", revision,
+                                                         deployment::crd_manifest::CRD_REVISION),
+        Ok(CrdInstallation::Reconfigured) => match webhook {
+            Some(webhook) => info!("Conversion webhook of the H2O CRD updated to service '{}/{}'.", webhook.namespace, webhook.service),
+            None => info!("Conversion webhook of the H2O CRD removed."),
+        },
+        Ok(CrdInstallation::UpToDate) => info!("H2O CRD is up to date."),
+        Ok(CrdInstallation::NewerPresent(revision)) => warn!("H2O CRD of a newer revision {} found, this operator generates revision {}. Consider upgrading the operator.",
+                                                             revision, deployment::crd_manifest::CRD_REVISION),
+        Err(Error::KubeError(KubeError::Api(error_response))) if error_response.code == 403 => {
+            error!("The operator is not allowed to create or upgrade the H2O CRD: {}\n\
+            Either grant the operator 'get', 'create' and 'patch' permissions on 'customresourcedefinitions' in the 'apiextensions.k8s.io' API group, \
+            or install the CRD by 'h2o-operator crd print | kubectl apply -f -' and start the operator with '--skip_crd_install'.",
+                   error_response.message);
+            std::process::exit(1);
+        }
+        Err(error) => {
+            error!("Unable to install the H2O CRD. Error:\n{}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
 async fn print_startup_diagnostics(client: &Client, namespace: &str) {
     info!("Kubeconfig found. Operator is running in '{}' namespace.", namespace);
     match client.apiserver_version().await {
@@ -86,3 +339,65 @@ fn initialize_logging() {
         .init()
         .unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use deployment::crd_manifest::CrdApiVersion;
+    use deployment::defaults::Defaults;
+
+    #[test]
+    fn test_crd_manifests_up_to_date() {
+        let manifests = [
+            (CrdApiVersion::V1, "v1", "crd/h2os.h2o.ai.crd.yaml"),
+            (CrdApiVersion::V1beta1, "v1beta1", "bundle/manifests/h2os.h2o.ai.crd.yaml"),
+        ];
+        for (api_version, api_version_arg, path) in manifests.iter() {
+            let generated: String = deployment::crd_manifest::h2o_crd_yaml(*api_version, None).unwrap();
+            let checked_in: String = std::fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join(path)).unwrap();
+            assert!(generated == checked_in,
+                    "'{}' does not match the H2O resource types. Regenerate it by 'cargo run -p h2o-operator -- crd print --api_version {} > operator/{}'.",
+                    path, api_version_arg, path);
+        }
+    }
+
+    #[test]
+    fn test_skip_crd_install() {
+        assert!(!super::build_app().get_matches_from(vec!["h2o-operator"]).is_present("skip_crd_install"));
+        assert!(super::build_app().get_matches_from(vec!["h2o-operator", "--skip_crd_install"]).is_present("skip_crd_install"));
+    }
+
+    #[test]
+    fn test_webhook_arguments() {
+        let matches = super::build_app().get_matches_from(vec!["h2o-operator", "--webhook_cert", "tls.crt", "--webhook_key", "tls.key"]);
+        assert_eq!(Some(super::webhook::DEFAULT_WEBHOOK_PORT), matches.value_of("webhook_port"));
+        let webhook = super::webhook_service(&matches, "h2o");
+        assert_eq!("h2o", webhook.namespace);
+        assert_eq!("h2o-operator", webhook.service);
+        assert!(webhook.ca_bundle.is_none());
+        assert!(super::build_app().get_matches_from_safe(vec!["h2o-operator", "--webhook_cert", "tls.crt"]).is_err());
+        assert!(super::build_app().get_matches_from_safe(vec!["h2o-operator", "--webhook_port", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_defaults_argument() {
+        assert_eq!(Defaults::default(), super::defaults(&super::build_app().get_matches_from(vec!["h2o-operator"])));
+        let path = std::env::temp_dir().join("h2o-operator-test-defaults.yaml");
+        std::fs::write(&path, "memoryPercentage: 80\nlabels:\n  cost-center: research\n").unwrap();
+        let matches = super::build_app().get_matches_from(vec!["h2o-operator", "--defaults", path.to_str().unwrap()]);
+        let defaults: Defaults = super::defaults(&matches);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(80, defaults.memory_percentage);
+        assert_eq!("research", defaults.labels["cost-center"]);
+    }
+
+    #[test]
+    fn test_crd_print_arguments() {
+        let matches = super::build_app().get_matches_from(vec!["h2o-operator", "crd", "print", "--api_version", "v1beta1"]);
+        let print_args = matches.subcommand_matches("crd").unwrap().subcommand_matches("print").unwrap();
+        assert_eq!(CrdApiVersion::V1beta1, super::crd_api_version(print_args));
+        assert_eq!(Some("h2o"), super::build_app().get_matches_from(vec!["h2o-operator", "crd", "print", "--conversion_webhook", "h2o"])
+            .subcommand_matches("crd").unwrap().subcommand_matches("print").unwrap().value_of("conversion_webhook"));
+    }
+}
@@ -0,0 +1,225 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use hyper::{Body, Method, Request, Response, StatusCode};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use log::{debug, info, warn};
+use native_tls::Identity;
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::{PKey, Private};
+use openssl::x509::X509;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::net::TcpListener;
+use tokio_native_tls::TlsAcceptor;
+
+use deployment::conversion::CONVERSION_PATH;
+use deployment::defaults::Defaults;
+use deployment::webhook::{MUTATION_PATH, VALIDATION_PATH};
+use deployment::Error;
+
+/// Port the webhook server listens on, unless specified otherwise. The webhook service maps port 443 to it.
+/// Kept as the default value of the operator's `webhook_port` argument.
+pub const DEFAULT_WEBHOOK_PORT: &str = "8443";
+
+/// Creates a TLS acceptor from a PEM-encoded certificate and private key, e.g. the `tls.crt` and `tls.key` keys
+/// of a secret issued by cert-manager. Certificates following the first one in `cert_path` are treated as its chain.
+///
+/// # Arguments
+/// `cert_path` - Path to the PEM-encoded certificate of the webhook server, optionally followed by its chain
+/// `key_path` - Path to the PEM-encoded private key of the certificate
+pub fn tls_acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor, Error> {
+    let invalid = |reason: String| Error::UserError(format!("Invalid webhook certificate '{}' or key '{}': {}",
+                                                            cert_path.display(), key_path.display(), reason));
+    let cert_pem: Vec<u8> = std::fs::read(cert_path).map_err(|error| invalid(error.to_string()))?;
+    let key_pem: Vec<u8> = std::fs::read(key_path).map_err(|error| invalid(error.to_string()))?;
+
+    let mut certificates: Vec<X509> = X509::stack_from_pem(&cert_pem).map_err(|error| invalid(error.to_string()))?;
+    if certificates.is_empty() {
+        return Err(invalid("no certificate found".to_string()));
+    }
+    let certificate: X509 = certificates.remove(0);
+    let key: PKey<Private> = PKey::private_key_from_pem(&key_pem).map_err(|error| invalid(error.to_string()))?;
+    let mut chain = openssl::stack::Stack::new().map_err(|error| invalid(error.to_string()))?;
+    for chained in certificates {
+        chain.push(chained).map_err(|error| invalid(error.to_string()))?;
+    }
+
+    // Native TLS only accepts identities in PKCS#12 archives
+    let archive: Pkcs12 = Pkcs12::builder()
+        .name("h2o-operator-webhook")
+        .pkey(&key)
+        .cert(&certificate)
+        .ca(chain)
+        .build2("")
+        .map_err(|error| invalid(error.to_string()))?;
+    let der: Vec<u8> = archive.to_der().map_err(|error| invalid(error.to_string()))?;
+    let identity: Identity = Identity::from_pkcs12(&der, "").map_err(|error| invalid(error.to_string()))?;
+    let acceptor = native_tls::TlsAcceptor::new(identity).map_err(|error| invalid(error.to_string()))?;
+    Ok(TlsAcceptor::from(acceptor))
+}
+
+/// Binds the webhook server to given `port` on all interfaces. Bound before the server is started, so the operator
+/// may refuse to start if the port is taken.
+///
+/// # Arguments
+/// `port` - Port to listen on
+pub async fn bind(port: u16) -> Result<TcpListener, Error> {
+    TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port))).await
+        .map_err(|error| Error::UserError(format!("Unable to bind the webhook server to port {}: {}", port, error)))
+}
+
+/// Serves webhooks called by the Kubernetes API server over HTTPS. Unless there is an error accepting connections,
+/// this function never returns. Each connection is handled by a separate task, failed TLS handshakes and connections
+/// are logged and dropped.
+///
+/// Served webhooks:
+/// - `POST /convert` - conversion of `H2O` resources between API versions, see `deployment::conversion`
+/// - `POST /mutate` - filling operator-level `defaults` into `H2O` resources on admission, see `deployment::webhook::mutate`
+/// - `POST /validate` - validation of `H2O` resources on admission, see `deployment::webhook::validate`
+///
+/// # Arguments
+/// `listener` - Listener obtained from `bind`
+/// `acceptor` - TLS acceptor obtained from `tls_acceptor`
+/// `defaults` - Operator-level defaults filled into `H2O` resources
+pub async fn serve(listener: TcpListener, acceptor: TlsAcceptor, defaults: Arc<Defaults>) -> Result<(), Error> {
+    info!("Serving webhooks on {}.", listener.local_addr()
+        .map(|address| address.to_string())
+        .unwrap_or_default());
+    loop {
+        let (stream, peer) = listener.accept().await
+            .map_err(|error| Error::UserError(format!("Webhook server unable to accept connections: {}", error)))?;
+        let acceptor: TlsAcceptor = acceptor.clone();
+        let defaults: Arc<Defaults> = defaults.clone();
+        tokio::spawn(async move {
+            let tls_stream = match acceptor.accept(stream).await {
+                Ok(tls_stream) => tls_stream,
+                Err(error) => {
+                    warn!("TLS handshake with {} failed: {}", peer, error);
+                    return;
+                }
+            };
+            let service = service_fn(move |request| handle(request, defaults.clone()));
+            if let Err(error) = Http::new().serve_connection(tls_stream, service).await {
+                debug!("Webhook connection with {} failed: {}", peer, error);
+            }
+        });
+    }
+}
+
+/// Routes a single webhook request. Unknown paths are answered with `404 Not Found`, malformed reviews
+/// with `400 Bad Request`.
+async fn handle(request: Request<Body>, defaults: Arc<Defaults>) -> Result<Response<Body>, Infallible> {
+    let response: Response<Body> = match (request.method(), request.uri().path()) {
+        (&Method::POST, CONVERSION_PATH) => {
+            review(request, deployment::conversion::review).await
+        }
+        (&Method::POST, MUTATION_PATH) => {
+            review(request, |review| deployment::webhook::mutate(review, &defaults)).await
+        }
+        (&Method::POST, VALIDATION_PATH) => {
+            review(request, deployment::webhook::validate).await
+        }
+        _ => status_response(StatusCode::NOT_FOUND, format!("No webhook served on '{}'.", request.uri().path())),
+    };
+    Ok(response)
+}
+
+/// Reads a review of type `T` from the request body, answers it by `reviewer` and serializes the answered review
+/// as the response body.
+async fn review<T: DeserializeOwned + Serialize>(request: Request<Body>, reviewer: impl FnOnce(T) -> T) -> Response<Body> {
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(error) => return status_response(StatusCode::BAD_REQUEST, error.to_string()),
+    };
+    let review: T = match serde_json::from_slice(&body) {
+        Ok(review) => review,
+        Err(error) => return status_response(StatusCode::BAD_REQUEST, format!("Malformed review: {}", error)),
+    };
+    match serde_json::to_vec(&reviewer(review)) {
+        Ok(answer) => Response::builder()
+            .header("Content-Type", "application/json")
+            .body(Body::from(answer))
+            .unwrap_or_else(|_| Response::new(Body::empty())),
+        Err(error) => status_response(StatusCode::INTERNAL_SERVER_ERROR, error.to_string()),
+    }
+}
+
+/// Plain text response with given `status`.
+fn status_response(status: StatusCode, message: String) -> Response<Body> {
+    let mut response: Response<Body> = Response::new(Body::from(message));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hyper::{Body, Request, Response, StatusCode};
+    use serde_json::{json, Value};
+
+    use deployment::defaults::Defaults;
+
+    #[tokio::test]
+    async fn test_handle() {
+        let defaults: Arc<Defaults> = Arc::new(Defaults::from_yaml("version: latest").unwrap());
+        let review: Value = json!({
+            "apiVersion": "apiextensions.k8s.io/v1",
+            "kind": "ConversionReview",
+            "request": {
+                "uid": "42",
+                "desiredAPIVersion": "h2o.ai/v1",
+                "objects": [{
+                    "apiVersion": "h2o.ai/v1beta",
+                    "kind": "H2O",
+                    "metadata": { "name": "h2o-test" },
+                    "spec": { "nodes": 1, "version": "latest", "resources": { "cpu": 1, "memory": "1Gi" } }
+                }]
+            }
+        });
+        let request: Request<Body> = Request::post("/convert").body(Body::from(review.to_string())).unwrap();
+        let response: Response<Body> = super::handle(request, defaults.clone()).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let reviewed: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!("latest", reviewed["response"]["convertedObjects"][0]["spec"]["image"]["version"]);
+
+        let malformed: Request<Body> = Request::post("/convert").body(Body::from("{}")).unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, super::handle(malformed, defaults.clone()).await.unwrap().status());
+
+        let mut admission: Value = json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "43",
+                "operation": "CREATE",
+                "object": {
+                    "apiVersion": "h2o.ai/v1",
+                    "kind": "H2O",
+                    "metadata": { "name": "h2o-test" },
+                    "spec": { "nodes": 0, "image": { "version": "latest" }, "resources": { "cpu": 1, "memory": "1Gi" } }
+                }
+            }
+        });
+        let request: Request<Body> = Request::post("/validate").body(Body::from(admission.to_string())).unwrap();
+        let response: Response<Body> = super::handle(request, defaults.clone()).await.unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let reviewed: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(false, reviewed["response"]["allowed"]);
+        assert_eq!("43", reviewed["response"]["uid"]);
+
+        admission["request"]["object"]["spec"]["image"] = json!({});
+        let request: Request<Body> = Request::post("/mutate").body(Body::from(admission.to_string())).unwrap();
+        let body = hyper::body::to_bytes(super::handle(request, defaults.clone()).await.unwrap().into_body()).await.unwrap();
+        let reviewed: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(true, reviewed["response"]["allowed"]);
+        assert_eq!("JSONPatch", reviewed["response"]["patchType"]);
+
+        let unknown: Request<Body> = Request::get("/metrics").body(Body::empty()).unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, super::handle(unknown, defaults.clone()).await.unwrap().status());
+    }
+}
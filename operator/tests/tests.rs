@@ -6,6 +6,7 @@ use std::path::PathBuf;
 use std::process::{Child, Command};
 
 use futures::StreamExt;
+use k8s_openapi::api::apps::v1::StatefulSet;
 use k8s_openapi::api::core::v1::{Pod, Service};
 use kube::{Api, Client, Error};
 use kube::api::{DeleteParams, ListParams, Meta, PostParams, WatchEvent};
@@ -41,6 +42,15 @@ async fn test_operator_deploy_undeploy() {
     let service_api: Api<Service> = Api::namespaced(client.clone(), &namespace);
     let service: Service = service_api.get(h2o_name).await.unwrap();
     assert!(service.spec.unwrap().cluster_ip.unwrap().eq("None")); // The service created must be a headless service - thus no cluster ip
+    assert_eq!("H2O", service.metadata.owner_references.unwrap()[0].kind);
+
+    // Sub-resources must be owned by the H2O resource to be garbage collected
+    let statefulset_api: Api<StatefulSet> = Api::namespaced(client.clone(), &namespace);
+    let statefulset: StatefulSet = statefulset_api.get(h2o_name).await.unwrap();
+    let owner_references = statefulset.metadata.owner_references.unwrap();
+    assert_eq!(1, owner_references.len());
+    assert_eq!(h2o_name, owner_references[0].name);
+    assert_eq!(Some(true), owner_references[0].controller);
 
     h2o_api.delete(h2o_name, &DeleteParams::default()).await.unwrap();
 